@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE post ADD COLUMN plain_text TEXT NOT NULL DEFAULT '';
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS post_chunk_model_idx;
+DROP INDEX IF EXISTS post_chunk_post_id_idx;
+DROP TABLE IF EXISTS post_chunk;
\ No newline at end of file
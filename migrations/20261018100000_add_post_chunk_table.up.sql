@@ -0,0 +1,13 @@
+-- Add up migration script here
+CREATE EXTENSION IF NOT EXISTS vector;
+-- 不同嵌入模型的向量维度不同, 因此不限定维度, 并通过model列区分
+CREATE TABLE post_chunk (
+    id SERIAL PRIMARY KEY,
+    post_id INTEGER NOT NULL REFERENCES post(id) ON DELETE CASCADE,
+    chunk_index INTEGER NOT NULL,
+    model VARCHAR(255) NOT NULL,
+    content TEXT NOT NULL,
+    embedding vector NOT NULL
+);
+CREATE INDEX post_chunk_post_id_idx ON post_chunk (post_id);
+CREATE INDEX post_chunk_model_idx ON post_chunk (model);
\ No newline at end of file
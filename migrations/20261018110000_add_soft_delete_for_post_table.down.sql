@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE comment DROP CONSTRAINT IF EXISTS comment_post_id_fkey;
+DROP INDEX IF EXISTS post_deleted_at_idx;
+ALTER TABLE post DROP COLUMN IF EXISTS deleted_at;
\ No newline at end of file
@@ -0,0 +1,7 @@
+-- Add up migration script here
+ALTER TABLE post ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
+CREATE INDEX post_deleted_at_idx ON post (deleted_at);
+-- 删除文章时一并删除评论, 先清理已经没有对应文章的评论
+DELETE FROM comment WHERE post_id NOT IN (SELECT id FROM post);
+ALTER TABLE comment ADD CONSTRAINT comment_post_id_fkey
+    FOREIGN KEY (post_id) REFERENCES post(id) ON DELETE CASCADE;
\ No newline at end of file
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS post_slug_idx;
+ALTER TABLE post DROP COLUMN IF EXISTS slug;
\ No newline at end of file
@@ -0,0 +1,6 @@
+-- Add up migration script here
+ALTER TABLE post ADD COLUMN slug VARCHAR(255);
+-- 已有的文章无法在数据库中转写拼音, 先使用id作为slug, 之后可以通过更新接口修改
+UPDATE post SET slug = 'post-' || id WHERE slug IS NULL;
+ALTER TABLE post ALTER COLUMN slug SET NOT NULL;
+CREATE UNIQUE INDEX post_slug_idx ON post (slug);
\ No newline at end of file
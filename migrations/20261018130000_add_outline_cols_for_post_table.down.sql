@@ -0,0 +1,6 @@
+-- Add down migration script here
+ALTER TABLE post
+    DROP COLUMN reading_time,
+    DROP COLUMN char_count,
+    DROP COLUMN word_count,
+    DROP COLUMN toc;
\ No newline at end of file
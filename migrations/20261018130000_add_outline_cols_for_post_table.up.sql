@@ -0,0 +1,6 @@
+-- Add up/down migration script here
+ALTER TABLE post
+    ADD COLUMN toc JSONB NOT NULL DEFAULT '[]'::jsonb,
+    ADD COLUMN word_count INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN char_count INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN reading_time INTEGER NOT NULL DEFAULT 0;
\ No newline at end of file
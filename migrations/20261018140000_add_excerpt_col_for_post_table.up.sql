@@ -0,0 +1,2 @@
+-- Add up/down migration script here
+ALTER TABLE post ADD COLUMN excerpt TEXT NOT NULL DEFAULT '';
\ No newline at end of file
@@ -0,0 +1,8 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS post_scheduled_idx;
+
+ALTER TABLE post
+    DROP COLUMN publish_at,
+    DROP COLUMN status;
+
+DROP TYPE post_status;
\ No newline at end of file
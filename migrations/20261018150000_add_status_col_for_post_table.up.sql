@@ -0,0 +1,8 @@
+-- Add up/down migration script here
+CREATE TYPE post_status AS ENUM ('draft', 'scheduled', 'published', 'unlisted', 'private');
+
+ALTER TABLE post
+    ADD COLUMN status post_status NOT NULL DEFAULT 'published',
+    ADD COLUMN publish_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX post_scheduled_idx ON post (publish_at) WHERE status = 'scheduled';
\ No newline at end of file
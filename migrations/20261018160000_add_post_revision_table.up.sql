@@ -0,0 +1,15 @@
+-- Add up migration script here
+CREATE TABLE post_revision (
+    id SERIAL PRIMARY KEY,
+    post_id INTEGER NOT NULL REFERENCES post(id) ON DELETE CASCADE,
+    revision INTEGER NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    slug VARCHAR(255) NOT NULL,
+    tags JSONB NOT NULL DEFAULT '[]'::jsonb,
+    content TEXT NOT NULL,
+    content_hash CHAR(64) NOT NULL,
+    author VARCHAR(255),
+    message TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (post_id, revision)
+);
\ No newline at end of file
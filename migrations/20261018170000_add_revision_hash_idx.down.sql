@@ -0,0 +1,2 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS post_revision_hash_idx;
\ No newline at end of file
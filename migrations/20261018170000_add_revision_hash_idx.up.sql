@@ -0,0 +1,2 @@
+-- Add up migration script here
+CREATE INDEX post_revision_hash_idx ON post_revision (content_hash);
\ No newline at end of file
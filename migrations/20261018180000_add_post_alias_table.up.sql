@@ -0,0 +1,7 @@
+-- Add up migration script here
+CREATE TABLE post_alias (
+    path VARCHAR(1024) PRIMARY KEY,
+    post_id INTEGER NOT NULL REFERENCES post(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+CREATE INDEX post_alias_post_id_idx ON post_alias (post_id);
\ No newline at end of file
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS post_asset;
+DROP TABLE IF EXISTS asset;
\ No newline at end of file
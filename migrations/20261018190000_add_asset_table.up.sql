@@ -0,0 +1,18 @@
+-- Add up migration script here
+CREATE TABLE asset (
+    id SERIAL PRIMARY KEY,
+    -- 文件内容的sha256, 相同内容的文件只保存一份
+    hash CHAR(64) NOT NULL UNIQUE,
+    mime VARCHAR(255) NOT NULL,
+    size BIGINT NOT NULL,
+    -- 第一次上传时的文件名
+    filename VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+-- 文章正文中引用的资源, 没有被任何文章引用的资源会被清理
+CREATE TABLE post_asset (
+    post_id INTEGER NOT NULL REFERENCES post(id) ON DELETE CASCADE,
+    asset_id INTEGER NOT NULL REFERENCES asset(id) ON DELETE CASCADE,
+    PRIMARY KEY (post_id, asset_id)
+);
+CREATE INDEX post_asset_asset_id_idx ON post_asset (asset_id);
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE asset
+    DROP COLUMN width,
+    DROP COLUMN height;
\ No newline at end of file
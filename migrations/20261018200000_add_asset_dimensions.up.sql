@@ -0,0 +1,4 @@
+-- Add up migration script here
+ALTER TABLE asset
+    ADD COLUMN width INTEGER,
+    ADD COLUMN height INTEGER;
\ No newline at end of file
@@ -0,0 +1,11 @@
+-- Add down migration script here
+ALTER TABLE comment DROP COLUMN IF EXISTS user_id;
+DROP INDEX IF EXISTS post_author_id_idx;
+ALTER TABLE post DROP COLUMN IF EXISTS author_id;
+DELETE FROM "user" WHERE role <> 'admin';
+ALTER TABLE "user" DROP COLUMN role;
+ALTER TABLE "user" RENAME CONSTRAINT user_username_key TO admin_username_key;
+ALTER TABLE "user" RENAME CONSTRAINT user_pkey TO admin_pkey;
+ALTER SEQUENCE user_id_seq RENAME TO admin_id_seq;
+ALTER TABLE "user" RENAME TO admin;
+DROP TYPE IF EXISTS user_role;
\ No newline at end of file
@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TYPE user_role AS ENUM ('commenter', 'author', 'editor', 'admin');
+ALTER TABLE admin RENAME TO "user";
+ALTER SEQUENCE admin_id_seq RENAME TO user_id_seq;
+ALTER TABLE "user" RENAME CONSTRAINT admin_pkey TO user_pkey;
+ALTER TABLE "user" RENAME CONSTRAINT admin_username_key TO user_username_key;
+-- 已有的账号都是管理员
+ALTER TABLE "user" ADD COLUMN role user_role NOT NULL DEFAULT 'admin';
+ALTER TABLE "user" ALTER COLUMN role SET DEFAULT 'commenter';
+ALTER TABLE post ADD COLUMN author_id INTEGER REFERENCES "user"(id) ON DELETE SET NULL;
+CREATE INDEX post_author_id_idx ON post (author_id);
+ALTER TABLE comment ADD COLUMN user_id INTEGER REFERENCES "user"(id) ON DELETE SET NULL;
\ No newline at end of file
@@ -0,0 +1,16 @@
+-- Add up migration script here
+CREATE TABLE user_session (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    -- 当前和上一个刷新令牌的sha256, 上一个令牌再次被使用说明令牌已经泄露
+    refresh_hash VARCHAR(64) NOT NULL UNIQUE,
+    previous_hash VARCHAR(64),
+    user_agent VARCHAR(512),
+    ip VARCHAR(64),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    revoked_at TIMESTAMP WITH TIME ZONE
+);
+CREATE INDEX user_session_user_id_idx ON user_session (user_id);
+CREATE INDEX user_session_previous_hash_idx ON user_session (previous_hash);
\ No newline at end of file
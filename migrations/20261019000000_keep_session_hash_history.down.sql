@@ -0,0 +1,6 @@
+-- Add down migration script here
+ALTER TABLE user_session ADD COLUMN previous_hash VARCHAR(64);
+UPDATE user_session SET previous_hash = previous_hashes[1];
+DROP INDEX user_session_previous_hashes_idx;
+ALTER TABLE user_session DROP COLUMN previous_hashes;
+CREATE INDEX user_session_previous_hash_idx ON user_session (previous_hash);
\ No newline at end of file
@@ -0,0 +1,7 @@
+-- Add up migration script here
+-- 保存最近几个换掉的刷新令牌的哈希, 它们中任何一个再次被使用都说明令牌已经泄露
+ALTER TABLE user_session ADD COLUMN previous_hashes VARCHAR(64)[] NOT NULL DEFAULT '{}';
+UPDATE user_session SET previous_hashes = ARRAY[previous_hash] WHERE previous_hash IS NOT NULL;
+DROP INDEX user_session_previous_hash_idx;
+ALTER TABLE user_session DROP COLUMN previous_hash;
+CREATE INDEX user_session_previous_hashes_idx ON user_session USING GIN (previous_hashes);
\ No newline at end of file
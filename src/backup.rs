@@ -0,0 +1,237 @@
+use crate::repositories::asset::Asset;
+use crate::repositories::backup::{PostAlias, PostState};
+use crate::repositories::comment::Comment;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::path::Path;
+use tokio::task;
+
+/// 备份格式的版本, 格式不兼容地改变时加一
+pub const BACKUP_VERSION: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+const POSTS: &str = "posts.json";
+const COMMENTS: &str = "comments.json";
+const ALIASES: &str = "aliases.json";
+const TAGS: &str = "tags.json";
+const ASSETS: &str = "assets.json";
+const POST_DIR: &str = "posts/";
+const ASSET_DIR: &str = "assets/";
+
+#[derive(Debug, thiserror::Error)]
+pub enum BackupError {
+    #[error("IO Error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Json Error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid Backup: {0}")]
+    Invalid(String),
+}
+
+/// 备份的说明, 恢复前用来判断备份是否兼容
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    /// 备份格式的版本, 见[`BACKUP_VERSION`]
+    pub version: u32,
+    /// 导出时数据库已经执行的最新一次迁移的版本号
+    pub schema_version: i64,
+    pub exported_at: DateTime<Utc>,
+    pub post_count: usize,
+    pub comment_count: usize,
+    #[serde(default)]
+    pub asset_count: usize,
+}
+
+/// 备份中的一篇文章, 内容是带有完整front matter的markdown
+pub struct BackupPost {
+    pub id: i32,
+    pub content: Vec<u8>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupComment {
+    pub id: i32,
+    pub post_id: i32,
+    pub parent_id: Option<i32>,
+    pub author: String,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 备份中的一个资源, 文件以id命名
+pub struct BackupAsset {
+    pub asset: Asset,
+    pub content: Bytes,
+}
+
+impl From<Comment> for BackupComment {
+    fn from(value: Comment) -> Self {
+        BackupComment {
+            id: value.id,
+            post_id: value.post_id,
+            parent_id: value.parent_id,
+            author: value.author,
+            content: value.content,
+            created_at: value.created_at,
+        }
+    }
+}
+
+/// 整个站点的备份, 打包为tar.gz:
+/// ```text
+/// manifest.json   备份的说明
+/// posts/{id}.md   文章, 元数据写在front matter中
+/// posts.json      访问计数, 是否在回收站中等front matter中没有的状态
+/// comments.json   所有评论
+/// aliases.json    文章的旧地址
+/// tags.json       所有标签, 只用于查看, 恢复时以文章的front matter为准
+/// assets.json     上传的图片和附件的信息
+/// assets/{id}     资源文件
+/// ```
+/// 文章的历史版本不包含在备份中
+pub struct Backup {
+    pub manifest: Manifest,
+    pub posts: Vec<BackupPost>,
+    pub states: Vec<PostState>,
+    pub comments: Vec<BackupComment>,
+    pub aliases: Vec<PostAlias>,
+    pub tags: Vec<String>,
+    pub assets: Vec<BackupAsset>,
+}
+
+impl Backup {
+    /// 打包为tar.gz
+    pub async fn write(self) -> Result<Vec<u8>, BackupError> {
+        task::spawn_blocking(move || self.write_blocking())
+            .await
+            .map_err(|e| BackupError::Invalid(e.to_string()))?
+    }
+
+    /// 读取tar.gz格式的备份, 版本不兼容时返回错误
+    pub async fn read(data: Vec<u8>) -> Result<Self, BackupError> {
+        task::spawn_blocking(move || Self::read_blocking(&data))
+            .await
+            .map_err(|e| BackupError::Invalid(e.to_string()))?
+    }
+
+    fn write_blocking(&self) -> Result<Vec<u8>, BackupError> {
+        let mut archive = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        let mtime = self.manifest.exported_at.timestamp().max(0) as u64;
+        let mut append = |path: &str, data: &[u8]| {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(mtime);
+            archive.append_data(&mut header, path, data)
+        };
+
+        append(MANIFEST, &serde_json::to_vec_pretty(&self.manifest)?)?;
+        for post in self.posts.iter() {
+            append(&format!("{}{}.md", POST_DIR, post.id), &post.content)?;
+        }
+        append(POSTS, &serde_json::to_vec_pretty(&self.states)?)?;
+        append(COMMENTS, &serde_json::to_vec_pretty(&self.comments)?)?;
+        append(ALIASES, &serde_json::to_vec_pretty(&self.aliases)?)?;
+        append(TAGS, &serde_json::to_vec_pretty(&self.tags)?)?;
+        let assets: Vec<&Asset> = self.assets.iter().map(|asset| &asset.asset).collect();
+        append(ASSETS, &serde_json::to_vec_pretty(&assets)?)?;
+        for asset in self.assets.iter() {
+            append(&format!("{}{}", ASSET_DIR, asset.asset.id), &asset.content)?;
+        }
+
+        Ok(archive.into_inner()?.finish()?)
+    }
+
+    fn read_blocking(data: &[u8]) -> Result<Self, BackupError> {
+        let mut manifest: Option<Manifest> = None;
+        let mut posts = Vec::new();
+        let mut states = Vec::new();
+        let mut comments = Vec::new();
+        let mut aliases = Vec::new();
+        let mut tags = Vec::new();
+        let mut assets: Vec<Asset> = Vec::new();
+        let mut asset_files: HashMap<i32, Vec<u8>> = HashMap::new();
+
+        let mut archive = tar::Archive::new(GzDecoder::new(data));
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let path = entry.path()?.to_string_lossy().into_owned();
+            let path = path.trim_start_matches("./").to_string();
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content)?;
+            match path.as_str() {
+                MANIFEST => manifest = Some(Self::parse_json(&path, &content)?),
+                POSTS => states = Self::parse_json(&path, &content)?,
+                COMMENTS => comments = Self::parse_json(&path, &content)?,
+                ALIASES => aliases = Self::parse_json(&path, &content)?,
+                TAGS => tags = Self::parse_json(&path, &content)?,
+                ASSETS => assets = Self::parse_json(&path, &content)?,
+                _ if path.starts_with(ASSET_DIR) => {
+                    match path[ASSET_DIR.len()..].parse() {
+                        Ok(id) => asset_files.insert(id, content),
+                        Err(_) => {
+                            return Err(BackupError::Invalid(format!("无法识别的文件`{}`", path)));
+                        }
+                    };
+                }
+                _ => {
+                    let id = path
+                        .strip_prefix(POST_DIR)
+                        .and_then(|name| Path::new(name).file_stem()?.to_str()?.parse().ok())
+                        .filter(|_| path.ends_with(".md"));
+                    match id {
+                        Some(id) => posts.push(BackupPost { id, content }),
+                        None => {
+                            return Err(BackupError::Invalid(format!("无法识别的文件`{}`", path)));
+                        }
+                    }
+                }
+            }
+        }
+
+        let Some(manifest) = manifest else {
+            return Err(BackupError::Invalid(format!("缺少`{}`", MANIFEST)));
+        };
+        if manifest.version != BACKUP_VERSION {
+            return Err(BackupError::Invalid(format!(
+                "不支持的备份版本{}, 当前版本为{}",
+                manifest.version, BACKUP_VERSION
+            )));
+        }
+        posts.sort_by_key(|post| post.id);
+        let assets = assets
+            .into_iter()
+            .map(|asset| match asset_files.remove(&asset.id) {
+                Some(content) => Ok(BackupAsset {
+                    asset,
+                    content: content.into(),
+                }),
+                None => Err(BackupError::Invalid(format!("缺少资源{}的文件", asset.id))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Backup {
+            manifest,
+            posts,
+            states,
+            comments,
+            aliases,
+            tags,
+            assets,
+        })
+    }
+
+    fn parse_json<T: DeserializeOwned>(path: &str, content: &[u8]) -> Result<T, BackupError> {
+        serde_json::from_slice(content)
+            .map_err(|e| BackupError::Invalid(format!("无法解析`{}`: {}", path, e)))
+    }
+}
@@ -0,0 +1,395 @@
+use crate::backup::Backup;
+use crate::config::AppConfig;
+use crate::import::{ImportFormat, read_path};
+use crate::models::import::ImportOutcome;
+use crate::models::post::Repair;
+use crate::repositories::user::Role;
+use crate::serve;
+use crate::service::Principal;
+use crate::state::AppState;
+use clap::{Parser, Subcommand};
+use std::io;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(version, about = "博客后端")]
+struct Cli {
+    /// 没有指定时启动http服务
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 启动http服务
+    Serve,
+    /// 检查数据库中的文章和文章文件是否一一对应
+    Check {
+        /// 同时修复发现的问题, 修复时应当先停止http服务
+        #[arg(long)]
+        repair: bool,
+    },
+    /// 批量导入目录, WXR文件或zip, tar压缩包中的文章
+    Import {
+        /// 目录, 文件或压缩包的路径
+        path: PathBuf,
+        /// 文章的来源: markdown, hexo, hugo, jekyll, wordpress
+        #[arg(long, default_value = "markdown")]
+        format: ImportFormat,
+    },
+    /// 导出整个站点的备份
+    Export {
+        /// 备份文件的路径, tar.gz格式
+        path: PathBuf,
+    },
+    /// 从备份恢复到空的数据库和文章存储
+    Restore {
+        /// 备份文件的路径
+        path: PathBuf,
+        /// 只检查备份能否恢复, 不写入任何内容
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// 删除上传超过保护期且没有被任何文章引用的资源
+    CleanAssets {
+        /// 只列出将要删除的资源, 不删除
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// 为升级之前上传的图片补充尺寸并去除EXIF等元数据, 应当先停止http服务
+    BackfillAssets,
+    /// 由文章文件重新计算搜索索引, 目录, 字数和摘要, 用于补全升级之前保存的文章
+    Reindex,
+    /// 添加用户或修改已有用户的密码和角色, 密码从标准输入读取
+    #[command(alias = "set-admin")]
+    SetUser {
+        username: String,
+        /// 用户的角色: admin, editor, author, commenter
+        #[arg(long, default_value = "admin")]
+        role: Role,
+    },
+}
+
+pub async fn run() -> ExitCode {
+    match Cli::parse().command.unwrap_or(Command::Serve) {
+        Command::Serve => match serve::serve().await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("配置错误: {}", e);
+                ExitCode::FAILURE
+            }
+        },
+        Command::Check { repair } => check(repair).await,
+        Command::Import { path, format } => import(path, format).await,
+        Command::Export { path } => export(path).await,
+        Command::Restore { path, dry_run } => restore(path, dry_run).await,
+        Command::CleanAssets { dry_run } => clean_assets(dry_run).await,
+        Command::BackfillAssets => backfill_assets().await,
+        Command::Reindex => reindex().await,
+        Command::SetUser { username, role } => set_user(username, role).await,
+    }
+}
+
+/// 输出检查结果, 存在问题且没有全部修复时返回失败
+async fn check(repair: bool) -> ExitCode {
+    let config = AppConfig::new();
+    serve::init_tracing(&config);
+    let state = AppState::new(config).await;
+
+    let report = match state.post_service.check_consistency(repair).await {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("一致性检查失败: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if report.is_consistent() {
+        println!("文章和文件一致");
+        return ExitCode::SUCCESS;
+    }
+
+    let mut fixed = repair;
+    for missing in report.missing_files.iter() {
+        println!("文章 {} `{}` 没有文件", missing.id, missing.title);
+        if let Some(repair) = &missing.repair {
+            println!("    {}", repair);
+            fixed &= !matches!(repair, Repair::Failed { .. });
+        }
+    }
+    for orphan in report.orphan_files.iter() {
+        println!("文件 {}.md 没有对应的文章", orphan.id);
+        if let Some(repair) = &orphan.repair {
+            println!("    {}", repair);
+            fixed &= !matches!(repair, Repair::Failed { .. });
+        }
+    }
+    if fixed {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// 输出每篇文章的导入结果, 有文章导入失败时返回失败
+async fn import(path: PathBuf, format: ImportFormat) -> ExitCode {
+    let config = AppConfig::new();
+    serve::init_tracing(&config);
+
+    let files = match read_path(path.clone(), format).await {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("读取`{}`失败: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let state = AppState::new(config).await;
+    let report = match state
+        .post_service
+        .import(&Principal::system(), files, format)
+        .await
+    {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("导入失败: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for file in report.files.iter() {
+        match &file.outcome {
+            ImportOutcome::Imported {
+                id,
+                title,
+                comments,
+                warnings,
+            } => {
+                println!("成功 {} -> 文章 {} `{}`", file.path, id, title);
+                if *comments > 0 {
+                    println!("    导入了{}条评论", comments);
+                }
+                for warning in warnings.iter() {
+                    println!("    警告: {}", warning);
+                }
+            }
+            ImportOutcome::Duplicate {
+                post_id: Some(post_id),
+                ..
+            } => println!("跳过 {}: 与文章 {} 内容相同", file.path, post_id),
+            ImportOutcome::Duplicate { path, .. } => println!(
+                "跳过 {}: 与 {} 内容相同",
+                file.path,
+                path.as_deref().unwrap_or_default()
+            ),
+            ImportOutcome::Failed { error } => println!("失败 {}: {}", file.path, error),
+        }
+    }
+    println!(
+        "共导入{}篇, 跳过{}篇, 失败{}篇",
+        report.imported, report.duplicated, report.failed
+    );
+    if report.failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+async fn export(path: PathBuf) -> ExitCode {
+    let config = AppConfig::new();
+    serve::init_tracing(&config);
+    let state = AppState::new(config).await;
+
+    let archive = match state
+        .post_service
+        .export_archive(&Principal::system(), &state.asset_service)
+        .await
+    {
+        Ok(archive) => archive,
+        Err(e) => {
+            eprintln!("导出备份失败: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(e) = tokio::fs::write(&path, &archive).await {
+        eprintln!("写入`{}`失败: {}", path.display(), e);
+        return ExitCode::FAILURE;
+    }
+    println!("已导出到`{}`, 共{}字节", path.display(), archive.len());
+    ExitCode::SUCCESS
+}
+
+/// 输出恢复结果, 备份无法恢复或恢复过程中出错时返回失败
+async fn restore(path: PathBuf, dry_run: bool) -> ExitCode {
+    let config = AppConfig::new();
+    serve::init_tracing(&config);
+
+    let backup = match tokio::fs::read(&path).await {
+        Ok(data) => Backup::read(data).await,
+        Err(e) => Err(e.into()),
+    };
+    let backup = match backup {
+        Ok(backup) => backup,
+        Err(e) => {
+            eprintln!("读取`{}`失败: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!(
+        "备份导出于{}, 数据库版本{}",
+        backup.manifest.exported_at, backup.manifest.schema_version
+    );
+    let state = AppState::new(config).await;
+    let report = match state
+        .post_service
+        .restore(backup, &state.asset_service, dry_run)
+        .await
+    {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("恢复失败: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for warning in report.warnings.iter() {
+        println!("警告: {}", warning);
+    }
+    for error in report.errors.iter() {
+        println!("错误: {}", error);
+    }
+    let action = if report.dry_run {
+        "可以恢复"
+    } else {
+        "已恢复"
+    };
+    println!(
+        "{}{}篇文章, {}条评论, {}个旧地址, {}个资源",
+        action, report.posts, report.comments, report.aliases, report.assets
+    );
+    if report.is_ok() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+async fn clean_assets(dry_run: bool) -> ExitCode {
+    let config = AppConfig::new();
+    serve::init_tracing(&config);
+    let state = AppState::new(config).await;
+
+    let removed = match state
+        .asset_service
+        .cleanup(&Principal::system(), dry_run)
+        .await
+    {
+        Ok(removed) => removed,
+        Err(e) => {
+            eprintln!("清理资源失败: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    for asset in removed.iter() {
+        println!("{} {} ({}字节)", asset.id, asset.filename, asset.size);
+    }
+    let action = if dry_run { "将要删除" } else { "已删除" };
+    println!("{}{}个资源", action, removed.len());
+    ExitCode::SUCCESS
+}
+
+/// 输出每个资源的处理结果, 有资源处理失败时返回失败
+async fn backfill_assets() -> ExitCode {
+    let config = AppConfig::new();
+    serve::init_tracing(&config);
+    let state = AppState::new(config).await;
+
+    let results = match state.asset_service.backfill(&Principal::system()).await {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("补充图片尺寸失败: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut failed = 0;
+    for result in results.iter() {
+        match &result.outcome {
+            Ok(asset) => println!(
+                "成功 {} {} ({}x{}, {}字节)",
+                result.id,
+                result.filename,
+                asset.width.unwrap_or_default(),
+                asset.height.unwrap_or_default(),
+                asset.size
+            ),
+            Err(e) => {
+                failed += 1;
+                println!("失败 {} {}: {}", result.id, result.filename, e);
+            }
+        }
+    }
+    println!("共处理{}个资源, 失败{}个", results.len(), failed);
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// 输出重建失败的文章, 有文章重建失败时返回失败
+async fn reindex() -> ExitCode {
+    let config = AppConfig::new();
+    serve::init_tracing(&config);
+    let state = AppState::new(config).await;
+
+    let results = match state.post_service.reindex(&Principal::system()).await {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("重建文章索引失败: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut failed = 0;
+    for result in results.iter() {
+        if let Err(e) = &result.outcome {
+            failed += 1;
+            println!("失败 {} `{}`: {}", result.id, result.title, e);
+        }
+    }
+    println!("共重建{}篇文章, 失败{}篇", results.len(), failed);
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// 读取标准输入的第一行作为密码, 可以通过管道传入
+async fn set_user(username: String, role: Role) -> ExitCode {
+    let config = AppConfig::new();
+    serve::init_tracing(&config);
+
+    println!("请输入`{}`的密码:", username);
+    let mut password = String::new();
+    if let Err(e) = io::stdin().read_line(&mut password) {
+        eprintln!("读取密码失败: {}", e);
+        return ExitCode::FAILURE;
+    }
+    let password = password.trim_end_matches(['\r', '\n']).to_string();
+    let state = AppState::new(config).await;
+
+    let user = match state.user_service.set_user(&username, password, role).await {
+        Ok(user) => user,
+        Err(e) => {
+            eprintln!("设置用户失败: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    // 已经登录的设备需要使用新的密码重新登录
+    if let Err(e) = state.auth_service.revoke_user(user.id).await {
+        eprintln!("撤销用户的会话失败: {}", e);
+        return ExitCode::FAILURE;
+    }
+    println!("已设置用户`{}`, 角色为{}", username, role);
+    ExitCode::SUCCESS
+}
@@ -1,18 +1,108 @@
 use config::Config;
 use dotenv::dotenv;
 use serde::Deserialize;
+use std::time::Duration;
 use tracing::event;
+
+/// 短于这个长度的密钥很容易被暴力破解
+const MIN_SECRET_LEN: usize = 32;
+/// `default.toml`中的占位密钥, 任何人都可以用它签发令牌
+const DEFAULT_SECRET: &str = "secret";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("请在`local.toml`或环境变量`SECRET`中设置长度至少为{MIN_SECRET_LEN}的`secret`")]
+    WeakSecret,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct AppConfig {
     pub database_url: String,
     pub port: u16,
     pub host: String,
     pub secret: String,
+    /// 访问令牌的有效期, 单位为分钟, 过期后使用刷新令牌获取新的访问令牌
     pub jwt_expiration_min: i64,
+    /// 刷新令牌的有效期, 单位为天, 每次刷新后重新计算
+    pub refresh_expiration_days: i64,
     pub run_migrations: bool,
     pub rust_log: String,
     pub save_dir: String,
     pub migrate_dir: String,
+    /// 检查定时发布文章的最长间隔, 单位为秒
+    pub publish_interval_secs: u64,
+    pub storage: StorageConfig,
+    pub asset: AssetConfig,
+    pub embedding: EmbeddingConfig,
+    pub search: SearchConfig,
+}
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    File,
+    Git,
+    /// 只保存在内存中, 用于测试
+    Memory,
+    S3,
+}
+/// 文章文件的存储方式
+/// - `file`和`git`将文件保存在`save_dir`中, `git`会在其中初始化仓库,
+///   并以`git_name`和`git_email`提交每次修改
+/// - `s3`将文件保存在`s3`配置的bucket中
+#[derive(Debug, Deserialize)]
+pub struct StorageConfig {
+    pub backend: StorageBackend,
+    pub git_name: String,
+    pub git_email: String,
+    pub s3: Option<S3Config>,
+}
+/// 兼容S3接口的对象存储, 如MinIO
+#[derive(Debug, Deserialize, Clone)]
+pub struct S3Config {
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+    /// 所有键的公共前缀, 如`posts/`
+    pub prefix: Option<String>,
+}
+/// 上传的图片和附件的存储, 使用与文章相同的存储方式:
+/// `file`和`git`保存在`save_dir`中(不记录git历史), `s3`保存在同一个bucket的`s3_prefix`下
+#[derive(Debug, Deserialize, Clone)]
+pub struct AssetConfig {
+    pub save_dir: String,
+    /// 没有被引用的资源在上传多久之后可以被清理, 单位为小时
+    pub orphan_grace_hours: i64,
+    pub s3_prefix: String,
+    /// 可以请求的图片宽度
+    pub image_widths: Vec<u32>,
+    /// 缩放和转换格式时的压缩质量, 1-100
+    pub image_quality: u8,
+}
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbeddingProvider {
+    Local,
+    Http,
+}
+/// 嵌入模型配置, `provider`为`http`时需要配置`url`和`model`
+#[derive(Debug, Deserialize)]
+pub struct EmbeddingConfig {
+    pub provider: EmbeddingProvider,
+    pub dimension: usize,
+    pub url: Option<String>,
+    pub model: Option<String>,
+    pub api_key: Option<String>,
+}
+/// 混合搜索时倒数排序融合(RRF)的默认参数, 可以被请求参数覆盖
+#[derive(Debug, Deserialize, Clone)]
+pub struct SearchConfig {
+    pub rrf_k: f32,
+    pub keyword_weight: f32,
+    pub semantic_weight: f32,
+    /// 每种搜索方式参与融合的候选结果数量
+    pub candidates: i32,
 }
 impl AppConfig {
     pub fn new() -> Self {
@@ -53,13 +143,55 @@ impl AppConfig {
     pub fn get_secret(&self) -> &str {
         self.secret.as_str()
     }
+    /// 令牌中的用户和角色不会再查询数据库, 密钥泄露等同于任何人都可以成为管理员,
+    /// 因此签发令牌之前必须替换`default.toml`中的占位密钥
+    pub fn check_secret(&self) -> Result<(), ConfigError> {
+        check_secret(self.get_secret())
+    }
     pub fn get_jwt_expiration(&self) -> i64 {
         self.jwt_expiration_min
     }
+    pub fn get_refresh_expiration(&self) -> i64 {
+        self.refresh_expiration_days
+    }
     pub fn get_log_level(&self) -> &str {
         self.rust_log.as_str()
     }
     pub fn get_save_dir(&self) -> &str {
         &self.save_dir
     }
+    pub fn get_publish_interval(&self) -> Duration {
+        Duration::from_secs(self.publish_interval_secs)
+    }
+    pub fn get_storage(&self) -> &StorageConfig {
+        &self.storage
+    }
+    pub fn get_asset(&self) -> &AssetConfig {
+        &self.asset
+    }
+    pub fn get_embedding(&self) -> &EmbeddingConfig {
+        &self.embedding
+    }
+    pub fn get_search(&self) -> &SearchConfig {
+        &self.search
+    }
+}
+
+fn check_secret(secret: &str) -> Result<(), ConfigError> {
+    if secret == DEFAULT_SECRET || secret.len() < MIN_SECRET_LEN {
+        return Err(ConfigError::WeakSecret);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weak_secrets_are_rejected() {
+        assert!(check_secret(DEFAULT_SECRET).is_err());
+        assert!(check_secret(&"a".repeat(MIN_SECRET_LEN - 1)).is_err());
+        assert!(check_secret(&"a".repeat(MIN_SECRET_LEN)).is_ok());
+    }
 }
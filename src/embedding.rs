@@ -0,0 +1,41 @@
+mod http;
+mod local;
+use crate::config::{EmbeddingConfig, EmbeddingProvider};
+use async_trait::async_trait;
+pub use http::HttpEmbedder;
+pub use local::LocalEmbedder;
+use std::sync::Arc;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmbeddingError {
+    #[error("Request Error: {0}")]
+    RequestError(String),
+    #[error("Bad Response: {0}")]
+    BadResponse(String),
+}
+
+/// 将文本转化为向量的嵌入模型
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    /// 模型标识, 不同模型生成的向量不能互相比较
+    fn model(&self) -> &str;
+    fn dimension(&self) -> usize;
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError>;
+}
+
+pub fn from_config(config: &EmbeddingConfig) -> Arc<dyn Embedder> {
+    match config.provider {
+        EmbeddingProvider::Local => Arc::new(LocalEmbedder::new(config.dimension)),
+        EmbeddingProvider::Http => {
+            let (Some(url), Some(model)) = (&config.url, &config.model) else {
+                panic!("使用http嵌入模型时必须配置`embedding.url`和`embedding.model`")
+            };
+            Arc::new(HttpEmbedder::new(
+                url,
+                model,
+                config.api_key.as_deref(),
+                config.dimension,
+            ))
+        }
+    }
+}
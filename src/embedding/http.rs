@@ -0,0 +1,114 @@
+use super::{Embedder, EmbeddingError};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{Level, event, instrument};
+
+/// 每次请求最多发送的文本数量
+const BATCH_SIZE: usize = 32;
+
+/// 兼容OpenAI `/embeddings` 接口的远程嵌入模型(如硅基流动)
+pub struct HttpEmbedder {
+    client: reqwest::Client,
+    url: String,
+    model: String,
+    api_key: Option<String>,
+    dimension: usize,
+}
+
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+    encoding_format: &'a str,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl HttpEmbedder {
+    pub fn new(url: &str, model: &str, api_key: Option<&str>, dimension: usize) -> Self {
+        tracing::info!("使用远程嵌入模型`{}`, 地址为: {}", model, url);
+        HttpEmbedder {
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+            model: model.to_string(),
+            api_key: api_key.map(|key| key.to_string()),
+            dimension,
+        }
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        let mut request = self.client.post(&self.url).json(&EmbeddingRequest {
+            model: &self.model,
+            input: texts,
+            encoding_format: "float",
+        });
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let response = request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| EmbeddingError::RequestError(e.to_string()))?;
+        let mut body: EmbeddingResponse = response
+            .json()
+            .await
+            .map_err(|e| EmbeddingError::BadResponse(e.to_string()))?;
+
+        if body.data.len() != texts.len() {
+            return Err(EmbeddingError::BadResponse(format!(
+                "请求了{}条文本, 但返回了{}条向量",
+                texts.len(),
+                body.data.len()
+            )));
+        }
+        body.data.sort_by_key(|data| data.index);
+        body.data
+            .into_iter()
+            .map(|data| {
+                if data.embedding.len() == self.dimension {
+                    Ok(data.embedding)
+                } else {
+                    Err(EmbeddingError::BadResponse(format!(
+                        "向量维度为{}, 与配置的{}不一致",
+                        data.embedding.len(),
+                        self.dimension
+                    )))
+                }
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Embedder for HttpEmbedder {
+    fn model(&self) -> &str {
+        &self.model
+    }
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+    #[instrument(name = "HttpEmbedder::embed", level = "debug", skip_all, fields(count = texts.len()))]
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(BATCH_SIZE) {
+            embeddings.extend(self.embed_batch(batch).await?);
+            event!(
+                Level::DEBUG,
+                embedded = embeddings.len(),
+                "完成一批文本的向量化"
+            );
+        }
+        Ok(embeddings)
+    }
+}
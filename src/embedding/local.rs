@@ -0,0 +1,173 @@
+use super::{Embedder, EmbeddingError};
+use crate::util::is_cjk;
+use async_trait::async_trait;
+
+/// 本地的哈希词袋模型, 不依赖外部服务, 相同的输入总是得到相同的向量
+///
+/// - 拉丁文字按单词和单词内的三元字符组计数
+/// - 中日韩文字按单字和相邻两字计数
+pub struct LocalEmbedder {
+    model: String,
+    dimension: usize,
+}
+
+impl LocalEmbedder {
+    pub fn new(dimension: usize) -> Self {
+        if dimension == 0 {
+            panic!("嵌入向量的维度不能为0")
+        }
+        tracing::info!("使用本地哈希嵌入模型, 维度为: {}", dimension);
+        LocalEmbedder {
+            model: format!("local-hash-{}", dimension),
+            dimension,
+        }
+    }
+
+    fn features(text: &str) -> Vec<String> {
+        let mut features = Vec::new();
+        let mut word = String::new();
+        let mut previous_cjk: Option<char> = None;
+        for c in text.chars().flat_map(char::to_lowercase) {
+            if is_cjk(c) {
+                Self::word_features(&mut word, &mut features);
+                features.push(c.to_string());
+                if let Some(previous) = previous_cjk {
+                    features.push(format!("{}{}", previous, c));
+                }
+                previous_cjk = Some(c);
+            } else if c.is_alphanumeric() {
+                word.push(c);
+                previous_cjk = None;
+            } else {
+                Self::word_features(&mut word, &mut features);
+                previous_cjk = None;
+            }
+        }
+        Self::word_features(&mut word, &mut features);
+        features
+    }
+
+    fn word_features(word: &mut String, features: &mut Vec<String>) {
+        if word.is_empty() {
+            return;
+        }
+        let padded: Vec<char> = format!("^{}$", word).chars().collect();
+        for trigram in padded.windows(3) {
+            features.push(trigram.iter().collect());
+        }
+        features.push(std::mem::take(word));
+    }
+
+    /// FNV-1a, 保证不同版本和不同机器上的结果一致
+    fn hash(feature: &str) -> u64 {
+        feature.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+
+    fn embed_one(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0f32; self.dimension];
+        for feature in Self::features(text) {
+            let hash = Self::hash(&feature);
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[(hash % self.dimension as u64) as usize] += sign;
+        }
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|x| *x /= norm);
+        }
+        vector
+    }
+}
+
+#[async_trait]
+impl Embedder for LocalEmbedder {
+    fn model(&self) -> &str {
+        &self.model
+    }
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        Ok(texts.iter().map(|text| self.embed_one(text)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn embed(embedder: &LocalEmbedder, text: &str) -> Vec<f32> {
+        embedder.embed_one(text)
+    }
+
+    fn dot(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(a, b)| a * b).sum()
+    }
+
+    #[test]
+    fn same_text_same_vector() {
+        let embedder = LocalEmbedder::new(64);
+        let text = "Rust异步编程 async/await";
+        assert_eq!(embed(&embedder, text), embed(&embedder, text));
+        assert_eq!(embed(&embedder, text), embed(&LocalEmbedder::new(64), text));
+        assert_eq!(embedder.model(), "local-hash-64");
+    }
+
+    #[test]
+    fn vectors_have_configured_dimension() {
+        for dimension in [1, 7, 384] {
+            let embedder = LocalEmbedder::new(dimension);
+            assert_eq!(embedder.dimension(), dimension);
+            assert_eq!(embed(&embedder, "hello 世界").len(), dimension);
+        }
+    }
+
+    #[test]
+    fn vectors_are_unit_length() {
+        let embedder = LocalEmbedder::new(256);
+        for text in [
+            "hello",
+            "Hello, World!",
+            "中文分词",
+            "混合 mixed 文本 text 123",
+        ] {
+            let vector = embed(&embedder, text);
+            assert!((dot(&vector, &vector) - 1.0).abs() < 1e-5, "{}", text);
+        }
+    }
+
+    #[test]
+    fn empty_input_is_zero_vector() {
+        let embedder = LocalEmbedder::new(32);
+        for text in ["", "   ", "\n\t", "，。！ ..."] {
+            let vector = embed(&embedder, text);
+            assert!(vector.iter().all(|x| *x == 0.0), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn similar_texts_are_closer() {
+        let embedder = LocalEmbedder::new(256);
+        let query = embed(&embedder, "rust programming language");
+        let related = embed(&embedder, "programming in Rust");
+        let unrelated = embed(&embedder, "红烧肉的做法");
+        assert!(dot(&query, &related) > dot(&query, &unrelated));
+    }
+
+    #[tokio::test]
+    async fn embed_keeps_input_order() {
+        let embedder = LocalEmbedder::new(16);
+        let texts = vec!["first".to_string(), String::new(), "third".to_string()];
+        let vectors = embedder.embed(&texts).await.unwrap();
+        assert_eq!(vectors.len(), 3);
+        assert_eq!(vectors[0], embed(&embedder, "first"));
+        assert_eq!(vectors[2], embed(&embedder, "third"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_dimension_panics() {
+        LocalEmbedder::new(0);
+    }
+}
@@ -0,0 +1,457 @@
+use image::codecs::avif::AvifEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::imageops::FilterType;
+use image::metadata::Orientation;
+use image::{DynamicImage, ExtendedColorType, ImageDecoder, ImageEncoder, ImageReader, Limits};
+use serde::Deserialize;
+use std::fmt;
+use std::io::Cursor;
+
+/// 允许处理的图片的最大边长, 防止解码时占用过多内存
+const MAX_DIMENSION: u32 = 12000;
+/// 需要旋转的原图重新编码时使用的质量
+const ORIGINAL_QUALITY: u8 = 90;
+/// avif编码速度, 1最慢压缩率最高, 10最快
+const AVIF_SPEED: u8 = 6;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImageError {
+    #[error("无法解析图片: {0}")]
+    Decode(String),
+    #[error("无法编码图片: {0}")]
+    Encode(String),
+}
+
+/// 图片的输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Jpeg,
+    Png,
+    Webp,
+    Avif,
+}
+
+impl OutputFormat {
+    /// 可以缩放和转换的原图类型对应的格式, bmp以png输出
+    pub fn from_mime(mime: &str) -> Option<Self> {
+        match mime {
+            "image/jpeg" => Some(Self::Jpeg),
+            "image/png" | "image/bmp" => Some(Self::Png),
+            "image/webp" => Some(Self::Webp),
+            _ => None,
+        }
+    }
+    pub fn mime(self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+            Self::Webp => "image/webp",
+            Self::Avif => "image/avif",
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Jpeg => "jpeg",
+            Self::Png => "png",
+            Self::Webp => "webp",
+            Self::Avif => "avif",
+        };
+        f.write_str(name)
+    }
+}
+
+/// 原图的一个衍生版本, 宽度为空时保持原图尺寸
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Variant {
+    pub width: Option<u32>,
+    pub format: OutputFormat,
+}
+
+impl Variant {
+    /// 用于存储的键和ETag, 如`640w.webp`, `full.avif`
+    pub fn name(&self) -> String {
+        match self.width {
+            Some(width) => format!("{}w.{}", width, self.format),
+            None => format!("full.{}", self.format),
+        }
+    }
+}
+
+/// 上传的图片去除元数据后的内容和尺寸
+pub struct Prepared {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 去除图片中的EXIF, XMP和文本等元数据, 其中可能包含拍摄位置等隐私信息;
+/// 一般情况下直接删除对应的数据段, 不会损失画质; EXIF中记录了旋转方向时先按方向旋转再重新编码,
+/// 避免删除EXIF后图片方向错误
+pub fn prepare(mime: &str, data: &[u8]) -> Result<Prepared, ImageError> {
+    let Some(format) = OutputFormat::from_mime(mime) else {
+        return Err(ImageError::Decode(format!("不支持处理{}", mime)));
+    };
+    let mut decoder = reader(data)?
+        .into_decoder()
+        .map_err(|e| ImageError::Decode(e.to_string()))?;
+    let (width, height) = decoder.dimensions();
+    let orientation = decoder
+        .orientation()
+        .map_err(|e| ImageError::Decode(e.to_string()))?;
+    if orientation != Orientation::NoTransforms {
+        let mut image =
+            DynamicImage::from_decoder(decoder).map_err(|e| ImageError::Decode(e.to_string()))?;
+        image.apply_orientation(orientation);
+        return Ok(Prepared {
+            data: encode(&image, format, ORIGINAL_QUALITY)?,
+            width: image.width(),
+            height: image.height(),
+        });
+    }
+
+    let data = match mime {
+        "image/jpeg" => strip_jpeg(data)?,
+        "image/png" => strip_png(data)?,
+        "image/webp" => strip_webp(data)?,
+        _ => data.to_vec(),
+    };
+    Ok(Prepared {
+        data,
+        width,
+        height,
+    })
+}
+
+/// 生成缩放到指定宽度(不会放大)并转换格式的图片, 按宽度等比例缩放
+pub fn derive(data: &[u8], variant: Variant, quality: u8) -> Result<Vec<u8>, ImageError> {
+    let mut decoder = reader(data)?
+        .into_decoder()
+        .map_err(|e| ImageError::Decode(e.to_string()))?;
+    let orientation = decoder
+        .orientation()
+        .map_err(|e| ImageError::Decode(e.to_string()))?;
+    let mut image =
+        DynamicImage::from_decoder(decoder).map_err(|e| ImageError::Decode(e.to_string()))?;
+    image.apply_orientation(orientation);
+    let image = match variant.width {
+        Some(width) if width < image.width() => image.resize(width, u32::MAX, FilterType::Lanczos3),
+        _ => image,
+    };
+    encode(&image, variant.format, quality)
+}
+
+fn reader(data: &[u8]) -> Result<ImageReader<Cursor<&[u8]>>, ImageError> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    let mut reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| ImageError::Decode(e.to_string()))?;
+    reader.limits(limits);
+    Ok(reader)
+}
+
+fn encode(image: &DynamicImage, format: OutputFormat, quality: u8) -> Result<Vec<u8>, ImageError> {
+    let (width, height) = (image.width(), image.height());
+    let alpha = image.color().has_alpha();
+    let mut out = Vec::new();
+    let result = match format {
+        OutputFormat::Jpeg => {
+            let rgb = image.to_rgb8();
+            JpegEncoder::new_with_quality(&mut out, quality).write_image(
+                rgb.as_raw(),
+                width,
+                height,
+                ExtendedColorType::Rgb8,
+            )
+        }
+        OutputFormat::Png => image.write_with_encoder(PngEncoder::new(&mut out)),
+        // image自带的webp编码器只支持无损压缩, 照片会比原图还大
+        OutputFormat::Webp => {
+            let memory = if alpha {
+                webp::Encoder::from_rgba(image.to_rgba8().as_raw(), width, height)
+                    .encode(quality as f32)
+            } else {
+                webp::Encoder::from_rgb(image.to_rgb8().as_raw(), width, height)
+                    .encode(quality as f32)
+            };
+            out.extend_from_slice(&memory);
+            Ok(())
+        }
+        OutputFormat::Avif => {
+            let encoder = AvifEncoder::new_with_speed_quality(&mut out, AVIF_SPEED, quality);
+            if alpha {
+                let rgba = image.to_rgba8();
+                encoder.write_image(rgba.as_raw(), width, height, ExtendedColorType::Rgba8)
+            } else {
+                let rgb = image.to_rgb8();
+                encoder.write_image(rgb.as_raw(), width, height, ExtendedColorType::Rgb8)
+            }
+        }
+    };
+    result.map_err(|e| ImageError::Encode(e.to_string()))?;
+    Ok(out)
+}
+
+fn malformed(format: &str) -> ImageError {
+    ImageError::Decode(format!("{}文件结构错误", format))
+}
+
+/// 删除APP1(EXIF, XMP), APP13(IPTC)和注释段, 保留APP2中的颜色配置
+fn strip_jpeg(data: &[u8]) -> Result<Vec<u8>, ImageError> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return Err(malformed("jpeg"));
+    }
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..2]);
+    let mut pos = 2;
+    loop {
+        if pos + 4 > data.len() || data[pos] != 0xFF {
+            return Err(malformed("jpeg"));
+        }
+        let marker = data[pos + 1];
+        // 段之间的填充字节
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        // SOS之后是压缩数据, 元数据只会出现在SOS之前
+        if marker == 0xDA {
+            out.extend_from_slice(&data[pos..]);
+            return Ok(out);
+        }
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let end = pos + 2 + length;
+        if length < 2 || end > data.len() {
+            return Err(malformed("jpeg"));
+        }
+        if !matches!(marker, 0xE1 | 0xED | 0xFE) {
+            out.extend_from_slice(&data[pos..end]);
+        }
+        pos = end;
+    }
+}
+
+/// 删除eXIf, 文本和时间块
+fn strip_png(data: &[u8]) -> Result<Vec<u8>, ImageError> {
+    if !data.starts_with(PNG_SIGNATURE) {
+        return Err(malformed("png"));
+    }
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(PNG_SIGNATURE);
+    let mut pos = PNG_SIGNATURE.len();
+    while pos < data.len() {
+        if pos + 12 > data.len() {
+            return Err(malformed("png"));
+        }
+        let length = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]);
+        let kind = &data[pos + 4..pos + 8];
+        let end = pos + 12 + length as usize;
+        if end > data.len() {
+            return Err(malformed("png"));
+        }
+        if !matches!(kind, b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt" | b"tIME") {
+            out.extend_from_slice(&data[pos..end]);
+        }
+        pos = end;
+        if kind == b"IEND" {
+            break;
+        }
+    }
+    Ok(out)
+}
+
+/// 删除EXIF和XMP块, 同时清除VP8X中对应的标记并更新RIFF的长度
+fn strip_webp(data: &[u8]) -> Result<Vec<u8>, ImageError> {
+    if data.len() < 12 || &data[..4] != b"RIFF" || &data[8..12] != b"WEBP" {
+        return Err(malformed("webp"));
+    }
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..12]);
+    let mut pos = 12;
+    while pos < data.len() {
+        if pos + 8 > data.len() {
+            return Err(malformed("webp"));
+        }
+        let kind = &data[pos..pos + 4];
+        let length =
+            u32::from_le_bytes([data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]]);
+        // 块的长度为奇数时有一个填充字节
+        let end = (pos + 8 + length as usize + (length as usize & 1)).min(data.len());
+        if pos + 8 + length as usize > data.len() {
+            return Err(malformed("webp"));
+        }
+        match kind {
+            b"EXIF" | b"XMP " => {}
+            b"VP8X" if length >= 1 => {
+                let start = out.len();
+                out.extend_from_slice(&data[pos..end]);
+                // 第一个字节中0x08表示有EXIF, 0x04表示有XMP
+                out[start + 8] &= !0x0C;
+            }
+            _ => out.extend_from_slice(&data[pos..end]),
+        }
+        pos = end;
+    }
+    let size = (out.len() - 8) as u32;
+    out[4..8].copy_from_slice(&size.to_le_bytes());
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jpeg_segment(marker: u8, payload: &[u8]) -> Vec<u8> {
+        let mut segment = vec![0xFF, marker];
+        segment.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+        segment.extend_from_slice(payload);
+        segment
+    }
+
+    fn png_chunk(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut chunk = (payload.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(payload);
+        // 不校验crc
+        chunk.extend_from_slice(&[0; 4]);
+        chunk
+    }
+
+    fn webp_chunk(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut chunk = kind.to_vec();
+        chunk.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(payload);
+        if payload.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    fn webp(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body = chunks.concat();
+        let mut data = b"RIFF".to_vec();
+        data.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes());
+        data.extend_from_slice(b"WEBP");
+        data.extend_from_slice(&body);
+        data
+    }
+
+    fn jpeg_parts() -> (Vec<u8>, Vec<u8>) {
+        let jfif = jpeg_segment(0xE0, b"JFIF\0\x01\x01");
+        let exif = jpeg_segment(0xE1, b"Exif\0\0GPS");
+        let xmp = jpeg_segment(0xE1, b"http://ns.adobe.com/xap/1.0/\0<x/>");
+        let icc = jpeg_segment(0xE2, b"ICC_PROFILE\0");
+        let comment = jpeg_segment(0xFE, b"camera");
+        let scan = [0xFF, 0xDA, 0x00, 0x02, 0x12, 0x34, 0xFF, 0xD9];
+        let data = [&[0xFF, 0xD8][..], &jfif, &exif, &xmp, &icc, &comment, &scan].concat();
+        let stripped = [&[0xFF, 0xD8][..], &jfif, &icc, &scan].concat();
+        (data, stripped)
+    }
+
+    #[test]
+    fn strip_jpeg_removes_exif_xmp_and_comments() {
+        let (data, stripped) = jpeg_parts();
+        assert_eq!(strip_jpeg(&data).unwrap(), stripped);
+    }
+
+    #[test]
+    fn strip_jpeg_skips_fill_bytes() {
+        let (data, stripped) = jpeg_parts();
+        let padded = [&data[..2], &[0xFF, 0xFF][..], &data[2..]].concat();
+        assert_eq!(strip_jpeg(&padded).unwrap(), stripped);
+    }
+
+    #[test]
+    fn strip_png_removes_metadata_chunks() {
+        let ihdr = png_chunk(b"IHDR", &[0; 13]);
+        let idat = png_chunk(b"IDAT", b"pixels");
+        let iend = png_chunk(b"IEND", b"");
+        let data = [
+            PNG_SIGNATURE,
+            &ihdr,
+            &png_chunk(b"eXIf", b"MM\0*"),
+            &png_chunk(b"iTXt", b"XML:com.adobe.xmp\0\0\0\0\0<x/>"),
+            &png_chunk(b"tEXt", b"Author\0me"),
+            &png_chunk(b"tIME", &[0; 7]),
+            &idat,
+            &iend,
+            b"trailing",
+        ]
+        .concat();
+        let stripped = [PNG_SIGNATURE, &ihdr, &idat, &iend].concat();
+        assert_eq!(strip_png(&data).unwrap(), stripped);
+    }
+
+    #[test]
+    fn strip_webp_removes_metadata_and_clears_flags() {
+        // 0x10表示有透明通道, 应当保留
+        let vp8x = [0x1C, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let vp8 = webp_chunk(b"VP8 ", b"odd");
+        let data = webp(&[
+            webp_chunk(b"VP8X", &vp8x),
+            vp8.clone(),
+            webp_chunk(b"EXIF", b"MM\0*"),
+            webp_chunk(b"XMP ", b"<x/>"),
+        ]);
+
+        let out = strip_webp(&data).unwrap();
+        let mut cleared = vp8x;
+        cleared[0] = 0x10;
+        assert_eq!(out, webp(&[webp_chunk(b"VP8X", &cleared), vp8]));
+        let size = u32::from_le_bytes([out[4], out[5], out[6], out[7]]) as usize;
+        assert_eq!(size, out.len() - 8);
+    }
+
+    #[test]
+    fn strip_webp_keeps_simple_files() {
+        let data = webp(&[webp_chunk(b"VP8L", b"lossless")]);
+        assert_eq!(strip_webp(&data).unwrap(), data);
+    }
+
+    /// 截断在任何位置都不能越界, 截断在元数据块`cut`中间时返回解析错误
+    fn assert_truncated(data: &[u8], cut: usize, strip: fn(&[u8]) -> Result<Vec<u8>, ImageError>) {
+        for len in 0..data.len() {
+            let _ = strip(&data[..len]);
+        }
+        assert!(matches!(strip(&data[..cut]), Err(ImageError::Decode(_))));
+    }
+
+    #[test]
+    fn truncated_jpeg_is_decode_error() {
+        let (data, _) = jpeg_parts();
+        // SOI和JFIF段共13字节, 之后是EXIF段
+        assert_truncated(&data, 20, strip_jpeg);
+    }
+
+    #[test]
+    fn truncated_png_is_decode_error() {
+        let data = [
+            PNG_SIGNATURE,
+            &png_chunk(b"IHDR", &[0; 13]),
+            &png_chunk(b"eXIf", b"MM\0*"),
+            &png_chunk(b"IEND", b""),
+        ]
+        .concat();
+        // 签名和IHDR块共33字节
+        assert_truncated(&data, 40, strip_png);
+    }
+
+    #[test]
+    fn truncated_webp_is_decode_error() {
+        let data = webp(&[
+            webp_chunk(b"VP8X", &[0x08, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            webp_chunk(b"EXIF", b"MM\0*"),
+            webp_chunk(b"VP8 ", b"data"),
+        ]);
+        // RIFF头和VP8X块共30字节
+        assert_truncated(&data, 34, strip_webp);
+    }
+}
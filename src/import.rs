@@ -0,0 +1,526 @@
+mod bundle;
+mod hexo;
+mod hugo;
+mod jekyll;
+mod markdown;
+mod wordpress;
+use crate::models::post::PostCreate;
+use crate::util::FrontMatter;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use std::fmt;
+use std::io::{self, Cursor, Read};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use tokio::task;
+
+pub use bundle::{Bundle, MAX_BUNDLE_SIZE, read_bundle};
+
+/// 单个文件的最大大小, 与上传接口的限制相同
+pub const MAX_FILE_SIZE: usize = 10 * 1024 * 1024;
+/// 上传的压缩包的最大大小, 也是WXR文件的最大大小
+pub const MAX_ARCHIVE_SIZE: usize = 256 * 1024 * 1024;
+/// 一次最多导入的文件数量
+pub const MAX_FILES: usize = 10000;
+/// 一次读取的所有文件解压后的总大小, 避免压缩率很高的压缩包耗尽内存
+pub const MAX_EXTRACTED_SIZE: usize = 2 * MAX_ARCHIVE_SIZE;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("IO Error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Archive Error: {0}")]
+    Archive(String),
+    #[error("Too Many Files: {0}")]
+    TooManyFiles(usize),
+}
+
+impl From<zip::result::ZipError> for ImportError {
+    fn from(value: zip::result::ZipError) -> Self {
+        Self::Archive(value.to_string())
+    }
+}
+
+/// 导入文件的来源
+/// - `Markdown`: 使用本项目front matter格式的markdown文件
+/// - `Hexo`, `Hugo`, `Jekyll`: 对应博客程序的源文件, 分类会作为标签导入,
+///   `permalink`, `aliases`等字段会作为文章的旧地址
+/// - `Wordpress`: WordPress导出的WXR文件, 包括文章的评论
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    #[default]
+    Markdown,
+    Hexo,
+    Hugo,
+    Jekyll,
+    Wordpress,
+}
+
+impl FromStr for ImportFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "markdown" | "md" => Ok(Self::Markdown),
+            "hexo" => Ok(Self::Hexo),
+            "hugo" => Ok(Self::Hugo),
+            "jekyll" => Ok(Self::Jekyll),
+            "wordpress" | "wxr" => Ok(Self::Wordpress),
+            _ => Err(format!("无效的导入格式: {}", s)),
+        }
+    }
+}
+
+impl ImportFormat {
+    /// 是否需要读取该文件, 跳过隐藏文件, macOS压缩时生成的`__MACOSX`目录和Hugo的列表页
+    fn accepts(self, path: &Path) -> bool {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default();
+        let accepted = match self {
+            Self::Wordpress => extension.eq_ignore_ascii_case("xml"),
+            Self::Hugo if path.file_stem().is_some_and(|stem| stem == "_index") => false,
+            _ => extension.eq_ignore_ascii_case("md") || extension.eq_ignore_ascii_case("markdown"),
+        };
+        accepted && !is_hidden(path)
+    }
+
+    /// 整个站点的源文件中只导入文章所在的目录:
+    /// Hexo和Jekyll的`_posts`, `_drafts`, Hugo的`content`
+    fn select(self, files: &mut Vec<ImportFile>) {
+        let dirs: &[&str] = match self {
+            Self::Hexo | Self::Jekyll => &["_posts", "_drafts"],
+            Self::Hugo => &["content"],
+            _ => return,
+        };
+        let in_dirs = |file: &ImportFile| file.path.split('/').any(|part| dirs.contains(&part));
+        if files.iter().any(in_dirs) {
+            files.retain(in_dirs);
+        }
+    }
+
+    fn max_file_size(self) -> usize {
+        match self {
+            Self::Wordpress => MAX_ARCHIVE_SIZE,
+            _ => MAX_FILE_SIZE,
+        }
+    }
+}
+
+/// 需要导入的文件
+pub struct ImportFile {
+    /// 相对于目录或压缩包根目录的路径, 使用`/`分隔
+    pub path: String,
+    /// 文件内容, 超过大小限制的文件只读取限制加一字节
+    pub content: Vec<u8>,
+}
+
+impl ImportFile {
+    /// 去掉扩展名的文件名, Hugo的`index.md`使用所在目录的名称
+    pub fn stem(&self) -> &str {
+        let path = Path::new(&self.path);
+        let stem = path.file_stem().and_then(|stem| stem.to_str());
+        let stem = match stem {
+            Some("index") => path
+                .parent()
+                .and_then(|parent| parent.file_name())
+                .and_then(|name| name.to_str())
+                .or(stem),
+            _ => stem,
+        };
+        stem.unwrap_or(&self.path)
+    }
+
+    /// 是否在某个目录中, 如Jekyll的`_drafts`
+    fn in_dir(&self, dir: &str) -> bool {
+        self.path.split('/').rev().skip(1).any(|part| part == dir)
+    }
+}
+
+/// 从导入的文件中解析出的一篇文章
+pub struct ImportPost {
+    /// 文章的来源, 如`_posts/2019-01-01-hello.md`, WXR中的文章为`blog.xml#12`
+    pub source: String,
+    pub post: PostCreate,
+    /// 文章原来的地址, 导入后仍然可以通过这些地址找到文章
+    pub aliases: Vec<String>,
+    pub comments: Vec<ImportComment>,
+    /// 导入时被忽略或修改的内容
+    pub warnings: Vec<String>,
+}
+
+impl ImportPost {
+    /// 由front matter生成文章, front matter中没有标题时使用`fallback_title`,
+    /// 没有修改时间的文章视为发布后没有修改过
+    fn new(
+        source: String,
+        front_matter: FrontMatter,
+        content: Vec<u8>,
+        fallback_title: &str,
+    ) -> Result<Self, ParseFailure> {
+        let status = match front_matter.status.as_deref().map(str::parse).transpose() {
+            Ok(status) => status,
+            Err(e) => return Err(ParseFailure::new(source, e)),
+        };
+        let title = front_matter
+            .title
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| fallback_title.to_string());
+        let message = Some(format!("从`{}`导入", source));
+        Ok(ImportPost {
+            post: PostCreate {
+                title,
+                slug: front_matter.slug,
+                tags: front_matter.tags.unwrap_or_default(),
+                content,
+                date: front_matter.date,
+                updated: front_matter.updated.or(front_matter.date),
+                summary: front_matter.summary,
+                status,
+                publish_at: front_matter.publish_at,
+                author: None,
+                message,
+            },
+            source,
+            aliases: Vec::new(),
+            comments: Vec::new(),
+            warnings: Vec::new(),
+        })
+    }
+}
+
+/// 导入的评论, `id`和`parent`是原来的博客程序中的id, 用于还原回复关系
+pub struct ImportComment {
+    pub id: i64,
+    pub parent: Option<i64>,
+    pub author: String,
+    pub content: String,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// 无法解析的文件或文章
+#[derive(Debug)]
+pub struct ParseFailure {
+    pub source: String,
+    pub error: String,
+}
+
+impl ParseFailure {
+    fn new(source: impl Into<String>, error: impl fmt::Display) -> Self {
+        ParseFailure {
+            source: source.into(),
+            error: error.to_string(),
+        }
+    }
+}
+
+/// 按照`format`解析文件, 一个WXR文件中包含多篇文章, 其他格式的文件都只有一篇
+pub fn parse(format: ImportFormat, file: ImportFile) -> Vec<Result<ImportPost, ParseFailure>> {
+    if file.content.len() > format.max_file_size() {
+        return vec![Err(ParseFailure::new(file.path, "文件大小超过限制"))];
+    }
+    match format {
+        ImportFormat::Markdown => vec![markdown::parse(file)],
+        ImportFormat::Hexo => vec![hexo::parse(file)],
+        ImportFormat::Hugo => vec![hugo::parse(file)],
+        ImportFormat::Jekyll => vec![jekyll::parse(file)],
+        ImportFormat::Wordpress => wordpress::parse(file),
+    }
+}
+
+/// front matter中的单个值, 标题等字段可能被写成数字
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Scalar {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl fmt::Display for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::String(value) => f.write_str(value),
+            Self::Integer(value) => write!(f, "{}", value),
+            Self::Float(value) => write!(f, "{}", value),
+            Self::Bool(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// 标签, 分类, 别名等既可以是单个值也可以是列表的字段, Hexo的多级分类是嵌套的列表
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Terms {
+    One(Scalar),
+    Many(Vec<Terms>),
+}
+
+impl Terms {
+    /// 展开为列表, `split`为`true`时单个值按空白拆分(Jekyll的写法)
+    fn flatten(self, split: bool) -> Vec<String> {
+        let mut terms = Vec::new();
+        self.flatten_into(split, &mut terms);
+        terms
+    }
+
+    fn flatten_into(self, split: bool, terms: &mut Vec<String>) {
+        match self {
+            Self::One(value) if split => {
+                terms.extend(value.to_string().split_whitespace().map(str::to_string))
+            }
+            Self::One(value) => terms.push(value.to_string().trim().to_string()),
+            Self::Many(values) => {
+                for value in values {
+                    value.flatten_into(split, terms);
+                }
+            }
+        }
+    }
+}
+
+/// 合并标签和分类, 去掉空白和重复的项
+fn merge_terms(groups: impl IntoIterator<Item = Vec<String>>) -> Option<Vec<String>> {
+    let mut merged: Vec<String> = Vec::new();
+    for term in groups.into_iter().flatten() {
+        if !term.is_empty() && !merged.contains(&term) {
+            merged.push(term);
+        }
+    }
+    Some(merged).filter(|merged| !merged.is_empty())
+}
+
+/// 解析日期, 无法识别时记录警告并忽略
+fn parse_date(
+    value: Option<String>,
+    field: &str,
+    warnings: &mut Vec<String>,
+) -> Option<DateTime<Utc>> {
+    let value = value.filter(|value| !value.trim().is_empty())?;
+    match crate::util::MARKDOWN_UTIL.parse_date(&value) {
+        Ok(date) => Some(date),
+        Err(e) => {
+            warnings.push(format!("忽略了`{}`: {}", field, e));
+            None
+        }
+    }
+}
+
+/// 读取目录中需要导入的文件, 路径是文件时直接导入该文件或作为压缩包读取
+pub async fn read_path(
+    path: PathBuf,
+    format: ImportFormat,
+) -> Result<Vec<ImportFile>, ImportError> {
+    task::spawn_blocking(move || {
+        let mut files = if path.is_dir() {
+            read_dir(&path, format)?
+        } else if format.accepts(&path) {
+            let name = path.file_name().map(Path::new).unwrap_or(&path);
+            let content = read_limited(std::fs::File::open(&path)?, format.max_file_size())?;
+            vec![ImportFile {
+                path: to_key(name),
+                content,
+            }]
+        } else {
+            parse_archive(&std::fs::read(&path)?, format)?
+        };
+        format.select(&mut files);
+        Ok(files)
+    })
+    .await
+    .map_err(|e| ImportError::Archive(e.to_string()))?
+}
+
+/// 读取zip, tar或tar.gz压缩包中需要导入的文件, 根据文件头判断格式
+pub async fn read_archive(
+    data: Bytes,
+    format: ImportFormat,
+) -> Result<Vec<ImportFile>, ImportError> {
+    task::spawn_blocking(move || {
+        let mut files = parse_archive(&data, format)?;
+        format.select(&mut files);
+        Ok(files)
+    })
+    .await
+    .map_err(|e| ImportError::Archive(e.to_string()))?
+}
+
+/// 隐藏文件和macOS压缩时生成的`__MACOSX`目录
+fn is_hidden(path: &Path) -> bool {
+    path.components().any(|c| match c {
+        Component::Normal(name) => {
+            let name = name.to_string_lossy();
+            name.starts_with('.') || name == "__MACOSX"
+        }
+        _ => false,
+    })
+}
+
+/// 读取压缩包时保留哪些文件, 以及单个文件和所有文件的大小限制
+struct Select<'a> {
+    accepts: &'a dyn Fn(&Path) -> bool,
+    max_size: usize,
+    max_total: usize,
+}
+
+fn parse_archive(data: &[u8], format: ImportFormat) -> Result<Vec<ImportFile>, ImportError> {
+    let accepts = |path: &Path| format.accepts(path);
+    read_any(
+        data,
+        &Select {
+            accepts: &accepts,
+            max_size: format.max_file_size(),
+            max_total: MAX_EXTRACTED_SIZE,
+        },
+    )
+}
+
+fn read_any(data: &[u8], select: &Select) -> Result<Vec<ImportFile>, ImportError> {
+    if data.starts_with(b"PK\x03\x04") || data.starts_with(b"PK\x05\x06") {
+        read_zip(data, select)
+    } else if data.starts_with(&[0x1f, 0x8b]) {
+        read_tar(GzDecoder::new(data), select)
+    } else if data.get(257..262) == Some(b"ustar") {
+        read_tar(data, select)
+    } else {
+        Err(ImportError::Archive("不支持的压缩包格式".to_string()))
+    }
+}
+
+fn to_key(path: &Path) -> String {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn read_limited(reader: impl Read, max_size: usize) -> io::Result<Vec<u8>> {
+    let mut content = Vec::new();
+    reader.take(max_size as u64 + 1).read_to_end(&mut content)?;
+    Ok(content)
+}
+
+/// 读取到的文件, 限制文件数量和总大小
+struct Collector {
+    files: Vec<ImportFile>,
+    total: usize,
+    max_total: usize,
+}
+
+impl Collector {
+    fn new(max_total: usize) -> Self {
+        Collector {
+            files: Vec::new(),
+            total: 0,
+            max_total,
+        }
+    }
+
+    fn push(&mut self, path: String, content: Vec<u8>) -> Result<(), ImportError> {
+        if self.files.len() >= MAX_FILES {
+            return Err(ImportError::TooManyFiles(MAX_FILES));
+        }
+        self.total += content.len();
+        if self.total > self.max_total {
+            return Err(ImportError::Archive(format!(
+                "解压后的文件总大小超过{}MiB",
+                self.max_total / 1024 / 1024
+            )));
+        }
+        self.files.push(ImportFile { path, content });
+        Ok(())
+    }
+}
+
+/// 递归读取目录, 结果按路径排序
+fn read_dir(root: &Path, format: ImportFormat) -> Result<Vec<ImportFile>, ImportError> {
+    let mut files = Collector::new(MAX_EXTRACTED_SIZE);
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let entry = entry?;
+            let path = entry.path();
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            if entry.file_type()?.is_dir() {
+                if !entry.file_name().to_string_lossy().starts_with('.') {
+                    pending.push(path);
+                }
+            } else if format.accepts(relative) {
+                let content = read_limited(std::fs::File::open(&path)?, format.max_file_size())?;
+                files.push(to_key(relative), content)?;
+            }
+        }
+    }
+    let mut files = files.files;
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+fn read_zip(data: &[u8], select: &Select) -> Result<Vec<ImportFile>, ImportError> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(data))?;
+    let mut files = Collector::new(select.max_total);
+    for index in 0..archive.len() {
+        let entry = archive.by_index(index)?;
+        // 不安全的路径(如包含`..`)直接跳过
+        let Some(path) = entry.enclosed_name() else {
+            continue;
+        };
+        if entry.is_file() && (select.accepts)(&path) {
+            let content = read_limited(entry, select.max_size)?;
+            files.push(to_key(&path), content)?;
+        }
+    }
+    Ok(files.files)
+}
+
+fn read_tar(reader: impl Read, select: &Select) -> Result<Vec<ImportFile>, ImportError> {
+    let mut archive = tar::Archive::new(reader);
+    let mut files = Collector::new(select.max_total);
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let path = entry.path()?.into_owned();
+        let safe = path
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+        if entry.header().entry_type().is_file() && safe && (select.accepts)(&path) {
+            let content = read_limited(entry, select.max_size)?;
+            files.push(to_key(&path), content)?;
+        }
+    }
+    Ok(files.files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub fn file(path: &str, content: &str) -> ImportFile {
+        ImportFile {
+            path: path.to_string(),
+            content: content.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn merge_terms_drops_empty_and_repeated_terms() {
+        let merged = merge_terms([
+            vec!["a".to_string(), String::new(), "b".to_string()],
+            vec!["a".to_string(), "c".to_string()],
+        ]);
+        assert_eq!(merged.unwrap(), ["a", "b", "c"]);
+        assert!(merge_terms([vec![String::new()]]).is_none());
+    }
+
+    #[test]
+    fn oversized_files_are_rejected() {
+        let content = "a".repeat(MAX_FILE_SIZE + 1);
+        let parsed = parse(ImportFormat::Hexo, file("big.md", &content));
+        assert!(matches!(parsed.as_slice(), [Err(_)]));
+    }
+}
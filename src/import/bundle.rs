@@ -0,0 +1,176 @@
+use super::{ImportError, ImportFile, Select, is_hidden, read_any, to_key};
+use crate::util::percent_decode;
+use bytes::Bytes;
+use std::collections::HashMap;
+use std::path::Path;
+use tokio::task;
+
+/// 上传的打包文件的最大大小
+pub const MAX_BUNDLE_SIZE: usize = 64 * 1024 * 1024;
+/// 打包中单个文件的最大大小, 与资源上传的限制相同
+const MAX_BUNDLE_FILE_SIZE: usize = 20 * 1024 * 1024;
+/// 打包中所有文件解压后的总大小
+const MAX_BUNDLE_EXTRACTED_SIZE: usize = 4 * MAX_BUNDLE_SIZE;
+
+/// 单篇文章的打包, 包含一个markdown文件和它以相对地址引用的图片等文件
+pub struct Bundle {
+    /// markdown文件在压缩包中的路径
+    pub path: String,
+    pub content: Vec<u8>,
+    /// 其他文件, 以相对于压缩包根目录的路径为键
+    pub files: HashMap<String, Vec<u8>>,
+}
+
+impl Bundle {
+    /// 将正文中的地址解析为压缩包中的路径, 地址相对于markdown文件所在的目录;
+    /// 完整的url, 以`/`开头的地址和页内锚点不是压缩包中的文件, 返回空
+    pub fn local_path(&self, url: &str) -> Option<String> {
+        let url = url.trim();
+        let scheme = url
+            .split_once(':')
+            .is_some_and(|(scheme, _)| !scheme.contains('/'));
+        if url.is_empty() || url.starts_with(['/', '#', '?']) || scheme {
+            return None;
+        }
+        let url = url.split(['?', '#']).next().unwrap_or_default();
+        let mut parts: Vec<String> = Path::new(&self.path)
+            .parent()
+            .map(to_key)
+            .filter(|parent| !parent.is_empty())
+            .map(|parent| parent.split('/').map(str::to_string).collect())
+            .unwrap_or_default();
+        for part in percent_decode(url).split('/') {
+            match part {
+                "" | "." => {}
+                // 超出压缩包根目录的地址保留`..`, 之后不会找到对应的文件
+                ".." if parts.last().is_some_and(|last| last != "..") => {
+                    parts.pop();
+                }
+                _ => parts.push(part.to_string()),
+            }
+        }
+        Some(parts.join("/"))
+    }
+}
+
+/// 读取zip, tar或tar.gz格式的打包, 其中必须有且只有一个最外层的markdown文件,
+/// 更深的目录中的markdown文件作为普通文件处理
+pub async fn read_bundle(data: Bytes) -> Result<Bundle, ImportError> {
+    task::spawn_blocking(move || {
+        let accepts = |path: &Path| !is_hidden(path);
+        let files = read_any(
+            &data,
+            &Select {
+                accepts: &accepts,
+                max_size: MAX_BUNDLE_FILE_SIZE,
+                max_total: MAX_BUNDLE_EXTRACTED_SIZE,
+            },
+        )?;
+
+        let is_markdown = |file: &ImportFile| {
+            Path::new(&file.path)
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| {
+                    ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown")
+                })
+        };
+        let depth = |file: &ImportFile| file.path.matches('/').count();
+        let Some(top) = files
+            .iter()
+            .filter(|file| is_markdown(file))
+            .map(depth)
+            .min()
+        else {
+            return Err(ImportError::Archive("压缩包中没有markdown文件".to_string()));
+        };
+        let markdown: Vec<&str> = files
+            .iter()
+            .filter(|file| is_markdown(file) && depth(file) == top)
+            .map(|file| file.path.as_str())
+            .collect();
+        if markdown.len() > 1 {
+            return Err(ImportError::Archive(format!(
+                "压缩包中有多个markdown文件: {}",
+                markdown.join(", ")
+            )));
+        }
+        let path = markdown[0].to_string();
+
+        let mut content = Vec::new();
+        let mut others = HashMap::new();
+        for file in files {
+            if file.path == path {
+                content = file.content;
+            } else {
+                others.insert(file.path, file.content);
+            }
+        }
+        Ok(Bundle {
+            path,
+            content,
+            files: others,
+        })
+    })
+    .await
+    .map_err(|e| ImportError::Archive(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle(path: &str) -> Bundle {
+        Bundle {
+            path: path.to_string(),
+            content: Vec::new(),
+            files: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn local_path_is_relative_to_markdown() {
+        let bundle = bundle("post/index.md");
+        assert_eq!(
+            bundle.local_path("img/a.png").as_deref(),
+            Some("post/img/a.png")
+        );
+        assert_eq!(
+            bundle.local_path(" ./img/a%20b.png?w=100#top ").as_deref(),
+            Some("post/img/a b.png")
+        );
+        assert_eq!(
+            bundle.local_path("../shared/c.png").as_deref(),
+            Some("shared/c.png")
+        );
+        // 超出根目录的地址不会对应到压缩包中的文件
+        assert_eq!(
+            bundle.local_path("../../c.png").as_deref(),
+            Some("../c.png")
+        );
+    }
+
+    #[test]
+    fn local_path_at_root() {
+        let bundle = bundle("index.md");
+        assert_eq!(bundle.local_path("img/a.png").as_deref(), Some("img/a.png"));
+        assert_eq!(bundle.local_path("./a.png").as_deref(), Some("a.png"));
+    }
+
+    #[test]
+    fn local_path_skips_external_urls() {
+        let bundle = bundle("post/index.md");
+        for url in [
+            "",
+            "https://example.com/a.png",
+            "//example.com/a.png",
+            "/images/a.png",
+            "#section",
+            "?page=2",
+            "mailto:me@example.com",
+            "data:image/png;base64,AAAA",
+        ] {
+            assert_eq!(bundle.local_path(url), None, "{}", url);
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use super::{ImportFile, ImportPost, ParseFailure, Scalar, Terms, merge_terms, parse_date};
+use crate::util::{FrontMatter, MARKDOWN_UTIL, slugify};
+use serde::Deserialize;
+
+/// Hexo的front matter, 见<https://hexo.io/docs/front-matter>
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct HexoFrontMatter {
+    title: Option<Scalar>,
+    date: Option<String>,
+    updated: Option<String>,
+    tags: Option<Terms>,
+    /// 多级分类写成嵌套的列表, 每一级都作为标签
+    categories: Option<Terms>,
+    permalink: Option<String>,
+    /// hexo-generator-alias插件使用的旧地址
+    alias: Option<Terms>,
+    published: Option<bool>,
+    excerpt: Option<String>,
+    description: Option<String>,
+}
+
+/// Hexo默认的链接使用文件名作为`:title`, 因此文件名作为slug;
+/// `_drafts`中的文章和`published: false`的文章导入为草稿
+pub(super) fn parse(file: ImportFile) -> Result<ImportPost, ParseFailure> {
+    let (raw, body) = match MARKDOWN_UTIL.parse_as::<HexoFrontMatter>(&file.content) {
+        Ok(parsed) => parsed,
+        Err(e) => return Err(ParseFailure::new(file.path, e)),
+    };
+    let mut warnings = Vec::new();
+    let draft = file.in_dir("_drafts") || raw.published == Some(false);
+    let front_matter = FrontMatter {
+        title: raw.title.map(|title| title.to_string().trim().to_string()),
+        slug: Some(slugify(file.stem())),
+        tags: merge_terms([
+            raw.tags.map(|tags| tags.flatten(false)).unwrap_or_default(),
+            raw.categories
+                .map(|categories| categories.flatten(false))
+                .unwrap_or_default(),
+        ]),
+        date: parse_date(raw.date, "date", &mut warnings),
+        updated: parse_date(raw.updated, "updated", &mut warnings),
+        summary: raw.excerpt.or(raw.description),
+        status: draft.then(|| "draft".to_string()),
+        publish_at: None,
+    };
+    let content = MARKDOWN_UTIL.compose(&front_matter, body);
+
+    let mut post = ImportPost::new(file.path.clone(), front_matter, content, file.stem())?;
+    post.aliases = raw
+        .permalink
+        .into_iter()
+        .chain(
+            raw.alias
+                .map(|alias| alias.flatten(false))
+                .unwrap_or_default(),
+        )
+        .collect();
+    post.warnings = warnings;
+    Ok(post)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::tests::file;
+    use crate::repositories::post::PostStatus;
+
+    #[test]
+    fn categories_and_aliases() {
+        let post = parse(file(
+            "source/_posts/hello-world.md",
+            "---\ntitle: Hello\ndate: 2019-01-02 03:04:05\ntags: [rust, web]\n\
+             categories:\n  - [编程, rust]\n  - 随笔\npermalink: /2019/hello/\n\
+             alias: [old/hello.html, /hello/]\n---\n正文\n",
+        ))
+        .unwrap();
+        assert_eq!(post.post.title, "Hello");
+        assert_eq!(post.post.slug.as_deref(), Some("hello-world"));
+        assert_eq!(post.post.tags, ["rust", "web", "编程", "随笔"]);
+        assert_eq!(post.aliases, ["/2019/hello/", "old/hello.html", "/hello/"]);
+        assert_eq!(post.post.status, None);
+        assert!(post.warnings.is_empty());
+    }
+
+    #[test]
+    fn drafts_and_bad_dates() {
+        let post = parse(file(
+            "source/_drafts/wip.md",
+            "---\ndate: yesterday\n---\n正文\n",
+        ))
+        .unwrap();
+        assert_eq!(post.post.title, "wip");
+        assert_eq!(post.post.status, Some(PostStatus::Draft));
+        assert_eq!(post.post.date, None);
+        assert_eq!(post.warnings.len(), 1);
+
+        let post = parse(file("_posts/a.md", "---\npublished: false\n---\n")).unwrap();
+        assert_eq!(post.post.status, Some(PostStatus::Draft));
+    }
+
+    #[test]
+    fn malformed_front_matter_is_a_failure() {
+        let failure = parse(file("_posts/a.md", "---\ntags: [a\n---\n"))
+            .err()
+            .unwrap();
+        assert_eq!(failure.source, "_posts/a.md");
+    }
+}
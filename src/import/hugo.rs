@@ -0,0 +1,143 @@
+use super::{ImportFile, ImportPost, ParseFailure, Scalar, Terms, merge_terms, parse_date};
+use crate::util::{FrontMatter, MARKDOWN_UTIL, slugify};
+use chrono::Utc;
+use serde::Deserialize;
+
+/// Hugo的front matter, 见<https://gohugo.io/content-management/front-matter/>
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct HugoFrontMatter {
+    title: Option<Scalar>,
+    date: Option<String>,
+    #[serde(alias = "lastMod")]
+    lastmod: Option<String>,
+    #[serde(rename = "publishDate", alias = "publishdate")]
+    publish_date: Option<String>,
+    #[serde(rename = "expiryDate", alias = "expirydate")]
+    expiry_date: Option<String>,
+    draft: Option<bool>,
+    tags: Option<Terms>,
+    categories: Option<Terms>,
+    aliases: Option<Terms>,
+    slug: Option<Scalar>,
+    /// 完整的文章地址, 最后一段作为slug
+    url: Option<String>,
+    summary: Option<String>,
+    description: Option<String>,
+}
+
+/// - `publishDate`在未来的文章导入为定时发布
+/// - 已经过了`expiryDate`的文章Hugo不会生成, 导入为私密文章
+/// - page bundle中的`index.md`使用所在目录的名称作为slug
+pub(super) fn parse(file: ImportFile) -> Result<ImportPost, ParseFailure> {
+    let (raw, body) = match MARKDOWN_UTIL.parse_as::<HugoFrontMatter>(&file.content) {
+        Ok(parsed) => parsed,
+        Err(e) => return Err(ParseFailure::new(file.path, e)),
+    };
+    let mut warnings = Vec::new();
+    let publish_date = parse_date(raw.publish_date, "publishDate", &mut warnings);
+    let expiry_date = parse_date(raw.expiry_date, "expiryDate", &mut warnings);
+    let now = Utc::now();
+
+    let (status, publish_at) = if raw.draft == Some(true) {
+        (Some("draft"), None)
+    } else if expiry_date.is_some_and(|expiry| expiry <= now) {
+        warnings.push("文章已经过期, 导入为私密文章".to_string());
+        (Some("private"), None)
+    } else if publish_date.is_some_and(|publish| publish > now) {
+        (Some("scheduled"), publish_date)
+    } else {
+        (None, None)
+    };
+    let slug = raw
+        .slug
+        .map(|slug| slug.to_string())
+        .or_else(|| {
+            raw.url.as_deref().and_then(|url| {
+                url.trim_end_matches('/')
+                    .rsplit('/')
+                    .next()
+                    .filter(|segment| !segment.is_empty())
+                    .map(str::to_string)
+            })
+        })
+        .unwrap_or_else(|| file.stem().to_string());
+
+    let front_matter = FrontMatter {
+        title: raw.title.map(|title| title.to_string().trim().to_string()),
+        slug: Some(slugify(&slug)),
+        tags: merge_terms([
+            raw.tags.map(|tags| tags.flatten(false)).unwrap_or_default(),
+            raw.categories
+                .map(|categories| categories.flatten(false))
+                .unwrap_or_default(),
+        ]),
+        date: parse_date(raw.date, "date", &mut warnings).or(publish_date),
+        updated: parse_date(raw.lastmod, "lastmod", &mut warnings),
+        summary: raw.summary.or(raw.description),
+        status: status.map(str::to_string),
+        publish_at,
+    };
+    let content = MARKDOWN_UTIL.compose(&front_matter, body);
+
+    let mut post = ImportPost::new(file.path.clone(), front_matter, content, file.stem())?;
+    post.aliases = raw
+        .url
+        .into_iter()
+        .chain(
+            raw.aliases
+                .map(|aliases| aliases.flatten(false))
+                .unwrap_or_default(),
+        )
+        .collect();
+    post.warnings = warnings;
+    Ok(post)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::tests::file;
+    use crate::repositories::post::PostStatus;
+
+    #[test]
+    fn categories_aliases_and_url() {
+        let post = parse(file(
+            "content/posts/hello/index.md",
+            "+++\ntitle = \"Hello\"\ndate = 2019-01-02T03:04:05Z\ntags = [\"rust\"]\n\
+             categories = [\"编程\", \"rust\"]\nurl = \"/blog/hello-world/\"\n\
+             aliases = [\"/old/hello/\"]\n+++\n正文\n",
+        ))
+        .unwrap();
+        assert_eq!(post.post.slug.as_deref(), Some("hello-world"));
+        assert_eq!(post.post.tags, ["rust", "编程"]);
+        assert_eq!(post.aliases, ["/blog/hello-world/", "/old/hello/"]);
+        assert_eq!(post.post.status, None);
+    }
+
+    #[test]
+    fn bundle_directory_is_the_slug() {
+        let post = parse(file(
+            "content/posts/my-post/index.md",
+            "---\ntitle: A\n---\n",
+        ))
+        .unwrap();
+        assert_eq!(post.post.slug.as_deref(), Some("my-post"));
+        assert!(post.aliases.is_empty());
+    }
+
+    #[test]
+    fn publish_and_expiry_dates() {
+        let post = parse(file("content/a.md", "---\npublishDate: 2999-01-01\n---\n")).unwrap();
+        assert_eq!(post.post.status, Some(PostStatus::Scheduled));
+        assert!(post.post.publish_at.is_some());
+        assert_eq!(post.post.date, post.post.publish_at);
+
+        let post = parse(file("content/b.md", "---\nexpiryDate: 2000-01-01\n---\n")).unwrap();
+        assert_eq!(post.post.status, Some(PostStatus::Private));
+        assert_eq!(post.warnings.len(), 1);
+
+        let post = parse(file("content/c.md", "---\ndraft: true\n---\n")).unwrap();
+        assert_eq!(post.post.status, Some(PostStatus::Draft));
+    }
+}
@@ -0,0 +1,142 @@
+use super::{ImportFile, ImportPost, ParseFailure, Scalar, Terms, merge_terms, parse_date};
+use crate::util::{FrontMatter, MARKDOWN_UTIL, slugify};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
+
+/// Jekyll的front matter, 见<https://jekyllrb.com/docs/front-matter/>
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct JekyllFrontMatter {
+    title: Option<Scalar>,
+    date: Option<String>,
+    /// jekyll-last-modified-at插件使用的修改时间
+    last_modified_at: Option<String>,
+    categories: Option<Terms>,
+    category: Option<Terms>,
+    tags: Option<Terms>,
+    tag: Option<Terms>,
+    permalink: Option<String>,
+    /// jekyll-redirect-from插件使用的旧地址
+    redirect_from: Option<Terms>,
+    published: Option<bool>,
+    slug: Option<Scalar>,
+    excerpt: Option<String>,
+    description: Option<String>,
+}
+
+/// - `_posts`中的文件名为`YYYY-MM-DD-slug.md`, front matter中没有日期时使用文件名中的日期
+/// - 分类和标签写成字符串时以空白分隔
+/// - `_drafts`中的文章和`published: false`的文章导入为草稿
+pub(super) fn parse(file: ImportFile) -> Result<ImportPost, ParseFailure> {
+    let (raw, body) = match MARKDOWN_UTIL.parse_as::<JekyllFrontMatter>(&file.content) {
+        Ok(parsed) => parsed,
+        Err(e) => return Err(ParseFailure::new(file.path, e)),
+    };
+    let mut warnings = Vec::new();
+    let (name_date, name) = split_name(file.stem());
+    let draft = file.in_dir("_drafts") || raw.published == Some(false);
+    let slug = raw
+        .slug
+        .map(|slug| slug.to_string())
+        .unwrap_or_else(|| name.to_string());
+    let terms = |terms: Option<Terms>| terms.map(|terms| terms.flatten(true)).unwrap_or_default();
+
+    let front_matter = FrontMatter {
+        title: raw.title.map(|title| title.to_string().trim().to_string()),
+        slug: Some(slugify(&slug)),
+        tags: merge_terms([
+            terms(raw.tags),
+            terms(raw.tag),
+            terms(raw.categories),
+            terms(raw.category),
+        ]),
+        date: parse_date(raw.date, "date", &mut warnings).or(name_date),
+        updated: parse_date(raw.last_modified_at, "last_modified_at", &mut warnings),
+        summary: raw.excerpt.or(raw.description),
+        status: draft.then(|| "draft".to_string()),
+        publish_at: None,
+    };
+    let content = MARKDOWN_UTIL.compose(&front_matter, body);
+
+    let mut post = ImportPost::new(file.path.clone(), front_matter, content, &titleize(name))?;
+    post.aliases = raw
+        .permalink
+        .into_iter()
+        .chain(
+            raw.redirect_from
+                .map(|redirects| redirects.flatten(false))
+                .unwrap_or_default(),
+        )
+        .collect();
+    post.warnings = warnings;
+    Ok(post)
+}
+
+/// 拆分`YYYY-MM-DD-slug`格式的文件名, 不符合格式时没有日期
+fn split_name(stem: &str) -> (Option<DateTime<Utc>>, &str) {
+    let date = stem
+        .get(..10)
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .and_then(|date| date.and_hms_opt(0, 0, 0));
+    match (date, stem.get(10..11), stem.get(11..)) {
+        (Some(date), Some("-"), Some(name)) if !name.is_empty() => (Some(date.and_utc()), name),
+        _ => (None, stem),
+    }
+}
+
+/// 与Jekyll相同, 没有标题时将slug中的单词首字母大写作为标题
+fn titleize(name: &str) -> String {
+    name.split('-')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::tests::file;
+    use crate::repositories::post::PostStatus;
+
+    #[test]
+    fn categories_and_permalink() {
+        let post = parse(file(
+            "_posts/2019-01-02-hello-world.md",
+            "---\ncategory: 随笔\ncategories: rust web\ntags: [rust, 编程]\n\
+             permalink: /hello/\nredirect_from:\n  - /old/hello/\n  - /2019/hello.html\n---\n正文\n",
+        ))
+        .unwrap();
+        assert_eq!(post.post.title, "Hello World");
+        assert_eq!(post.post.slug.as_deref(), Some("hello-world"));
+        assert_eq!(post.post.tags, ["rust", "编程", "web", "随笔"]);
+        assert_eq!(post.aliases, ["/hello/", "/old/hello/", "/2019/hello.html"]);
+        assert_eq!(
+            post.post.date,
+            NaiveDate::from_ymd_opt(2019, 1, 2)
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map(|date| date.and_utc())
+        );
+    }
+
+    #[test]
+    fn drafts_without_a_date() {
+        let post = parse(file("_drafts/work-in-progress.md", "---\n---\n正文\n")).unwrap();
+        assert_eq!(post.post.title, "Work In Progress");
+        assert_eq!(post.post.status, Some(PostStatus::Draft));
+        assert_eq!(post.post.date, None);
+    }
+
+    #[test]
+    fn split_name_requires_a_full_date() {
+        assert_eq!(split_name("2019-01-02-a").1, "a");
+        assert_eq!(split_name("2019-01-02").1, "2019-01-02");
+        assert_eq!(split_name("2019-13-02-a"), (None, "2019-13-02-a"));
+    }
+}
@@ -0,0 +1,12 @@
+use super::{ImportFile, ImportPost, ParseFailure};
+use crate::util::MARKDOWN_UTIL;
+
+/// 本项目的front matter格式, 文件内容原样保存
+pub(super) fn parse(file: ImportFile) -> Result<ImportPost, ParseFailure> {
+    let front_matter = match MARKDOWN_UTIL.parse(&file.content) {
+        Ok(parsed) => parsed.front_matter,
+        Err(e) => return Err(ParseFailure::new(file.path, e)),
+    };
+    let stem = file.stem().to_string();
+    ImportPost::new(file.path, front_matter, file.content, &stem)
+}
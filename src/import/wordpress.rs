@@ -0,0 +1,395 @@
+use super::{ImportComment, ImportFile, ImportPost, ParseFailure, merge_terms};
+use crate::util::{FrontMatter, MARKDOWN_UTIL, percent_decode, slugify};
+use chrono::{DateTime, Utc};
+use quick_xml::Reader;
+use quick_xml::errors::IllFormedError;
+use quick_xml::escape::resolve_xml_entity;
+use quick_xml::events::Event;
+
+/// WXR中的一个`<item>`, 字段都是原始的文本
+#[derive(Default)]
+struct Item {
+    id: String,
+    title: String,
+    link: String,
+    content: String,
+    excerpt: String,
+    date: String,
+    date_gmt: String,
+    modified: String,
+    modified_gmt: String,
+    name: String,
+    status: String,
+    kind: String,
+    tags: Vec<String>,
+    categories: Vec<String>,
+    comments: Vec<Comment>,
+}
+
+#[derive(Default)]
+struct Comment {
+    id: String,
+    parent: String,
+    author: String,
+    content: String,
+    date: String,
+    date_gmt: String,
+    approved: String,
+    kind: String,
+}
+
+/// 解析WordPress导出的WXR文件, 只导入文章(不包括页面和附件), 回收站中的文章被跳过
+///
+/// 正文保持WordPress保存的html, markdown渲染时会原样输出
+pub(super) fn parse(file: ImportFile) -> Vec<Result<ImportPost, ParseFailure>> {
+    let Ok(text) = std::str::from_utf8(&file.content) else {
+        return vec![Err(ParseFailure::new(
+            file.path,
+            "文件内容不是有效的UTF-8编码",
+        ))];
+    };
+    match read_items(text) {
+        Ok(items) => items
+            .into_iter()
+            .filter_map(|item| item.into_post(&file.path))
+            .collect(),
+        Err(e) => vec![Err(ParseFailure::new(file.path, e))],
+    }
+}
+
+fn read_items(text: &str) -> Result<Vec<Item>, quick_xml::Error> {
+    let mut reader = Reader::from_str(text);
+    let mut items = Vec::new();
+    let mut item: Option<Item> = None;
+    let mut comment: Option<Comment> = None;
+    // 当前`<category>`的`domain`属性, 区分标签和分类
+    let mut domain: Option<String> = None;
+    let mut value = String::new();
+    // 没有结束的元素, 文件被截断时reader不会报错
+    let mut open: Vec<String> = Vec::new();
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => {
+                open.push(String::from_utf8_lossy(e.name().as_ref()).into_owned());
+                match e.name().as_ref() {
+                    b"item" => item = Some(Item::default()),
+                    b"wp:comment" if item.is_some() => comment = Some(Comment::default()),
+                    b"category" => {
+                        domain = match e.try_get_attribute("domain")? {
+                            Some(domain) => Some(domain.unescape_value()?.into_owned()),
+                            None => None,
+                        }
+                    }
+                    _ => {}
+                }
+                value.clear();
+            }
+            Event::Text(e) => value.push_str(&e.xml_content()?),
+            Event::CData(e) => value.push_str(&e.decode()?),
+            Event::GeneralRef(e) => match e.resolve_char_ref()? {
+                Some(c) => value.push(c),
+                None => {
+                    let name = e.decode()?;
+                    match resolve_xml_entity(&name) {
+                        Some(resolved) => value.push_str(resolved),
+                        None => value.push_str(&format!("&{};", name)),
+                    }
+                }
+            },
+            Event::End(e) => {
+                open.pop();
+                let value = std::mem::take(&mut value);
+                let name = e.name();
+                let name = name.as_ref();
+                if name == b"wp:comment" {
+                    if let (Some(item), Some(comment)) = (item.as_mut(), comment.take()) {
+                        item.comments.push(comment);
+                    }
+                } else if let Some(comment) = comment.as_mut() {
+                    comment.set(name, value);
+                } else if name == b"item" {
+                    items.extend(item.take());
+                } else if let Some(item) = item.as_mut() {
+                    match name {
+                        b"category" => item.add_term(domain.take().as_deref(), value),
+                        _ => item.set(name, value),
+                    }
+                }
+            }
+            Event::Eof => match open.pop() {
+                Some(name) => return Err(IllFormedError::MissingEndTag(name).into()),
+                None => break,
+            },
+            _ => {}
+        }
+    }
+    Ok(items)
+}
+
+impl Item {
+    fn set(&mut self, name: &[u8], value: String) {
+        match name {
+            b"title" => self.title = value,
+            b"link" => self.link = value,
+            b"content:encoded" => self.content = value,
+            b"excerpt:encoded" => self.excerpt = value,
+            b"wp:post_id" => self.id = value,
+            b"wp:post_date" => self.date = value,
+            b"wp:post_date_gmt" => self.date_gmt = value,
+            b"wp:post_modified" => self.modified = value,
+            b"wp:post_modified_gmt" => self.modified_gmt = value,
+            b"wp:post_name" => self.name = value,
+            b"wp:status" => self.status = value,
+            b"wp:post_type" => self.kind = value,
+            _ => {}
+        }
+    }
+
+    /// 默认的"未分类"不作为标签
+    fn add_term(&mut self, domain: Option<&str>, value: String) {
+        let value = value.trim().to_string();
+        match domain {
+            Some("post_tag") => self.tags.push(value),
+            Some("category") if !matches!(value.as_str(), "Uncategorized" | "未分类") => {
+                self.categories.push(value)
+            }
+            _ => {}
+        }
+    }
+
+    /// 不需要导入的内容返回空
+    fn into_post(self, path: &str) -> Option<Result<ImportPost, ParseFailure>> {
+        if self.kind != "post" {
+            return None;
+        }
+        let status = match self.status.as_str() {
+            "publish" => "published",
+            "future" => "scheduled",
+            "draft" | "pending" => "draft",
+            "private" => "private",
+            _ => return None,
+        };
+        let source = format!("{}#{}", path, self.id);
+        let date = wp_date(&self.date_gmt, &self.date);
+        let name = percent_decode(self.name.trim());
+
+        let (comments, skipped): (Vec<_>, Vec<_>) =
+            self.comments.into_iter().partition(|comment| {
+                comment.approved == "1" && matches!(comment.kind.as_str(), "" | "comment")
+            });
+        let mut warnings = Vec::new();
+        if !skipped.is_empty() {
+            warnings.push(format!(
+                "跳过了{}条未审核的评论, 垃圾评论或引用通告",
+                skipped.len()
+            ));
+        }
+
+        let front_matter = FrontMatter {
+            title: Some(self.title.trim().to_string()).filter(|title| !title.is_empty()),
+            slug: Some(name.as_str())
+                .filter(|name| !name.is_empty())
+                .map(slugify),
+            tags: merge_terms([self.tags, self.categories]),
+            date,
+            updated: wp_date(&self.modified_gmt, &self.modified),
+            summary: Some(self.excerpt.trim().to_string()).filter(|excerpt| !excerpt.is_empty()),
+            status: Some(status.to_string()),
+            publish_at: if status == "scheduled" { date } else { None },
+        };
+        let content = MARKDOWN_UTIL.compose(&front_matter, &self.content);
+        let fallback_title = if name.is_empty() {
+            format!("post-{}", self.id)
+        } else {
+            name
+        };
+
+        let mut post = match ImportPost::new(source, front_matter, content, &fallback_title) {
+            Ok(post) => post,
+            Err(e) => return Some(Err(e)),
+        };
+        post.aliases = vec![self.link];
+        post.comments = comments
+            .into_iter()
+            .filter_map(|comment| {
+                let author = comment.author.trim();
+                Some(ImportComment {
+                    id: comment.id.trim().parse().ok()?,
+                    parent: comment
+                        .parent
+                        .trim()
+                        .parse()
+                        .ok()
+                        .filter(|parent| *parent != 0),
+                    author: if author.is_empty() {
+                        "匿名".to_string()
+                    } else {
+                        author.to_string()
+                    },
+                    created_at: wp_date(&comment.date_gmt, &comment.date),
+                    content: comment.content,
+                })
+            })
+            .collect();
+        post.warnings = warnings;
+        Some(Ok(post))
+    }
+}
+
+impl Comment {
+    fn set(&mut self, name: &[u8], value: String) {
+        match name {
+            b"wp:comment_id" => self.id = value,
+            b"wp:comment_parent" => self.parent = value,
+            b"wp:comment_author" => self.author = value,
+            b"wp:comment_content" => self.content = value,
+            b"wp:comment_date" => self.date = value,
+            b"wp:comment_date_gmt" => self.date_gmt = value,
+            b"wp:comment_approved" => self.approved = value,
+            b"wp:comment_type" => self.kind = value,
+            _ => {}
+        }
+    }
+}
+
+/// 优先使用UTC时间, 草稿的UTC时间为`0000-00-00 00:00:00`, 此时使用站点时间
+fn wp_date(gmt: &str, local: &str) -> Option<DateTime<Utc>> {
+    [gmt, local]
+        .into_iter()
+        .map(str::trim)
+        .filter(|date| !date.is_empty() && !date.starts_with("0000"))
+        .find_map(|date| MARKDOWN_UTIL.parse_date(date).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::tests::file;
+    use crate::repositories::post::PostStatus;
+
+    const WXR: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:content="http://purl.org/rss/1.0/modules/content/"
+    xmlns:excerpt="http://wordpress.org/export/1.2/excerpt/"
+    xmlns:wp="http://wordpress.org/export/1.2/">
+<channel>
+<item>
+    <title>Hello &amp; welcome</title>
+    <link>https://example.com/2019/01/hello/</link>
+    <content:encoded><![CDATA[<p>正文</p>]]></content:encoded>
+    <excerpt:encoded><![CDATA[]]></excerpt:encoded>
+    <wp:post_id>12</wp:post_id>
+    <wp:post_date>2019-01-02 11:04:05</wp:post_date>
+    <wp:post_date_gmt>2019-01-02 03:04:05</wp:post_date_gmt>
+    <wp:post_name>%e4%bd%a0%e5%a5%bd</wp:post_name>
+    <wp:status>publish</wp:status>
+    <wp:post_type>post</wp:post_type>
+    <category domain="category" nicename="uncategorized"><![CDATA[Uncategorized]]></category>
+    <category domain="category" nicename="code"><![CDATA[编程]]></category>
+    <category domain="post_tag" nicename="rust"><![CDATA[rust]]></category>
+    <wp:comment>
+        <wp:comment_id>1</wp:comment_id>
+        <wp:comment_author><![CDATA[alice]]></wp:comment_author>
+        <wp:comment_date_gmt>2019-01-03 00:00:00</wp:comment_date_gmt>
+        <wp:comment_content><![CDATA[第一条]]></wp:comment_content>
+        <wp:comment_approved>1</wp:comment_approved>
+        <wp:comment_type><![CDATA[comment]]></wp:comment_type>
+        <wp:comment_parent>0</wp:comment_parent>
+    </wp:comment>
+    <wp:comment>
+        <wp:comment_id>2</wp:comment_id>
+        <wp:comment_author><![CDATA[]]></wp:comment_author>
+        <wp:comment_content><![CDATA[回复]]></wp:comment_content>
+        <wp:comment_approved>1</wp:comment_approved>
+        <wp:comment_type><![CDATA[]]></wp:comment_type>
+        <wp:comment_parent>1</wp:comment_parent>
+    </wp:comment>
+    <wp:comment>
+        <wp:comment_id>3</wp:comment_id>
+        <wp:comment_content><![CDATA[spam]]></wp:comment_content>
+        <wp:comment_approved>spam</wp:comment_approved>
+        <wp:comment_parent>0</wp:comment_parent>
+    </wp:comment>
+</item>
+<item>
+    <title>About</title>
+    <wp:post_id>13</wp:post_id>
+    <wp:status>publish</wp:status>
+    <wp:post_type>page</wp:post_type>
+</item>
+<item>
+    <title>Deleted</title>
+    <wp:post_id>14</wp:post_id>
+    <wp:status>trash</wp:status>
+    <wp:post_type>post</wp:post_type>
+</item>
+<item>
+    <title></title>
+    <wp:post_id>15</wp:post_id>
+    <wp:post_date>2019-02-01 08:00:00</wp:post_date>
+    <wp:post_date_gmt>0000-00-00 00:00:00</wp:post_date_gmt>
+    <wp:status>draft</wp:status>
+    <wp:post_type>post</wp:post_type>
+</item>
+</channel>
+</rss>"#;
+
+    fn posts(content: &str) -> Vec<Result<ImportPost, ParseFailure>> {
+        parse(file("blog.xml", content))
+    }
+
+    #[test]
+    fn posts_and_terms() {
+        let posts: Vec<_> = posts(WXR).into_iter().map(Result::unwrap).collect();
+        assert_eq!(posts.len(), 2);
+
+        let post = &posts[0];
+        assert_eq!(post.source, "blog.xml#12");
+        assert_eq!(post.post.title, "Hello & welcome");
+        assert_eq!(post.post.slug.as_deref(), Some(slugify("你好").as_str()));
+        assert_eq!(post.post.tags, ["rust", "编程"]);
+        assert_eq!(post.post.status, Some(PostStatus::Published));
+        assert_eq!(post.aliases, ["https://example.com/2019/01/hello/"]);
+        assert_eq!(
+            post.post.date,
+            MARKDOWN_UTIL.parse_date("2019-01-02 03:04:05").ok()
+        );
+
+        let draft = &posts[1];
+        assert_eq!(draft.post.title, "post-15");
+        assert_eq!(draft.post.status, Some(PostStatus::Draft));
+        assert_eq!(
+            draft.post.date,
+            MARKDOWN_UTIL.parse_date("2019-02-01 08:00:00").ok()
+        );
+    }
+
+    #[test]
+    fn comment_threads() {
+        let post = posts(WXR).remove(0).unwrap();
+        let threads: Vec<_> = post
+            .comments
+            .iter()
+            .map(|comment| (comment.id, comment.parent, comment.author.as_str()))
+            .collect();
+        assert_eq!(threads, [(1, None, "alice"), (2, Some(1), "匿名")]);
+        assert_eq!(post.comments[1].content, "回复");
+        assert_eq!(post.warnings.len(), 1);
+    }
+
+    #[test]
+    fn malformed_xml_is_a_failure() {
+        let broken = WXR.replace("</wp:post_name>", "</wp:post_title>");
+        let parsed = posts(&broken);
+        assert!(matches!(parsed.as_slice(), [Err(failure)] if failure.source == "blog.xml"));
+
+        let truncated = &WXR[..WXR.find("<wp:comment>").unwrap()];
+        assert!(matches!(posts(truncated).as_slice(), [Err(_)]));
+
+        let invalid_utf8 = ImportFile {
+            path: "blog.xml".to_string(),
+            content: vec![0xff, 0xfe],
+        };
+        assert!(matches!(parse(invalid_utf8).as_slice(), [Err(_)]));
+    }
+}
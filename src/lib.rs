@@ -1,9 +1,16 @@
+pub mod backup;
+pub mod cli;
 pub mod config;
 pub mod database;
+pub mod embedding;
+pub mod imaging;
+pub mod import;
 pub mod models;
 pub mod repositories;
 pub mod router;
+pub mod scheduler;
 pub mod serve;
 pub mod service;
 pub mod state;
+pub mod storage;
 pub mod util;
@@ -1,5 +1,6 @@
-use blog_backend::serve;
+use blog_backend::cli;
+use std::process::ExitCode;
 #[tokio::main]
-async fn main() {
-    serve::serve().await;
+async fn main() -> ExitCode {
+    cli::run().await
 }
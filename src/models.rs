@@ -1,7 +1,12 @@
 mod response;
 pub use response::*;
+pub mod asset;
+pub mod auth;
+pub mod backup;
 pub mod comment;
+pub mod import;
 pub mod post;
+pub mod user;
 
 #[derive(serde::Deserialize)]
 pub struct Pagenigation {
@@ -0,0 +1,95 @@
+use crate::imaging::OutputFormat;
+use crate::repositories::asset::Asset;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize)]
+pub struct AssetRead {
+    pub id: i32,
+    /// 在文章中引用资源使用的地址
+    pub url: String,
+    pub mime: String,
+    pub size: i64,
+    pub filename: String,
+    pub hash: String,
+    pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<i32>,
+    /// 可以缩放的图片的各个格式, 按avif, webp, 原格式排列, 可以直接用于`<picture>`中的`<source>`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<AssetSource>,
+}
+impl AssetRead {
+    /// `widths`为可以请求的图片宽度, 只使用小于原图宽度的部分
+    pub fn new(value: Asset, widths: &[u32]) -> Self {
+        let sources = match (OutputFormat::from_mime(&value.mime), value.width) {
+            (Some(original), Some(width)) => {
+                let mut formats = vec![OutputFormat::Avif, OutputFormat::Webp];
+                if !formats.contains(&original) {
+                    formats.push(original);
+                }
+                formats
+                    .into_iter()
+                    .map(|format| AssetSource::new(value.id, width, widths, format))
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+        Self {
+            url: format!("/asset/{}", value.id),
+            id: value.id,
+            mime: value.mime,
+            size: value.size,
+            filename: value.filename,
+            hash: value.hash,
+            created_at: value.created_at.to_string(),
+            width: value.width,
+            height: value.height,
+            sources,
+        }
+    }
+}
+#[derive(Serialize)]
+pub struct AssetSource {
+    pub mime: &'static str,
+    /// 如`/asset/1?w=480&format=webp 480w, /asset/1?format=webp 800w`
+    pub srcset: String,
+}
+impl AssetSource {
+    fn new(id: i32, original_width: i32, widths: &[u32], format: OutputFormat) -> Self {
+        let mut srcset: Vec<String> = widths
+            .iter()
+            .filter(|width| i64::from(**width) < i64::from(original_width))
+            .map(|width| format!("/asset/{}?w={}&format={} {}w", id, width, format, width))
+            .collect();
+        srcset.push(format!(
+            "/asset/{}?format={} {}w",
+            id, format, original_width
+        ));
+        Self {
+            mime: format.mime(),
+            srcset: srcset.join(", "),
+        }
+    }
+}
+/// 请求缩放或转换格式的图片, 都为空时返回原图
+#[derive(Deserialize)]
+pub struct AssetVariantQuery {
+    pub w: Option<u32>,
+    pub format: Option<OutputFormat>,
+}
+/// 清理没有被引用的资源, `dry_run`为`true`时只列出将要删除的资源
+#[derive(Deserialize)]
+pub struct AssetCleanup {
+    #[serde(default)]
+    pub dry_run: bool,
+}
+/// 为升级之前上传的图片补充尺寸的结果
+#[derive(Debug)]
+pub struct AssetBackfill {
+    pub id: i32,
+    pub filename: String,
+    /// 去除元数据后的资源, 失败时为错误信息
+    pub outcome: Result<Asset, String>,
+}
@@ -0,0 +1,74 @@
+use crate::repositories::session::Session;
+use crate::repositories::user::Role;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct LoginRequest {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Serialize)]
+pub struct TokenRead {
+    pub access_token: String,
+    /// 总是`Bearer`, 请求时放在`Authorization`头中
+    pub token_type: &'static str,
+    /// 访问令牌的有效期, 单位为秒
+    pub expires_in: i64,
+    /// 用于获取新的访问令牌, 只能使用一次, 每次刷新都会返回新的刷新令牌
+    pub refresh_token: String,
+    /// 刷新令牌的有效期, 单位为秒
+    pub refresh_expires_in: i64,
+}
+
+/// 令牌中保存的信息
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    /// 用户id
+    pub sub: i32,
+    pub name: String,
+    pub role: Role,
+    /// 签发令牌的会话id, 会话被撤销后令牌立即失效
+    pub sid: i32,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+/// 登录请求的设备信息
+#[derive(Debug, Default)]
+pub struct Device {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SessionRead {
+    pub id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    pub created_at: String,
+    pub last_used_at: String,
+    pub expires_at: String,
+    /// 是否是发起请求的会话
+    pub current: bool,
+}
+impl SessionRead {
+    pub fn new(value: Session, current: Option<i32>) -> Self {
+        Self {
+            current: current == Some(value.id),
+            id: value.id,
+            user_agent: value.user_agent,
+            ip: value.ip,
+            created_at: value.created_at.to_string(),
+            last_used_at: value.last_used_at.to_string(),
+            expires_at: value.expires_at.to_string(),
+        }
+    }
+}
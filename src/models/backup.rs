@@ -0,0 +1,18 @@
+/// 从备份恢复的结果, 试运行时是将要恢复的数量
+#[derive(Debug, Default)]
+pub struct RestoreReport {
+    pub dry_run: bool,
+    pub posts: usize,
+    pub comments: usize,
+    pub aliases: usize,
+    pub assets: usize,
+    /// 导致无法恢复的问题, 检查阶段发现问题时没有写入任何内容
+    pub errors: Vec<String>,
+    /// 恢复时被跳过的内容, 如文章已经不存在的评论
+    pub warnings: Vec<String>,
+}
+impl RestoreReport {
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
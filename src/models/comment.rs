@@ -22,6 +22,9 @@ pub struct CommentRead {
     pub content: String,
     pub created_at: String,
     pub parent_id: Option<i32>,
+    /// 登录用户发表的评论的用户id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<i32>,
 }
 
 impl From<Comment> for CommentRead {
@@ -33,6 +36,7 @@ impl From<Comment> for CommentRead {
             content: value.content,
             created_at: value.created_at.to_string(),
             parent_id: value.parent_id,
+            user_id: value.user_id,
         }
     }
 }
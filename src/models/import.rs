@@ -0,0 +1,49 @@
+use serde::Serialize;
+
+/// 批量导入的结果, 每个文件一条记录
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub duplicated: usize,
+    pub failed: usize,
+    pub files: Vec<ImportFileReport>,
+}
+impl ImportReport {
+    pub fn push(&mut self, path: String, outcome: ImportOutcome) {
+        match outcome {
+            ImportOutcome::Imported { .. } => self.imported += 1,
+            ImportOutcome::Duplicate { .. } => self.duplicated += 1,
+            ImportOutcome::Failed { .. } => self.failed += 1,
+        }
+        self.files.push(ImportFileReport { path, outcome });
+    }
+}
+#[derive(Debug, Serialize)]
+pub struct ImportFileReport {
+    pub path: String,
+    #[serde(flatten)]
+    pub outcome: ImportOutcome,
+}
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum ImportOutcome {
+    Imported {
+        id: i32,
+        title: String,
+        /// 一起导入的评论数量
+        comments: usize,
+        /// 导入时被忽略或修改的内容, 如无法识别的日期, 已经被使用的旧地址
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        warnings: Vec<String>,
+    },
+    /// 内容与已有的文章(`post_id`)或同一批中之前的文件(`path`)相同, 没有导入
+    Duplicate {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        post_id: Option<i32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        path: Option<String>,
+    },
+    Failed {
+        error: String,
+    },
+}
@@ -1,11 +1,61 @@
-use crate::repositories::post::PostMeta;
+use crate::repositories::post::{PostMeta, PostSearchHit, PostStatus};
+use crate::repositories::revision::{Revision, RevisionContent};
+use crate::util::TocEntry;
 use serde;
 use serde::{Deserialize, Serialize};
+use sqlx::types::chrono::{DateTime, Utc};
+use std::fmt;
 #[derive(Default)]
 pub struct PostCreate {
     pub title: String,
+    /// 为空时由标题生成
+    pub slug: Option<String>,
     pub tags: Vec<String>,
     pub content: Vec<u8>,
+    /// front matter中的`date`, 为空时使用当前时间
+    pub date: Option<DateTime<Utc>>,
+    /// front matter中的`updated`, 为空时使用当前时间
+    pub updated: Option<DateTime<Utc>>,
+    pub summary: Option<String>,
+    /// 为空时根据`publish_at`判断是否为定时发布
+    pub status: Option<PostStatus>,
+    pub publish_at: Option<DateTime<Utc>>,
+    /// 记录到历史版本中的作者和修改说明
+    pub author: Option<String>,
+    pub message: Option<String>,
+}
+/// 更新文章时提交的字段, 为空的字段保持不变
+#[derive(Default)]
+pub struct PostUpdate {
+    pub title: Option<String>,
+    pub slug: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub content: Option<Vec<u8>>,
+    pub status: Option<PostStatus>,
+    pub publish_at: Option<DateTime<Utc>>,
+    /// 记录到历史版本中的作者和修改说明
+    pub author: Option<String>,
+    pub message: Option<String>,
+}
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentFormat {
+    /// 原始的markdown文件
+    #[default]
+    Markdown,
+    /// 服务端渲染的html, 不包含front matter
+    Html,
+}
+#[derive(Deserialize)]
+pub struct PostRead {
+    #[serde(default)]
+    pub format: ContentFormat,
+}
+#[derive(Deserialize)]
+pub struct PostDelete {
+    /// 为`true`时永久删除, 否则移入回收站
+    #[serde(default)]
+    pub permanent: bool,
 }
 #[derive(Serialize)]
 pub struct PostId {
@@ -16,6 +66,32 @@ impl PostId {
         return Self { id };
     }
 }
+#[derive(Debug, Default, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// 基于分词的全文检索
+    #[default]
+    Keyword,
+    /// 基于嵌入向量的语义检索
+    Semantic,
+    /// 使用倒数排序融合合并以上两种结果
+    Hybrid,
+}
+/// 搜索参数, 结果按相关度排序, 因此使用偏移量分页
+#[derive(Deserialize)]
+pub struct PostSearch {
+    pub q: String,
+    #[serde(default)]
+    pub mode: SearchMode,
+    #[serde(default)]
+    pub offset: i32,
+    #[serde(default = "super::default_page_size")]
+    pub page_size: i32,
+    /// 以下参数只在混合搜索时生效, 为空时使用配置文件中的值
+    pub k: Option<f32>,
+    pub keyword_weight: Option<f32>,
+    pub semantic_weight: Option<f32>,
+}
 #[derive(Deserialize)]
 pub struct RowTags {
     pub tags: String,
@@ -35,43 +111,244 @@ impl TryFrom<RowTags> for Tags {
 pub struct PostMetaRead {
     id: i32,
     title: String,
+    slug: String,
     tags: Vec<String>,
     count: i32,
+    word_count: i32,
+    char_count: i32,
+    /// 预计阅读时间, 单位为分钟
+    reading_time: i32,
+    toc: Vec<TocEntry>,
+    excerpt: String,
+    status: PostStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    publish_at: Option<String>,
     first_publish: String,
     last_modify: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author_id: Option<i32>,
 }
 impl From<PostMeta> for PostMetaRead {
     fn from(value: PostMeta) -> Self {
         return Self {
             id: value.id,
             title: value.title,
+            slug: value.slug,
             tags: value.tags.0,
             count: value.count,
+            word_count: value.word_count,
+            char_count: value.char_count,
+            reading_time: value.reading_time,
+            toc: value.toc.0,
+            excerpt: value.excerpt,
+            status: value.status,
+            publish_at: value.publish_at.map(|date| date.to_string()),
             first_publish: value.first_publish.to_string(),
             last_modify: value.last_modify.to_string(),
+            author_id: value.author_id,
         };
     }
 }
+/// 上传或更新文章的结果, 以打包上传时附带引用文件的警告
+#[derive(Serialize)]
+pub struct PostUploadRead {
+    #[serde(flatten)]
+    pub post: PostMetaRead,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+/// 混合搜索中每个结果的得分构成, 排名从1开始, 没有出现在某一列表中时为空
+#[derive(Serialize, Default, Debug)]
+pub struct SearchExplain {
+    pub keyword_rank: Option<usize>,
+    pub keyword_score: Option<f32>,
+    pub keyword_rrf: f32,
+    pub semantic_rank: Option<usize>,
+    pub semantic_score: Option<f32>,
+    pub semantic_rrf: f32,
+}
+#[derive(Serialize)]
+pub struct PostSearchRead {
+    #[serde(flatten)]
+    meta: PostMetaRead,
+    score: f32,
+    /// 转义后的html, 关键词搜索命中的词语用`<mark>`标出
+    snippet: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    explain: Option<SearchExplain>,
+}
+impl PostSearchRead {
+    pub fn with_explain(hit: PostSearchHit, explain: SearchExplain) -> Self {
+        Self {
+            explain: Some(explain),
+            ..hit.into()
+        }
+    }
+}
+impl From<PostSearchHit> for PostSearchRead {
+    fn from(value: PostSearchHit) -> Self {
+        Self {
+            meta: value.meta.into(),
+            score: value.score,
+            snippet: value.snippet,
+            explain: None,
+        }
+    }
+}
 #[derive(Serialize)]
 pub struct Post {
     id: i32,
     title: String,
+    slug: String,
     tags: Vec<String>,
     content: String,
+    format: ContentFormat,
     count: i32,
+    word_count: i32,
+    char_count: i32,
+    reading_time: i32,
+    toc: Vec<TocEntry>,
     first_publish: String,
     last_modify: String,
 }
 impl Post {
-    pub fn with_content(meta: PostMeta, content: String) -> Self {
+    pub fn with_content(meta: PostMeta, content: String, format: ContentFormat) -> Self {
         return Self {
             id: meta.id,
             title: meta.title,
+            slug: meta.slug,
             tags: meta.tags.0,
             content,
+            format,
             count: meta.count,
+            word_count: meta.word_count,
+            char_count: meta.char_count,
+            reading_time: meta.reading_time,
+            toc: meta.toc.0,
             first_publish: meta.first_publish.to_string(),
             last_modify: meta.last_modify.to_string(),
         };
     }
 }
+#[derive(Serialize)]
+pub struct RevisionRead {
+    revision: i32,
+    title: String,
+    slug: String,
+    tags: Vec<String>,
+    content_hash: String,
+    author: Option<String>,
+    message: Option<String>,
+    created_at: String,
+}
+impl From<Revision> for RevisionRead {
+    fn from(value: Revision) -> Self {
+        Self {
+            revision: value.revision,
+            title: value.title,
+            slug: value.slug,
+            tags: value.tags.0,
+            content_hash: value.content_hash,
+            author: value.author,
+            message: value.message,
+            created_at: value.created_at.to_string(),
+        }
+    }
+}
+#[derive(Serialize)]
+pub struct RevisionContentRead {
+    #[serde(flatten)]
+    meta: RevisionRead,
+    content: String,
+}
+impl From<RevisionContent> for RevisionContentRead {
+    fn from(value: RevisionContent) -> Self {
+        Self {
+            meta: value.meta.into(),
+            content: value.content,
+        }
+    }
+}
+/// 比较`from`和`to`两个版本
+#[derive(Deserialize)]
+pub struct RevisionDiffQuery {
+    pub from: i32,
+    pub to: i32,
+}
+#[derive(Serialize)]
+pub struct RevisionDiff {
+    pub from: i32,
+    pub to: i32,
+    /// unified diff格式的差异, 两个版本相同时为空
+    pub diff: String,
+}
+/// 导入前的旧地址, 可以是完整的链接或路径
+#[derive(Deserialize)]
+pub struct PostAliasQuery {
+    pub path: String,
+}
+#[derive(Deserialize)]
+pub struct PostRollback {
+    pub author: Option<String>,
+    /// 为空时使用"回滚到版本N"
+    pub message: Option<String>,
+}
+/// 重建一篇文章的索引的结果
+#[derive(Debug)]
+pub struct PostReindex {
+    pub id: i32,
+    pub title: String,
+    /// 失败时为错误信息
+    pub outcome: Result<(), String>,
+}
+/// 数据库中的文章和文章文件的一致性检查结果
+#[derive(Debug, Default)]
+pub struct ConsistencyReport {
+    /// 没有文件的文章
+    pub missing_files: Vec<MissingFile>,
+    /// 没有对应文章的文件
+    pub orphan_files: Vec<OrphanFile>,
+}
+impl ConsistencyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.missing_files.is_empty() && self.orphan_files.is_empty()
+    }
+}
+#[derive(Debug)]
+pub struct MissingFile {
+    pub id: i32,
+    pub title: String,
+    /// 修复时采取的操作, 只检查时为空
+    pub repair: Option<Repair>,
+}
+#[derive(Debug)]
+pub struct OrphanFile {
+    pub id: i32,
+    pub repair: Option<Repair>,
+}
+#[derive(Debug)]
+pub enum Repair {
+    /// 使用最新的版本记录重新写入文件
+    Restored {
+        revision: i32,
+    },
+    /// 没有版本记录可以恢复, 将文章移入回收站, 评论保留
+    Trashed,
+    /// 文件被移到`key`
+    Quarantined {
+        key: String,
+    },
+    Failed {
+        error: String,
+    },
+}
+impl fmt::Display for Repair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Restored { revision } => write!(f, "已从版本{}恢复文件", revision),
+            Self::Trashed => write!(f, "没有可以恢复的版本, 已将文章移入回收站"),
+            Self::Quarantined { key } => write!(f, "已将文件移到`{}`", key),
+            Self::Failed { error } => write!(f, "修复失败: {}", error),
+        }
+    }
+}
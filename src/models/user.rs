@@ -0,0 +1,37 @@
+use crate::repositories::user::{Role, User};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct UserCreate {
+    pub username: String,
+    pub password: String,
+    pub role: Role,
+}
+
+/// 为空的字段保持不变
+#[derive(Deserialize)]
+pub struct UserUpdate {
+    pub password: Option<String>,
+    pub role: Option<Role>,
+}
+
+#[derive(Serialize)]
+pub struct UserRead {
+    pub id: i32,
+    pub username: String,
+    pub role: Role,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl From<User> for UserRead {
+    fn from(value: User) -> Self {
+        Self {
+            id: value.id,
+            username: value.username,
+            role: value.role,
+            created_at: value.created_at.to_string(),
+            updated_at: value.updated_at.to_string(),
+        }
+    }
+}
@@ -1,6 +1,13 @@
+pub mod alias;
+pub mod asset;
+pub mod backup;
+pub mod chunk;
 pub mod comment;
 mod impls;
 pub mod post;
+pub mod revision;
+pub mod session;
+pub mod user;
 #[derive(Debug, thiserror::Error)]
 pub enum ReponsitoryError {
     #[error("Not Found")]
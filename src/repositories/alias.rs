@@ -0,0 +1,12 @@
+use super::ReponsitoryError;
+use async_trait::async_trait;
+
+/// 文章的旧地址, 从其他博客程序导入的文章通过它们保持原来的链接可用
+#[async_trait]
+pub trait PostAliasReponsitory: Send + Sync {
+    /// 添加文章的旧地址, 返回已经被其他文章使用而没有添加的地址
+    async fn add(&self, post_id: i32, paths: &[String]) -> Result<Vec<String>, ReponsitoryError>;
+    /// 查找使用该地址的文章
+    async fn find(&self, path: &str) -> Result<i32, ReponsitoryError>;
+}
+pub use super::impls::alias::SqlxReponsitory;
@@ -0,0 +1,82 @@
+use super::ReponsitoryError;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use sqlx::types::chrono::{DateTime, Utc};
+
+/// 上传的图片或附件, 文件以内容的sha256保存
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct Asset {
+    pub id: i32,
+    pub hash: String,
+    pub mime: String,
+    pub size: i64,
+    /// 第一次上传时的文件名
+    pub filename: String,
+    pub created_at: DateTime<Utc>,
+    /// 图片的尺寸, 不是可以缩放的图片时为空
+    #[serde(default)]
+    pub width: Option<i32>,
+    #[serde(default)]
+    pub height: Option<i32>,
+}
+
+/// 资源哈希上的锁, 写入和删除相同内容的文件及其记录时互斥;
+/// 加锁期间的写入在同一个事务中进行, 调用`release`时提交, 直接丢弃时回滚
+pub struct HashLock {
+    pub(super) tx: sqlx::Transaction<'static, sqlx::Postgres>,
+}
+
+pub struct AssetCreate {
+    /// 从备份恢复时保留原来的id, 为空时自动分配
+    pub id: Option<i32>,
+    pub hash: String,
+    pub mime: String,
+    pub size: i64,
+    pub filename: String,
+    /// 为空时使用当前时间
+    pub created_at: Option<DateTime<Utc>>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+}
+
+#[async_trait]
+pub trait AssetReponsitory: Send + Sync {
+    /// 锁定哈希, 在写入或删除文件之前获取, 直到记录写入之后才释放
+    async fn lock_hash(&self, hash: &str) -> Result<HashLock, ReponsitoryError>;
+    /// 提交加锁期间的写入并释放锁
+    async fn release(&self, lock: HashLock) -> Result<(), ReponsitoryError>;
+    /// 写入资源, 相同内容的资源已经存在时返回已有的资源
+    async fn add(&self, lock: &mut HashLock, asset: AssetCreate)
+    -> Result<Asset, ReponsitoryError>;
+    async fn find_by_id(&self, id: i32) -> Result<Asset, ReponsitoryError>;
+    async fn list_all(&self) -> Result<Vec<Asset>, ReponsitoryError>;
+    /// 将资源关联到文章, 忽略不存在的资源, 文章不存在时返回`NotFound`
+    async fn link(&self, post_id: i32, asset_ids: &[i32]) -> Result<(), ReponsitoryError>;
+    /// 将文章关联的资源替换为`asset_ids`, 忽略不存在的资源
+    async fn sync_links(&self, post_id: i32, asset_ids: &[i32]) -> Result<(), ReponsitoryError>;
+    /// 列出在`before`之前上传且没有被任何文章(包括回收站中的文章)引用的资源
+    async fn list_unreferenced(
+        &self,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<Asset>, ReponsitoryError>;
+    /// 去除元数据后更新资源的哈希, 大小和尺寸
+    async fn update_content(
+        &self,
+        lock: &mut HashLock,
+        id: i32,
+        hash: &str,
+        size: i64,
+        width: Option<i32>,
+        height: Option<i32>,
+    ) -> Result<Asset, ReponsitoryError>;
+    /// 删除没有被引用的资源, 资源在此期间被引用时返回`NotFound`
+    async fn delete_unreferenced(
+        &self,
+        lock: &mut HashLock,
+        id: i32,
+    ) -> Result<Asset, ReponsitoryError>;
+    /// 写入指定id的资源后, 将资源id的序列调整到已有的最大id之后
+    async fn reset_sequence(&self) -> Result<(), ReponsitoryError>;
+}
+pub use super::impls::asset::SqlxReponsitory;
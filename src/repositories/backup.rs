@@ -0,0 +1,39 @@
+use super::ReponsitoryError;
+use super::comment::Comment;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use sqlx::types::chrono::{DateTime, Utc};
+
+/// 文章文件和元数据中都没有, 但备份时需要保存的状态
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct PostState {
+    pub id: i32,
+    /// 访问计数
+    pub count: i32,
+    /// 移入回收站的时间, 不在回收站中时为空
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct PostAlias {
+    pub path: String,
+    pub post_id: i32,
+}
+
+/// 导出和恢复整个站点时使用的跨表操作
+#[async_trait]
+pub trait BackupReponsitory: Send + Sync {
+    /// 数据库已经执行的最新一次迁移的版本号
+    async fn schema_version(&self) -> Result<i64, ReponsitoryError>;
+    /// 数据库中是否没有文章, 评论, 旧地址和资源
+    async fn is_empty(&self) -> Result<bool, ReponsitoryError>;
+    async fn list_post_states(&self) -> Result<Vec<PostState>, ReponsitoryError>;
+    async fn set_post_state(&self, state: &PostState) -> Result<(), ReponsitoryError>;
+    /// 列出所有文章(包括回收站中的文章)的评论
+    async fn list_comments(&self) -> Result<Vec<Comment>, ReponsitoryError>;
+    async fn list_aliases(&self) -> Result<Vec<PostAlias>, ReponsitoryError>;
+    /// 写入指定id的文章后, 将文章id的序列调整到已有的最大id之后
+    async fn reset_post_sequence(&self) -> Result<(), ReponsitoryError>;
+}
+pub use super::impls::backup::SqlxReponsitory;
@@ -0,0 +1,30 @@
+use super::ReponsitoryError;
+use super::post::PostSearchHit;
+use async_trait::async_trait;
+use pgvector::Vector;
+
+/// 博文切分后的一段文本及其向量
+pub struct PostChunkCreate {
+    pub content: String,
+    pub embedding: Vector,
+}
+
+#[async_trait]
+pub trait PostChunkReponsitory: Send + Sync {
+    /// 删除博文在该模型下的所有分段后写入新的分段
+    async fn replace(
+        &self,
+        post_id: i32,
+        model: &str,
+        chunks: Vec<PostChunkCreate>,
+    ) -> Result<(), ReponsitoryError>;
+    /// 按余弦相似度查询文章, 每篇文章取最相似的分段作为摘要
+    async fn find_by_embedding(
+        &self,
+        model: &str,
+        embedding: Vector,
+        offset: i32,
+        limit: i32,
+    ) -> Result<Vec<PostSearchHit>, ReponsitoryError>;
+}
+pub use super::impls::chunk::SqlxReponsitory;
@@ -10,6 +10,12 @@ pub struct CommentCreate {
     pub author: String,
     pub content: String,
     pub parent_id: Option<i32>,
+    /// 为空时使用当前时间, 导入其他博客程序的评论时保留原来的时间
+    #[serde(skip)]
+    pub created_at: Option<DateTime<Utc>>,
+    /// 登录用户发表的评论记录用户id, 匿名评论为空
+    #[serde(skip)]
+    pub user_id: Option<i32>,
 }
 #[derive(Debug, Serialize)]
 pub struct CommentUpdate {
@@ -24,6 +30,7 @@ pub struct Comment {
     pub content: String,
     pub created_at: DateTime<Utc>,
     pub parent_id: Option<i32>,
+    pub user_id: Option<i32>,
 }
 
 #[async_trait]
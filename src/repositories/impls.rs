@@ -1,2 +1,9 @@
+pub mod alias;
+pub mod asset;
+pub mod backup;
+pub mod chunk;
 pub mod comment;
 pub mod post;
+pub mod revision;
+pub mod session;
+pub mod user;
@@ -0,0 +1,60 @@
+use crate::repositories::ReponsitoryError;
+use crate::repositories::alias::PostAliasReponsitory;
+use async_trait::async_trait;
+use sqlx::PgPool;
+use tracing::{Level, event, instrument};
+
+pub struct SqlxReponsitory(PgPool);
+
+impl SqlxReponsitory {
+    pub fn new(pool: PgPool) -> SqlxReponsitory {
+        tracing::info!("创建PostAliasRepository成功");
+        SqlxReponsitory(pool)
+    }
+}
+
+#[async_trait]
+impl PostAliasReponsitory for SqlxReponsitory {
+    #[instrument(name = "PostAliasReponsitory::add", level = "debug", skip(self))]
+    async fn add(&self, post_id: i32, paths: &[String]) -> Result<Vec<String>, ReponsitoryError> {
+        event!(
+            Level::DEBUG,
+            post_id = post_id,
+            alias_count = paths.len(),
+            "开始添加文章旧地址"
+        );
+
+        let added: Vec<String> = sqlx::query_scalar(
+            r#"INSERT INTO post_alias (path, post_id)
+            SELECT path, $1 FROM UNNEST($2::varchar[]) AS path
+            ON CONFLICT (path) DO NOTHING RETURNING path"#,
+        )
+        .bind(post_id)
+        .bind(paths)
+        .fetch_all(&self.0)
+        .await?;
+        let conflicted = paths
+            .iter()
+            .filter(|path| !added.contains(path))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        event!(
+            Level::DEBUG,
+            post_id = post_id,
+            added_count = added.len(),
+            conflicted_count = conflicted.len(),
+            "成功添加文章旧地址"
+        );
+        Ok(conflicted)
+    }
+
+    #[instrument(name = "PostAliasReponsitory::find", level = "debug", skip(self))]
+    async fn find(&self, path: &str) -> Result<i32, ReponsitoryError> {
+        let post_id: i32 = sqlx::query_scalar("SELECT post_id FROM post_alias WHERE path = $1")
+            .bind(path)
+            .fetch_one(&self.0)
+            .await?;
+        Ok(post_id)
+    }
+}
@@ -0,0 +1,244 @@
+use crate::repositories::ReponsitoryError;
+use crate::repositories::asset::{Asset, AssetCreate, AssetReponsitory, HashLock};
+use async_trait::async_trait;
+use sqlx::PgPool;
+use sqlx::types::chrono::{DateTime, Utc};
+use tracing::{Level, event, instrument};
+
+pub struct SqlxReponsitory(PgPool);
+
+impl SqlxReponsitory {
+    pub fn new(pool: PgPool) -> SqlxReponsitory {
+        tracing::info!("创建AssetRepository成功");
+        SqlxReponsitory(pool)
+    }
+}
+
+#[async_trait]
+impl AssetReponsitory for SqlxReponsitory {
+    #[instrument(name = "AssetReponsitory::lock_hash", level = "debug", skip(self))]
+    async fn lock_hash(&self, hash: &str) -> Result<HashLock, ReponsitoryError> {
+        // 事务级别的咨询锁在事务提交或回滚时释放
+        let mut tx = self.0.begin().await?;
+        sqlx::query("SELECT pg_advisory_xact_lock('asset'::regclass::oid::int, hashtext($1))")
+            .bind(hash)
+            .execute(&mut *tx)
+            .await?;
+        Ok(HashLock { tx })
+    }
+
+    #[instrument(name = "AssetReponsitory::release", level = "debug", skip_all)]
+    async fn release(&self, lock: HashLock) -> Result<(), ReponsitoryError> {
+        lock.tx.commit().await?;
+        Ok(())
+    }
+
+    #[instrument(name = "AssetReponsitory::add", level = "debug", skip_all, fields(hash = %asset.hash))]
+    async fn add(
+        &self,
+        lock: &mut HashLock,
+        asset: AssetCreate,
+    ) -> Result<Asset, ReponsitoryError> {
+        let AssetCreate {
+            id,
+            hash,
+            mime,
+            size,
+            filename,
+            created_at,
+            width,
+            height,
+        } = asset;
+
+        event!(Level::DEBUG, hash = %hash, mime = %mime, size = size, "开始写入资源");
+
+        // 冲突时进行一次无意义的更新, 使`RETURNING`能够返回已有的行
+        let asset = sqlx::query_as::<_, Asset>(
+            r#"INSERT INTO asset (id, hash, mime, size, filename, created_at, width, height)
+            VALUES (COALESCE($1, nextval(pg_get_serial_sequence('asset', 'id'))),
+                $2, $3, $4, $5, COALESCE($6, NOW()), $7, $8)
+            ON CONFLICT (hash) DO UPDATE SET hash = EXCLUDED.hash
+            RETURNING *"#,
+        )
+        .bind(id)
+        .bind(&hash)
+        .bind(&mime)
+        .bind(size)
+        .bind(&filename)
+        .bind(created_at)
+        .bind(width)
+        .bind(height)
+        .fetch_one(&mut *lock.tx)
+        .await?;
+
+        event!(Level::DEBUG, asset_id = asset.id, hash = %asset.hash, "成功写入资源");
+        Ok(asset)
+    }
+
+    #[instrument(name = "AssetReponsitory::find_by_id", level = "debug", skip(self))]
+    async fn find_by_id(&self, id: i32) -> Result<Asset, ReponsitoryError> {
+        let asset = sqlx::query_as::<_, Asset>("SELECT * FROM asset WHERE id = $1")
+            .bind(id)
+            .fetch_one(&self.0)
+            .await?;
+        Ok(asset)
+    }
+
+    #[instrument(name = "AssetReponsitory::list_all", level = "debug", skip(self))]
+    async fn list_all(&self) -> Result<Vec<Asset>, ReponsitoryError> {
+        let assets = sqlx::query_as::<_, Asset>("SELECT * FROM asset ORDER BY id")
+            .fetch_all(&self.0)
+            .await?;
+
+        event!(Level::DEBUG, asset_count = assets.len(), "成功查询所有资源");
+        Ok(assets)
+    }
+
+    #[instrument(name = "AssetReponsitory::link", level = "debug", skip(self))]
+    async fn link(&self, post_id: i32, asset_ids: &[i32]) -> Result<(), ReponsitoryError> {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM post WHERE id = $1 AND deleted_at IS NULL)",
+        )
+        .bind(post_id)
+        .fetch_one(&self.0)
+        .await?;
+        if !exists {
+            return Err(ReponsitoryError::NotFound);
+        }
+        sqlx::query(
+            r#"INSERT INTO post_asset (post_id, asset_id)
+            SELECT $1, id FROM asset WHERE id = ANY($2)
+            ON CONFLICT DO NOTHING"#,
+        )
+        .bind(post_id)
+        .bind(asset_ids)
+        .execute(&self.0)
+        .await?;
+
+        event!(
+            Level::DEBUG,
+            post_id = post_id,
+            asset_count = asset_ids.len(),
+            "成功关联资源"
+        );
+        Ok(())
+    }
+
+    #[instrument(name = "AssetReponsitory::sync_links", level = "debug", skip(self))]
+    async fn sync_links(&self, post_id: i32, asset_ids: &[i32]) -> Result<(), ReponsitoryError> {
+        let mut tx = self.0.begin().await?;
+        sqlx::query("DELETE FROM post_asset WHERE post_id = $1 AND NOT (asset_id = ANY($2))")
+            .bind(post_id)
+            .bind(asset_ids)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            r#"INSERT INTO post_asset (post_id, asset_id)
+            SELECT $1, id FROM asset WHERE id = ANY($2)
+            ON CONFLICT DO NOTHING"#,
+        )
+        .bind(post_id)
+        .bind(asset_ids)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        event!(
+            Level::DEBUG,
+            post_id = post_id,
+            asset_count = asset_ids.len(),
+            "成功更新文章引用的资源"
+        );
+        Ok(())
+    }
+
+    #[instrument(
+        name = "AssetReponsitory::list_unreferenced",
+        level = "debug",
+        skip(self)
+    )]
+    async fn list_unreferenced(
+        &self,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<Asset>, ReponsitoryError> {
+        let assets = sqlx::query_as::<_, Asset>(
+            r#"SELECT * FROM asset
+            WHERE created_at < $1
+                AND NOT EXISTS(SELECT 1 FROM post_asset WHERE asset_id = asset.id)
+            ORDER BY id"#,
+        )
+        .bind(before)
+        .fetch_all(&self.0)
+        .await?;
+
+        event!(
+            Level::DEBUG,
+            asset_count = assets.len(),
+            "成功查询没有被引用的资源"
+        );
+        Ok(assets)
+    }
+
+    #[instrument(
+        name = "AssetReponsitory::update_content",
+        level = "debug",
+        skip(self, lock)
+    )]
+    async fn update_content(
+        &self,
+        lock: &mut HashLock,
+        id: i32,
+        hash: &str,
+        size: i64,
+        width: Option<i32>,
+        height: Option<i32>,
+    ) -> Result<Asset, ReponsitoryError> {
+        let asset = sqlx::query_as::<_, Asset>(
+            "UPDATE asset SET hash = $2, size = $3, width = $4, height = $5 WHERE id = $1 RETURNING *",
+        )
+        .bind(id)
+        .bind(hash)
+        .bind(size)
+        .bind(width)
+        .bind(height)
+        .fetch_one(&mut *lock.tx)
+        .await?;
+
+        event!(Level::DEBUG, asset_id = id, hash = %asset.hash, "成功更新资源内容");
+        Ok(asset)
+    }
+
+    #[instrument(
+        name = "AssetReponsitory::delete_unreferenced",
+        level = "debug",
+        skip(self, lock)
+    )]
+    async fn delete_unreferenced(
+        &self,
+        lock: &mut HashLock,
+        id: i32,
+    ) -> Result<Asset, ReponsitoryError> {
+        let asset = sqlx::query_as::<_, Asset>(
+            r#"DELETE FROM asset
+            WHERE id = $1 AND NOT EXISTS(SELECT 1 FROM post_asset WHERE asset_id = $1)
+            RETURNING *"#,
+        )
+        .bind(id)
+        .fetch_one(&mut *lock.tx)
+        .await?;
+
+        event!(Level::DEBUG, asset_id = id, hash = %asset.hash, "成功删除资源");
+        Ok(asset)
+    }
+
+    #[instrument(name = "AssetReponsitory::reset_sequence", level = "debug", skip(self))]
+    async fn reset_sequence(&self) -> Result<(), ReponsitoryError> {
+        sqlx::query(
+            r#"SELECT setval(pg_get_serial_sequence('asset', 'id'),
+                COALESCE((SELECT MAX(id) FROM asset), 0) + 1, false)"#,
+        )
+        .execute(&self.0)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+use crate::repositories::ReponsitoryError;
+use crate::repositories::backup::{BackupReponsitory, PostAlias, PostState};
+use crate::repositories::comment::Comment;
+use async_trait::async_trait;
+use sqlx::PgPool;
+use tracing::{Level, event, instrument};
+
+pub struct SqlxReponsitory(PgPool);
+
+impl SqlxReponsitory {
+    pub fn new(pool: PgPool) -> SqlxReponsitory {
+        tracing::info!("创建BackupRepository成功");
+        SqlxReponsitory(pool)
+    }
+}
+
+#[async_trait]
+impl BackupReponsitory for SqlxReponsitory {
+    #[instrument(
+        name = "BackupReponsitory::schema_version",
+        level = "debug",
+        skip(self)
+    )]
+    async fn schema_version(&self) -> Result<i64, ReponsitoryError> {
+        let version: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(MAX(version), 0) FROM _sqlx_migrations WHERE success",
+        )
+        .fetch_one(&self.0)
+        .await?;
+
+        event!(Level::DEBUG, schema_version = version, "成功查询数据库版本");
+        Ok(version)
+    }
+
+    #[instrument(name = "BackupReponsitory::is_empty", level = "debug", skip(self))]
+    async fn is_empty(&self) -> Result<bool, ReponsitoryError> {
+        let empty: bool = sqlx::query_scalar(
+            r#"SELECT NOT EXISTS(SELECT 1 FROM post)
+                AND NOT EXISTS(SELECT 1 FROM comment)
+                AND NOT EXISTS(SELECT 1 FROM post_alias)
+                AND NOT EXISTS(SELECT 1 FROM asset)"#,
+        )
+        .fetch_one(&self.0)
+        .await?;
+        Ok(empty)
+    }
+
+    #[instrument(
+        name = "BackupReponsitory::list_post_states",
+        level = "debug",
+        skip(self)
+    )]
+    async fn list_post_states(&self) -> Result<Vec<PostState>, ReponsitoryError> {
+        let states =
+            sqlx::query_as::<_, PostState>("SELECT id, count, deleted_at FROM post ORDER BY id")
+                .fetch_all(&self.0)
+                .await?;
+
+        event!(Level::DEBUG, post_count = states.len(), "成功查询文章状态");
+        Ok(states)
+    }
+
+    #[instrument(
+        name = "BackupReponsitory::set_post_state",
+        level = "debug",
+        skip(self)
+    )]
+    async fn set_post_state(&self, state: &PostState) -> Result<(), ReponsitoryError> {
+        let result = sqlx::query("UPDATE post SET count = $2, deleted_at = $3 WHERE id = $1")
+            .bind(state.id)
+            .bind(state.count)
+            .bind(state.deleted_at)
+            .execute(&self.0)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(ReponsitoryError::NotFound);
+        }
+        Ok(())
+    }
+
+    #[instrument(name = "BackupReponsitory::list_comments", level = "debug", skip(self))]
+    async fn list_comments(&self) -> Result<Vec<Comment>, ReponsitoryError> {
+        let comments = sqlx::query_as::<_, Comment>("SELECT * FROM comment ORDER BY id")
+            .fetch_all(&self.0)
+            .await?;
+
+        event!(
+            Level::DEBUG,
+            comment_count = comments.len(),
+            "成功查询所有评论"
+        );
+        Ok(comments)
+    }
+
+    #[instrument(name = "BackupReponsitory::list_aliases", level = "debug", skip(self))]
+    async fn list_aliases(&self) -> Result<Vec<PostAlias>, ReponsitoryError> {
+        let aliases =
+            sqlx::query_as::<_, PostAlias>("SELECT path, post_id FROM post_alias ORDER BY path")
+                .fetch_all(&self.0)
+                .await?;
+
+        event!(
+            Level::DEBUG,
+            alias_count = aliases.len(),
+            "成功查询所有旧地址"
+        );
+        Ok(aliases)
+    }
+
+    #[instrument(
+        name = "BackupReponsitory::reset_post_sequence",
+        level = "debug",
+        skip(self)
+    )]
+    async fn reset_post_sequence(&self) -> Result<(), ReponsitoryError> {
+        sqlx::query(
+            r#"SELECT setval(pg_get_serial_sequence('post', 'id'),
+                COALESCE((SELECT MAX(id) FROM post), 0) + 1, false)"#,
+        )
+        .execute(&self.0)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+use crate::repositories::ReponsitoryError;
+use crate::repositories::chunk::{PostChunkCreate, PostChunkReponsitory};
+use crate::repositories::post::{POST_META_COLUMNS, PostSearchHit};
+use async_trait::async_trait;
+use pgvector::Vector;
+use sqlx::PgPool;
+use tracing::{Level, event, instrument};
+
+pub struct SqlxReponsitory(PgPool);
+
+impl SqlxReponsitory {
+    pub fn new(pool: PgPool) -> SqlxReponsitory {
+        tracing::info!("创建PostChunkRepository成功");
+        SqlxReponsitory(pool)
+    }
+}
+
+#[async_trait]
+impl PostChunkReponsitory for SqlxReponsitory {
+    #[instrument(
+        name = "PostChunkReponsitory::replace",
+        level = "debug",
+        skip(self, chunks)
+    )]
+    async fn replace(
+        &self,
+        post_id: i32,
+        model: &str,
+        chunks: Vec<PostChunkCreate>,
+    ) -> Result<(), ReponsitoryError> {
+        event!(
+            Level::DEBUG,
+            post_id = post_id,
+            chunk_count = chunks.len(),
+            "开始写入文章分段向量"
+        );
+
+        let mut tx = self.0.begin().await?;
+        sqlx::query("DELETE FROM post_chunk WHERE post_id = $1 AND model = $2")
+            .bind(post_id)
+            .bind(model)
+            .execute(&mut *tx)
+            .await?;
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            sqlx::query(
+                r#"INSERT INTO
+                post_chunk (post_id, chunk_index, model, content, embedding)
+                VALUES ($1, $2, $3, $4, $5)"#,
+            )
+            .bind(post_id)
+            .bind(index as i32)
+            .bind(model)
+            .bind(chunk.content)
+            .bind(chunk.embedding)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        event!(Level::DEBUG, post_id = post_id, "成功写入文章分段向量");
+        Ok(())
+    }
+
+    #[instrument(
+        name = "PostChunkReponsitory::find_by_embedding",
+        level = "debug",
+        skip(self, embedding)
+    )]
+    async fn find_by_embedding(
+        &self,
+        model: &str,
+        embedding: Vector,
+        offset: i32,
+        limit: i32,
+    ) -> Result<Vec<PostSearchHit>, ReponsitoryError> {
+        event!(Level::DEBUG, model = model, "开始根据向量查询文章");
+
+        // 分段表的列名与文章表不重复, 文章的列不需要加表名
+        let sql = format!(
+            r#"WITH ranked AS (
+                SELECT post_id, content, embedding <=> $2 AS distance,
+                ROW_NUMBER() OVER (PARTITION BY post_id ORDER BY embedding <=> $2) AS rank
+                FROM post_chunk WHERE model = $1
+            )
+            SELECT {},
+            (1 - r.distance)::real AS score, r.content AS snippet
+            FROM ranked r JOIN post p ON p.id = r.post_id
+            WHERE r.rank = 1 AND p.status = 'published' AND p.deleted_at IS NULL
+            ORDER BY r.distance, p.id DESC
+            OFFSET $3 LIMIT $4"#,
+            POST_META_COLUMNS
+        );
+        let posts = sqlx::query_as::<_, PostSearchHit>(&sql)
+            .bind(model)
+            .bind(embedding)
+            .bind(offset)
+            .bind(limit)
+            .fetch_all(&self.0)
+            .await?;
+
+        event!(
+            Level::DEBUG,
+            post_count = posts.len(),
+            "成功根据向量查询文章"
+        );
+        Ok(posts)
+    }
+}
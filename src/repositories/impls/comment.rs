@@ -18,12 +18,14 @@ impl CommentReponsitory for SqlxReponsitory {
         
         let new: Comment = sqlx::query_as(
             r#"
-        INSERT INTO comment(post_id, author, content, parent_id) VALUES($1, $2, $3, $4) RETURNING *"#,
+        INSERT INTO comment(post_id, author, content, parent_id, created_at, user_id) VALUES($1, $2, $3, $4, COALESCE($5, NOW()), $6) RETURNING *"#,
         )
         .bind(comment.post_id)
         .bind(&comment.author)
         .bind(&comment.content)
         .bind(comment.parent_id)
+        .bind(comment.created_at)
+        .bind(comment.user_id)
         .fetch_one(&self.0)
         .await?;
         
@@ -1,12 +1,26 @@
 use crate::repositories::ReponsitoryError;
-use crate::repositories::post::{PostMeta, PostMetaCreate, PostMetaReponsitory, PostMetaUpdate};
+use crate::repositories::post::{
+    POST_META_COLUMNS, PostIndexUpdate, PostMeta, PostMetaCreate, PostMetaReponsitory,
+    PostMetaUpdate, PostSearchHit,
+};
+use crate::util::{HIGHLIGHT_END, HIGHLIGHT_START};
 use async_trait::async_trait;
 use serde_json;
 use sqlx::Pool;
 use sqlx::Row;
 use sqlx::types::Json;
+use sqlx::types::chrono::{DateTime, Utc};
+use std::sync::LazyLock;
 use tracing::{Level, event, instrument};
 
+/// 正文没有转义, 高亮使用控制字符标记, 由服务层转义之后再替换为html标签
+static HEADLINE_OPTIONS: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        "StartSel={}, StopSel={}, MaxWords=35, MinWords=15, MaxFragments=2",
+        HIGHLIGHT_START, HIGHLIGHT_END
+    )
+});
+
 pub struct SqlxReponsitory(Pool<sqlx::Postgres>);
 
 impl SqlxReponsitory {
@@ -40,7 +54,8 @@ impl PostMetaReponsitory for SqlxReponsitory {
         );
 
         let posts = sqlx::query_as::<_, PostMeta>(
-            "SELECT id, title, tags, first_publish, last_modify, count FROM post LIMIT id > $1 limit $2",
+            r#"SELECT id, title, slug, tags, first_publish, last_modify, count, toc, word_count, char_count, reading_time, excerpt, status, publish_at, author_id FROM post
+            WHERE id > $1 AND status = 'published' AND deleted_at IS NULL ORDER BY id LIMIT $2"#,
         )
         .bind(start_id)
         .bind(page_size)
@@ -63,7 +78,7 @@ impl PostMetaReponsitory for SqlxReponsitory {
         event!(Level::DEBUG, post_id = id, "开始根据ID查询文章元数据");
 
         let post = sqlx::query_as::<_, PostMeta>(
-            "SELECT id, title, tags, first_publish, last_modify, count FROM post WHERE id = $1",
+            "SELECT id, title, slug, tags, first_publish, last_modify, count, toc, word_count, char_count, reading_time, excerpt, status, publish_at, author_id FROM post WHERE id = $1 AND deleted_at IS NULL",
         )
         .bind(id)
         .fetch_one(&self.0)
@@ -73,6 +88,66 @@ impl PostMetaReponsitory for SqlxReponsitory {
         Ok(post)
     }
 
+    #[instrument(
+        name = "PostMetaReponsitory::find_by_slug",
+        level = "debug",
+        skip(self)
+    )]
+    async fn find_by_slug(&self, slug: &str) -> Result<PostMeta, ReponsitoryError> {
+        event!(Level::DEBUG, slug = slug, "开始根据slug查询文章元数据");
+
+        let post = sqlx::query_as::<_, PostMeta>(
+            "SELECT id, title, slug, tags, first_publish, last_modify, count, toc, word_count, char_count, reading_time, excerpt, status, publish_at, author_id FROM post WHERE slug = $1 AND deleted_at IS NULL",
+        )
+        .bind(slug)
+        .fetch_one(&self.0)
+        .await?;
+
+        event!(
+            Level::DEBUG,
+            post_id = post.id,
+            slug = slug,
+            "成功根据slug查询文章元数据"
+        );
+        Ok(post)
+    }
+
+    #[instrument(name = "PostMetaReponsitory::find_author", level = "debug", skip(self))]
+    async fn find_author(&self, id: i32) -> Result<Option<i32>, ReponsitoryError> {
+        let author_id: Option<i32> = sqlx::query_scalar("SELECT author_id FROM post WHERE id = $1")
+            .bind(id)
+            .fetch_one(&self.0)
+            .await?;
+        Ok(author_id)
+    }
+
+    #[instrument(name = "PostMetaReponsitory::slug_exists", level = "debug", skip(self))]
+    async fn slug_exists(&self, slug: &str, exclude_id: i32) -> Result<bool, ReponsitoryError> {
+        let exists: bool =
+            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM post WHERE slug = $1 AND id <> $2)")
+                .bind(slug)
+                .bind(exclude_id)
+                .fetch_one(&self.0)
+                .await?;
+        Ok(exists)
+    }
+
+    #[instrument(name = "PostMetaReponsitory::list_all", level = "debug", skip(self))]
+    async fn list_all(&self) -> Result<Vec<PostMeta>, ReponsitoryError> {
+        event!(Level::DEBUG, "开始查询所有文章元数据");
+
+        let posts = sqlx::query_as::<_, PostMeta>("SELECT * FROM post ORDER BY id")
+            .fetch_all(&self.0)
+            .await?;
+
+        event!(
+            Level::DEBUG,
+            post_count = posts.len(),
+            "成功查询所有文章元数据"
+        );
+        Ok(posts)
+    }
+
     #[instrument(
         name = "PostMetaReponsitory::find_by_keywords",
         level = "debug",
@@ -81,7 +156,9 @@ impl PostMetaReponsitory for SqlxReponsitory {
     async fn find_by_keywords(
         &self,
         keywords: &[String],
-    ) -> Result<Vec<PostMeta>, ReponsitoryError> {
+        offset: i32,
+        limit: i32,
+    ) -> Result<Vec<PostSearchHit>, ReponsitoryError> {
         if keywords.is_empty() {
             event!(Level::DEBUG, "关键词列表为空，返回空结果");
             return Ok(vec![]);
@@ -92,16 +169,27 @@ impl PostMetaReponsitory for SqlxReponsitory {
         // 构建正确的tsquery格式，每个关键词用 & 连接
         let query_string = keywords
             .iter()
-            .map(|keyword| format!("'{}'", keyword.replace("'", "''")))
+            .map(|keyword| format!("'{}'", keyword.replace('\\', "\\\\").replace("'", "''")))
             .collect::<Vec<_>>()
             .join(" & ");
 
-        let posts = sqlx::query_as::<_, PostMeta>(
-            "SELECT * FROM post WHERE kw @@ to_tsquery('simple', $1)",
-        )
-        .bind(query_string)
-        .fetch_all(&self.0)
-        .await?;
+        let sql = format!(
+            r#"SELECT {},
+            ts_rank_cd(kw, query) AS score,
+            ts_headline('simple', plain_text, query, $4) AS snippet
+            FROM post, to_tsquery('simple', $1) query
+            WHERE kw @@ query AND status = 'published' AND deleted_at IS NULL
+            ORDER BY score DESC, id DESC
+            OFFSET $2 LIMIT $3"#,
+            POST_META_COLUMNS
+        );
+        let posts = sqlx::query_as::<_, PostSearchHit>(&sql)
+            .bind(query_string)
+            .bind(offset)
+            .bind(limit)
+            .bind(HEADLINE_OPTIONS.as_str())
+            .fetch_all(&self.0)
+            .await?;
 
         event!(
             Level::DEBUG,
@@ -120,10 +208,12 @@ impl PostMetaReponsitory for SqlxReponsitory {
 
         event!(Level::DEBUG, tags_count = tags.len(), tags = ?tags, "开始根据标签查询文章");
 
-        let posts = sqlx::query_as::<_, PostMeta>("SELECT * FROM post WHERE tags @> $1::jsonb")
-            .bind(serde_json::to_value(tags).unwrap())
-            .fetch_all(&self.0)
-            .await?;
+        let posts = sqlx::query_as::<_, PostMeta>(
+            "SELECT * FROM post WHERE tags @> $1::jsonb AND status = 'published' AND deleted_at IS NULL",
+        )
+        .bind(serde_json::to_value(tags).unwrap())
+        .fetch_all(&self.0)
+        .await?;
 
         event!(
             Level::DEBUG,
@@ -135,19 +225,56 @@ impl PostMetaReponsitory for SqlxReponsitory {
 
     #[instrument(name = "PostMetaReponsitory::add", level = "debug", skip_all)]
     async fn add(&self, post: PostMetaCreate) -> Result<PostMeta, ReponsitoryError> {
-        let PostMetaCreate { title, tags, kw } = post;
+        let PostMetaCreate {
+            id,
+            title,
+            slug,
+            tags,
+            kw,
+            toc,
+            stats,
+            excerpt,
+            status,
+            publish_at,
+            first_publish,
+            last_modify,
+            author_id,
+        } = post;
 
-        event!(Level::DEBUG, title = %title, tags_count = tags.len(), keywords_count = kw.len(), "开始创建文章元数据");
+        event!(Level::DEBUG, title = %title, tags_count = tags.len(), keywords_count = kw.count(), "开始创建文章元数据");
 
         let new_post = sqlx::query_as::<_, PostMeta>(
             r#"INSERT INTO
-            post (title, tags, kw)
-            VALUES ($1, $2, to_tsvector('simple', $3))
+            post (id, title, tags, kw, plain_text, first_publish, last_modify, slug,
+                toc, word_count, char_count, reading_time, excerpt,
+                status, publish_at, author_id)
+            VALUES (COALESCE($17, nextval(pg_get_serial_sequence('post', 'id'))), $1, $2,
+                setweight(to_tsvector('simple', $3), 'A') ||
+                setweight(to_tsvector('simple', $4), 'B') ||
+                setweight(to_tsvector('simple', $5), 'C'),
+                $6, COALESCE($7, NOW()), COALESCE($8, $7, NOW()), $9,
+                $10, $11, $12, $13, $14,
+                $15, $16, $18)
             RETURNING *"#,
         )
         .bind(&title)
         .bind(Json(tags))
-        .bind(kw.join("&"))
+        .bind(kw.title.join(" "))
+        .bind(kw.headings.join(" "))
+        .bind(kw.body.join(" "))
+        .bind(&kw.segmented)
+        .bind(first_publish)
+        .bind(last_modify)
+        .bind(&slug)
+        .bind(Json(toc))
+        .bind(stats.word_count)
+        .bind(stats.char_count)
+        .bind(stats.reading_time)
+        .bind(&excerpt)
+        .bind(status)
+        .bind(publish_at)
+        .bind(id)
+        .bind(author_id)
         .fetch_one(&self.0)
         .await?;
 
@@ -160,22 +287,50 @@ impl PostMetaReponsitory for SqlxReponsitory {
         let PostMetaUpdate {
             id,
             title,
+            slug,
             tags,
             kw,
+            toc,
+            stats,
+            excerpt,
+            status,
+            publish_at,
         } = post;
 
-        event!(Level::DEBUG, post_id = id, title = %title, tags_count = tags.len(), keywords_count = kw.len(), "开始更新文章元数据");
+        event!(Level::DEBUG, post_id = id, title = %title, tags_count = tags.len(), keywords_count = kw.count(), "开始更新文章元数据");
 
         let updated_post = sqlx::query_as::<_, PostMeta>(
             r#"UPDATE
-        post SET title = $1, tags = $2,kw = to_tsvector('simple', $3),
-        last_modify = CURRENT_TIMESTAMP, count = count+1
-        WHERE id = $4 RETURNING *"#,
+        post SET title = $1, tags = $2,
+        kw = setweight(to_tsvector('simple', $3), 'A') ||
+            setweight(to_tsvector('simple', $4), 'B') ||
+            setweight(to_tsvector('simple', $5), 'C'),
+        plain_text = $6, slug = $8,
+        toc = $9, word_count = $10, char_count = $11, reading_time = $12,
+        excerpt = $13,
+        first_publish = CASE
+            WHEN $14 = 'scheduled' THEN $15
+            WHEN status IN ('draft', 'scheduled') AND $14 IN ('published', 'unlisted') THEN NOW()
+            ELSE first_publish END,
+        status = $14, publish_at = $15,
+        last_modify = CURRENT_TIMESTAMP
+        WHERE id = $7 AND deleted_at IS NULL RETURNING *"#,
         )
         .bind(&title)
         .bind(Json(tags))
-        .bind(kw.join("&"))
+        .bind(kw.title.join(" "))
+        .bind(kw.headings.join(" "))
+        .bind(kw.body.join(" "))
+        .bind(&kw.segmented)
         .bind(id)
+        .bind(&slug)
+        .bind(Json(toc))
+        .bind(stats.word_count)
+        .bind(stats.char_count)
+        .bind(stats.reading_time)
+        .bind(&excerpt)
+        .bind(status)
+        .bind(publish_at)
         .fetch_one(&self.0)
         .await?;
 
@@ -183,10 +338,55 @@ impl PostMetaReponsitory for SqlxReponsitory {
         Ok(updated_post)
     }
 
+    #[instrument(name = "PostMetaReponsitory::reindex", level = "debug", skip_all, fields(id = %post.id))]
+    async fn reindex(&self, post: PostIndexUpdate) -> Result<PostMeta, ReponsitoryError> {
+        let PostIndexUpdate {
+            id,
+            kw,
+            toc,
+            stats,
+            excerpt,
+        } = post;
+
+        event!(
+            Level::DEBUG,
+            post_id = id,
+            keywords_count = kw.count(),
+            "开始重建文章索引"
+        );
+
+        let post = sqlx::query_as::<_, PostMeta>(
+            r#"UPDATE post SET
+        kw = setweight(to_tsvector('simple', $2), 'A') ||
+            setweight(to_tsvector('simple', $3), 'B') ||
+            setweight(to_tsvector('simple', $4), 'C'),
+        plain_text = $5,
+        toc = $6, word_count = $7, char_count = $8, reading_time = $9,
+        excerpt = $10
+        WHERE id = $1 RETURNING *"#,
+        )
+        .bind(id)
+        .bind(kw.title.join(" "))
+        .bind(kw.headings.join(" "))
+        .bind(kw.body.join(" "))
+        .bind(&kw.segmented)
+        .bind(Json(toc))
+        .bind(stats.word_count)
+        .bind(stats.char_count)
+        .bind(stats.reading_time)
+        .bind(&excerpt)
+        .fetch_one(&self.0)
+        .await?;
+
+        event!(Level::DEBUG, post_id = id, "成功重建文章索引");
+        Ok(post)
+    }
+
     #[instrument(name = "PostMetaReponsitory::delete", level = "debug", skip(self))]
-    async fn delete(&self, id: i32) -> Result<(), ReponsitoryError> {
+    async fn delete(&self, id: i32) -> Result<PostMeta, ReponsitoryError> {
         event!(Level::DEBUG, post_id = id, "开始删除文章元数据");
 
+        // 评论和分段向量通过外键级联删除
         let deleted_post =
             sqlx::query_as::<_, PostMeta>("DELETE FROM post WHERE id = $1 RETURNING *")
                 .bind(id)
@@ -194,8 +394,57 @@ impl PostMetaReponsitory for SqlxReponsitory {
                 .await?;
 
         event!(Level::DEBUG, post_id = id, title = %deleted_post.title, "成功删除文章元数据");
-        Ok(())
+        Ok(deleted_post)
+    }
+
+    #[instrument(name = "PostMetaReponsitory::trash", level = "debug", skip(self))]
+    async fn trash(&self, id: i32) -> Result<PostMeta, ReponsitoryError> {
+        event!(Level::DEBUG, post_id = id, "开始将文章移入回收站");
+
+        let trashed_post = sqlx::query_as::<_, PostMeta>(
+            "UPDATE post SET deleted_at = NOW() WHERE id = $1 AND deleted_at IS NULL RETURNING *",
+        )
+        .bind(id)
+        .fetch_one(&self.0)
+        .await?;
+
+        event!(Level::DEBUG, post_id = id, title = %trashed_post.title, "成功将文章移入回收站");
+        Ok(trashed_post)
+    }
+
+    #[instrument(name = "PostMetaReponsitory::restore", level = "debug", skip(self))]
+    async fn restore(&self, id: i32) -> Result<PostMeta, ReponsitoryError> {
+        event!(Level::DEBUG, post_id = id, "开始从回收站恢复文章");
+
+        let restored_post = sqlx::query_as::<_, PostMeta>(
+            "UPDATE post SET deleted_at = NULL WHERE id = $1 AND deleted_at IS NOT NULL RETURNING *",
+        )
+        .bind(id)
+        .fetch_one(&self.0)
+        .await?;
+
+        event!(Level::DEBUG, post_id = id, title = %restored_post.title, "成功从回收站恢复文章");
+        Ok(restored_post)
+    }
+
+    #[instrument(name = "PostMetaReponsitory::list_trash", level = "debug", skip(self))]
+    async fn list_trash(&self) -> Result<Vec<PostMeta>, ReponsitoryError> {
+        event!(Level::DEBUG, "开始查询回收站中的文章");
+
+        let posts = sqlx::query_as::<_, PostMeta>(
+            "SELECT * FROM post WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC",
+        )
+        .fetch_all(&self.0)
+        .await?;
+
+        event!(
+            Level::DEBUG,
+            post_count = posts.len(),
+            "成功查询回收站中的文章"
+        );
+        Ok(posts)
     }
+
     #[instrument(
         name = "PostMetaReponsitory::list_all_tags",
         level = "debug",
@@ -205,7 +454,7 @@ impl PostMetaReponsitory for SqlxReponsitory {
         event!(Level::DEBUG, "开始查询所有标签");
 
         let tags: Vec<String> = sqlx::query(
-            "SELECT DISTINCT jsonb_array_elements_text(tags) as tag FROM post ORDER BY tag",
+            "SELECT DISTINCT jsonb_array_elements_text(tags) as tag FROM post WHERE status = 'published' AND deleted_at IS NULL ORDER BY tag",
         )
         .fetch_all(&self.0)
         .await?
@@ -216,4 +465,38 @@ impl PostMetaReponsitory for SqlxReponsitory {
         event!(Level::DEBUG, tags_count = tags.len(), "成功查询所有标签");
         Ok(tags)
     }
+
+    #[instrument(name = "PostMetaReponsitory::publish_due", level = "debug", skip(self))]
+    async fn publish_due(&self) -> Result<Vec<PostMeta>, ReponsitoryError> {
+        event!(Level::DEBUG, "开始发布已到时间的定时文章");
+
+        let posts = sqlx::query_as::<_, PostMeta>(
+            r#"UPDATE post SET status = 'published', first_publish = publish_at
+            WHERE status = 'scheduled' AND publish_at <= NOW() AND deleted_at IS NULL
+            RETURNING *"#,
+        )
+        .fetch_all(&self.0)
+        .await?;
+
+        event!(
+            Level::DEBUG,
+            post_count = posts.len(),
+            "成功发布已到时间的定时文章"
+        );
+        Ok(posts)
+    }
+
+    #[instrument(
+        name = "PostMetaReponsitory::next_scheduled",
+        level = "debug",
+        skip(self)
+    )]
+    async fn next_scheduled(&self) -> Result<Option<DateTime<Utc>>, ReponsitoryError> {
+        let next: Option<DateTime<Utc>> = sqlx::query_scalar(
+            "SELECT MIN(publish_at) FROM post WHERE status = 'scheduled' AND deleted_at IS NULL",
+        )
+        .fetch_one(&self.0)
+        .await?;
+        Ok(next)
+    }
 }
@@ -0,0 +1,126 @@
+use crate::repositories::ReponsitoryError;
+use crate::repositories::revision::{
+    PostRevisionReponsitory, Revision, RevisionContent, RevisionCreate,
+};
+use async_trait::async_trait;
+use sqlx::PgPool;
+use sqlx::types::Json;
+use tracing::{Level, event, instrument};
+
+const REVISION_COLUMNS: &str =
+    "id, post_id, revision, title, slug, tags, content_hash, author, message, created_at";
+
+pub struct SqlxReponsitory(PgPool);
+
+impl SqlxReponsitory {
+    pub fn new(pool: PgPool) -> SqlxReponsitory {
+        tracing::info!("创建PostRevisionRepository成功");
+        SqlxReponsitory(pool)
+    }
+}
+
+#[async_trait]
+impl PostRevisionReponsitory for SqlxReponsitory {
+    #[instrument(name = "PostRevisionReponsitory::add", level = "debug", skip_all, fields(post_id = %revision.post_id))]
+    async fn add(&self, revision: RevisionCreate) -> Result<Revision, ReponsitoryError> {
+        let RevisionCreate {
+            post_id,
+            title,
+            slug,
+            tags,
+            content,
+            content_hash,
+            author,
+            message,
+        } = revision;
+
+        event!(Level::DEBUG, post_id = post_id, content_hash = %content_hash, "开始写入文章版本");
+
+        let revision = sqlx::query_as::<_, Revision>(&format!(
+            r#"INSERT INTO
+            post_revision (post_id, revision, title, slug, tags, content, content_hash, author, message)
+            VALUES ($1,
+                (SELECT COALESCE(MAX(revision), 0) + 1 FROM post_revision WHERE post_id = $1),
+                $2, $3, $4, $5, $6, $7, $8)
+            RETURNING {}"#,
+            REVISION_COLUMNS
+        ))
+        .bind(post_id)
+        .bind(&title)
+        .bind(&slug)
+        .bind(Json(tags))
+        .bind(&content)
+        .bind(&content_hash)
+        .bind(&author)
+        .bind(&message)
+        .fetch_one(&self.0)
+        .await?;
+
+        event!(
+            Level::DEBUG,
+            post_id = post_id,
+            revision = revision.revision,
+            "成功写入文章版本"
+        );
+        Ok(revision)
+    }
+
+    #[instrument(name = "PostRevisionReponsitory::list", level = "debug", skip(self))]
+    async fn list(&self, post_id: i32) -> Result<Vec<Revision>, ReponsitoryError> {
+        event!(Level::DEBUG, post_id = post_id, "开始查询文章版本");
+
+        let revisions = sqlx::query_as::<_, Revision>(&format!(
+            "SELECT {} FROM post_revision WHERE post_id = $1 ORDER BY revision DESC",
+            REVISION_COLUMNS
+        ))
+        .bind(post_id)
+        .fetch_all(&self.0)
+        .await?;
+
+        event!(
+            Level::DEBUG,
+            post_id = post_id,
+            revision_count = revisions.len(),
+            "成功查询文章版本"
+        );
+        Ok(revisions)
+    }
+
+    #[instrument(name = "PostRevisionReponsitory::find", level = "debug", skip(self))]
+    async fn find(&self, post_id: i32, revision: i32) -> Result<RevisionContent, ReponsitoryError> {
+        let revision = sqlx::query_as::<_, RevisionContent>(&format!(
+            "SELECT {}, content FROM post_revision WHERE post_id = $1 AND revision = $2",
+            REVISION_COLUMNS
+        ))
+        .bind(post_id)
+        .bind(revision)
+        .fetch_one(&self.0)
+        .await?;
+        Ok(revision)
+    }
+
+    #[instrument(name = "PostRevisionReponsitory::exists", level = "debug", skip(self))]
+    async fn exists(&self, post_id: i32) -> Result<bool, ReponsitoryError> {
+        let exists: bool =
+            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM post_revision WHERE post_id = $1)")
+                .bind(post_id)
+                .fetch_one(&self.0)
+                .await?;
+        Ok(exists)
+    }
+
+    #[instrument(
+        name = "PostRevisionReponsitory::find_post_by_hash",
+        level = "debug",
+        skip(self)
+    )]
+    async fn find_post_by_hash(&self, content_hash: &str) -> Result<Option<i32>, ReponsitoryError> {
+        let post_id: Option<i32> = sqlx::query_scalar(
+            "SELECT post_id FROM post_revision WHERE content_hash = $1 ORDER BY post_id LIMIT 1",
+        )
+        .bind(content_hash)
+        .fetch_optional(&self.0)
+        .await?;
+        Ok(post_id)
+    }
+}
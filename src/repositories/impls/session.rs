@@ -0,0 +1,167 @@
+use crate::repositories::ReponsitoryError;
+use crate::repositories::session::{
+    PREVIOUS_HASH_LIMIT, Session, SessionCreate, SessionReponsitory,
+};
+use async_trait::async_trait;
+use sqlx::PgPool;
+use sqlx::types::chrono::{DateTime, Utc};
+use tracing::{Level, event, instrument};
+
+pub struct SqlxReponsitory(PgPool);
+
+impl SqlxReponsitory {
+    pub fn new(pool: PgPool) -> SqlxReponsitory {
+        tracing::info!("创建SessionRepository成功");
+        SqlxReponsitory(pool)
+    }
+}
+
+#[async_trait]
+impl SessionReponsitory for SqlxReponsitory {
+    #[instrument(name = "SessionReponsitory::add", level = "debug", skip_all, fields(user_id = session.user_id))]
+    async fn add(&self, session: SessionCreate) -> Result<Session, ReponsitoryError> {
+        event!(Level::DEBUG, user_id = session.user_id, "开始创建会话");
+
+        let new = sqlx::query_as::<_, Session>(
+            r#"INSERT INTO user_session (user_id, refresh_hash, user_agent, ip, expires_at)
+            VALUES ($1, $2, $3, $4, $5) RETURNING *"#,
+        )
+        .bind(session.user_id)
+        .bind(&session.refresh_hash)
+        .bind(&session.user_agent)
+        .bind(&session.ip)
+        .bind(session.expires_at)
+        .fetch_one(&self.0)
+        .await?;
+
+        event!(Level::DEBUG, session_id = new.id, "成功创建会话");
+        Ok(new)
+    }
+
+    #[instrument(name = "SessionReponsitory::find_by_id", level = "debug", skip(self))]
+    async fn find_by_id(&self, id: i32) -> Result<Session, ReponsitoryError> {
+        let session = sqlx::query_as::<_, Session>("SELECT * FROM user_session WHERE id = $1")
+            .bind(id)
+            .fetch_one(&self.0)
+            .await?;
+        Ok(session)
+    }
+
+    #[instrument(name = "SessionReponsitory::find_by_hash", level = "debug", skip_all)]
+    async fn find_by_hash(&self, hash: &str) -> Result<Session, ReponsitoryError> {
+        let session = sqlx::query_as::<_, Session>(
+            r#"SELECT * FROM user_session
+            WHERE refresh_hash = $1 OR previous_hashes @> ARRAY[$1]::VARCHAR(64)[] LIMIT 1"#,
+        )
+        .bind(hash)
+        .fetch_one(&self.0)
+        .await?;
+        Ok(session)
+    }
+
+    #[instrument(
+        name = "SessionReponsitory::rotate",
+        level = "debug",
+        skip(self, old_hash, new_hash)
+    )]
+    async fn rotate(
+        &self,
+        id: i32,
+        old_hash: &str,
+        new_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Session, ReponsitoryError> {
+        let session = sqlx::query_as::<_, Session>(
+            r#"UPDATE user_session
+            SET previous_hashes = (refresh_hash || previous_hashes)[1:$5], refresh_hash = $3,
+            last_used_at = NOW(), expires_at = $4
+            WHERE id = $1 AND refresh_hash = $2 AND revoked_at IS NULL AND expires_at > NOW()
+            RETURNING *"#,
+        )
+        .bind(id)
+        .bind(old_hash)
+        .bind(new_hash)
+        .bind(expires_at)
+        .bind(PREVIOUS_HASH_LIMIT)
+        .fetch_one(&self.0)
+        .await?;
+
+        event!(Level::DEBUG, session_id = id, "成功更换刷新令牌");
+        Ok(session)
+    }
+
+    #[instrument(name = "SessionReponsitory::list_active", level = "debug", skip(self))]
+    async fn list_active(&self, user_id: i32) -> Result<Vec<Session>, ReponsitoryError> {
+        let sessions = sqlx::query_as::<_, Session>(
+            r#"SELECT * FROM user_session
+            WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+            ORDER BY last_used_at DESC"#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.0)
+        .await?;
+        Ok(sessions)
+    }
+
+    #[instrument(name = "SessionReponsitory::revoke", level = "debug", skip(self))]
+    async fn revoke(&self, id: i32) -> Result<Session, ReponsitoryError> {
+        let session = sqlx::query_as::<_, Session>(
+            r#"UPDATE user_session SET revoked_at = NOW()
+            WHERE id = $1 AND revoked_at IS NULL RETURNING *"#,
+        )
+        .bind(id)
+        .fetch_one(&self.0)
+        .await?;
+
+        event!(Level::DEBUG, session_id = id, "成功撤销会话");
+        Ok(session)
+    }
+
+    #[instrument(name = "SessionReponsitory::revoke_user", level = "debug", skip(self))]
+    async fn revoke_user(&self, user_id: i32) -> Result<Vec<i32>, ReponsitoryError> {
+        let ids: Vec<i32> = sqlx::query_scalar(
+            r#"UPDATE user_session SET revoked_at = NOW()
+            WHERE user_id = $1 AND revoked_at IS NULL RETURNING id"#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.0)
+        .await?;
+
+        event!(
+            Level::DEBUG,
+            user_id = user_id,
+            session_count = ids.len(),
+            "成功撤销用户的所有会话"
+        );
+        Ok(ids)
+    }
+
+    #[instrument(
+        name = "SessionReponsitory::list_revoked_since",
+        level = "debug",
+        skip(self)
+    )]
+    async fn list_revoked_since(&self, since: DateTime<Utc>) -> Result<Vec<i32>, ReponsitoryError> {
+        let ids: Vec<i32> = sqlx::query_scalar("SELECT id FROM user_session WHERE revoked_at > $1")
+            .bind(since)
+            .fetch_all(&self.0)
+            .await?;
+        Ok(ids)
+    }
+
+    #[instrument(name = "SessionReponsitory::delete_stale", level = "debug", skip(self))]
+    async fn delete_stale(&self, before: DateTime<Utc>) -> Result<u64, ReponsitoryError> {
+        let result =
+            sqlx::query("DELETE FROM user_session WHERE expires_at < $1 OR revoked_at < $1")
+                .bind(before)
+                .execute(&self.0)
+                .await?;
+
+        event!(
+            Level::DEBUG,
+            deleted_count = result.rows_affected(),
+            "成功删除失效的会话"
+        );
+        Ok(result.rows_affected())
+    }
+}
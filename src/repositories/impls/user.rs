@@ -0,0 +1,133 @@
+use crate::repositories::ReponsitoryError;
+use crate::repositories::user::{Role, User, UserReponsitory, UserUpdate};
+use async_trait::async_trait;
+use sqlx::PgPool;
+use tracing::{Level, event, instrument};
+
+pub struct SqlxReponsitory(PgPool);
+
+impl SqlxReponsitory {
+    pub fn new(pool: PgPool) -> SqlxReponsitory {
+        tracing::info!("创建UserRepository成功");
+        SqlxReponsitory(pool)
+    }
+}
+
+#[async_trait]
+impl UserReponsitory for SqlxReponsitory {
+    #[instrument(name = "UserReponsitory::find_by_id", level = "debug", skip(self))]
+    async fn find_by_id(&self, id: i32) -> Result<User, ReponsitoryError> {
+        let user = sqlx::query_as::<_, User>(r#"SELECT * FROM "user" WHERE id = $1"#)
+            .bind(id)
+            .fetch_one(&self.0)
+            .await?;
+        Ok(user)
+    }
+
+    #[instrument(
+        name = "UserReponsitory::find_by_username",
+        level = "debug",
+        skip(self)
+    )]
+    async fn find_by_username(&self, username: &str) -> Result<User, ReponsitoryError> {
+        let user = sqlx::query_as::<_, User>(r#"SELECT * FROM "user" WHERE username = $1"#)
+            .bind(username)
+            .fetch_one(&self.0)
+            .await?;
+        Ok(user)
+    }
+
+    #[instrument(name = "UserReponsitory::list", level = "debug", skip(self))]
+    async fn list(&self) -> Result<Vec<User>, ReponsitoryError> {
+        let users = sqlx::query_as::<_, User>(r#"SELECT * FROM "user" ORDER BY id"#)
+            .fetch_all(&self.0)
+            .await?;
+        Ok(users)
+    }
+
+    #[instrument(
+        name = "UserReponsitory::add",
+        level = "debug",
+        skip(self, password_hash)
+    )]
+    async fn add(
+        &self,
+        username: &str,
+        password_hash: &str,
+        role: Role,
+    ) -> Result<Option<User>, ReponsitoryError> {
+        event!(Level::DEBUG, "开始添加用户");
+
+        let user = sqlx::query_as::<_, User>(
+            r#"INSERT INTO "user" (username, password_hash, role) VALUES ($1, $2, $3)
+            ON CONFLICT (username) DO NOTHING RETURNING *"#,
+        )
+        .bind(username)
+        .bind(password_hash)
+        .bind(role)
+        .fetch_optional(&self.0)
+        .await?;
+
+        event!(Level::DEBUG, user_id = ?user.as_ref().map(|user| user.id), "完成添加用户");
+        Ok(user)
+    }
+
+    #[instrument(
+        name = "UserReponsitory::upsert",
+        level = "debug",
+        skip(self, password_hash)
+    )]
+    async fn upsert(
+        &self,
+        username: &str,
+        password_hash: &str,
+        role: Role,
+    ) -> Result<User, ReponsitoryError> {
+        event!(Level::DEBUG, "开始保存用户");
+
+        let user = sqlx::query_as::<_, User>(
+            r#"INSERT INTO "user" (username, password_hash, role) VALUES ($1, $2, $3)
+            ON CONFLICT (username) DO UPDATE
+            SET password_hash = EXCLUDED.password_hash, role = EXCLUDED.role, updated_at = NOW()
+            RETURNING *"#,
+        )
+        .bind(username)
+        .bind(password_hash)
+        .bind(role)
+        .fetch_one(&self.0)
+        .await?;
+
+        event!(Level::DEBUG, user_id = user.id, "成功保存用户");
+        Ok(user)
+    }
+
+    #[instrument(name = "UserReponsitory::update", level = "debug", skip_all, fields(id = user.id))]
+    async fn update(&self, user: UserUpdate) -> Result<User, ReponsitoryError> {
+        event!(Level::DEBUG, user_id = user.id, role = ?user.role, "开始更新用户");
+
+        let updated = sqlx::query_as::<_, User>(
+            r#"UPDATE "user" SET password_hash = COALESCE($2, password_hash),
+            role = COALESCE($3, role), updated_at = NOW()
+            WHERE id = $1 RETURNING *"#,
+        )
+        .bind(user.id)
+        .bind(user.password_hash)
+        .bind(user.role)
+        .fetch_one(&self.0)
+        .await?;
+
+        event!(Level::DEBUG, user_id = updated.id, "成功更新用户");
+        Ok(updated)
+    }
+
+    #[instrument(name = "UserReponsitory::delete", level = "debug", skip(self))]
+    async fn delete(&self, id: i32) -> Result<User, ReponsitoryError> {
+        let user = sqlx::query_as::<_, User>(r#"DELETE FROM "user" WHERE id = $1 RETURNING *"#)
+            .bind(id)
+            .fetch_one(&self.0)
+            .await?;
+
+        event!(Level::DEBUG, user_id = id, "成功删除用户");
+        Ok(user)
+    }
+}
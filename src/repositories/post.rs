@@ -1,31 +1,150 @@
 use super::ReponsitoryError;
+use crate::util::{TextStats, TocEntry};
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use sqlx::types::Json;
 use sqlx::types::chrono::{DateTime, Utc};
+use std::fmt;
+use std::str::FromStr;
+/// 文章的发布状态
+/// - 只有`Published`的文章会出现在列表, 标签和搜索结果中
+/// - `Unlisted`的文章只能通过id或slug访问
+/// - `Scheduled`的文章在`publish_at`之后由后台任务改为`Published`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, sqlx::Type, Serialize, Deserialize)]
+#[sqlx(type_name = "post_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum PostStatus {
+    Draft,
+    Scheduled,
+    #[default]
+    Published,
+    Unlisted,
+    Private,
+}
+impl PostStatus {
+    /// 是否可以通过id或slug公开访问, 其他状态的文章只有能够修改它的用户可以读取
+    pub fn is_reachable(self) -> bool {
+        matches!(self, Self::Published | Self::Unlisted)
+    }
+}
+impl FromStr for PostStatus {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "draft" => Ok(Self::Draft),
+            "scheduled" => Ok(Self::Scheduled),
+            "published" => Ok(Self::Published),
+            "unlisted" => Ok(Self::Unlisted),
+            "private" => Ok(Self::Private),
+            _ => Err(format!("无效的发布状态: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for PostStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            Self::Draft => "draft",
+            Self::Scheduled => "scheduled",
+            Self::Published => "published",
+            Self::Unlisted => "unlisted",
+            Self::Private => "private",
+        };
+        f.write_str(status)
+    }
+}
+
 /// 存储的博文结构
 #[allow(dead_code)]
 #[derive(FromRow)]
 pub struct PostMeta {
     pub id: i32,
     pub title: String,
+    pub slug: String,
     pub tags: Json<Vec<String>>,
     pub first_publish: DateTime<Utc>,
     pub last_modify: DateTime<Utc>,
     pub count: i32,
+    pub toc: Json<Vec<TocEntry>>,
+    pub word_count: i32,
+    pub char_count: i32,
+    /// 预计阅读时间, 单位为分钟
+    pub reading_time: i32,
+    /// 文章列表中展示的摘要
+    pub excerpt: String,
+    pub status: PostStatus,
+    /// 定时发布的时间, 只对`Scheduled`的文章有意义
+    pub publish_at: Option<DateTime<Utc>>,
+    /// 上传文章的用户, 导入和从备份恢复的文章以及作者被删除的文章为空
+    pub author_id: Option<i32>,
+}
+
+/// `PostMeta`读取的所有列, 用于需要额外计算得分和摘要而不能使用`SELECT *`的查询
+pub const POST_META_COLUMNS: &str = "id, title, slug, tags, first_publish, last_modify, count, \
+    toc, word_count, char_count, reading_time, excerpt, status, publish_at, author_id";
+
+/// 关键词搜索的结果, 带有相关度得分和高亮摘要
+#[derive(FromRow)]
+pub struct PostSearchHit {
+    #[sqlx(flatten)]
+    pub meta: PostMeta,
+    pub score: f32,
+    pub snippet: String,
+}
+
+/// 分词结果, 按照来源分别写入`kw`列的A, B, C权重
+#[derive(Debug, Default)]
+pub struct Keywords {
+    pub title: Vec<String>,
+    pub headings: Vec<String>,
+    pub body: Vec<String>,
+    /// 用空格隔开词语的正文, 用于生成搜索结果的高亮摘要
+    pub segmented: String,
+}
+impl Keywords {
+    pub fn count(&self) -> usize {
+        self.title.len() + self.headings.len() + self.body.len()
+    }
 }
 
 pub struct PostMetaCreate {
+    /// 从备份恢复时保留原来的id, 为空时自动分配
+    pub id: Option<i32>,
     pub title: String,
+    pub slug: String,
     pub tags: Vec<String>,
-    pub kw: Vec<String>,
+    pub kw: Keywords,
+    pub toc: Vec<TocEntry>,
+    pub stats: TextStats,
+    pub excerpt: String,
+    pub status: PostStatus,
+    pub publish_at: Option<DateTime<Utc>>,
+    pub first_publish: Option<DateTime<Utc>>,
+    pub last_modify: Option<DateTime<Utc>>,
+    pub author_id: Option<i32>,
 }
 
 pub struct PostMetaUpdate {
     pub id: i32,
     pub title: String,
+    pub slug: String,
     pub tags: Vec<String>,
-    pub kw: Vec<String>,
+    pub kw: Keywords,
+    pub toc: Vec<TocEntry>,
+    pub stats: TextStats,
+    pub excerpt: String,
+    pub status: PostStatus,
+    pub publish_at: Option<DateTime<Utc>>,
+}
+
+/// 由文章内容得到的索引和统计, 重建索引时不修改文章的其他字段
+pub struct PostIndexUpdate {
+    pub id: i32,
+    pub kw: Keywords,
+    pub toc: Vec<TocEntry>,
+    pub stats: TextStats,
+    pub excerpt: String,
 }
 #[async_trait]
 pub trait PostMetaReponsitory: Send + Sync {
@@ -35,14 +154,73 @@ pub trait PostMetaReponsitory: Send + Sync {
         page_size: i32,
     ) -> Result<Vec<PostMeta>, ReponsitoryError>;
     async fn find_by_id(&self, id: i32) -> Result<PostMeta, ReponsitoryError>;
+    async fn find_by_slug(&self, slug: &str) -> Result<PostMeta, ReponsitoryError>;
+    /// 文章的作者, 包括回收站中的文章
+    async fn find_author(&self, id: i32) -> Result<Option<i32>, ReponsitoryError>;
+    /// slug是否已被除`exclude_id`之外的文章(包括回收站中的文章)使用
+    async fn slug_exists(&self, slug: &str, exclude_id: i32) -> Result<bool, ReponsitoryError>;
+    /// 列出所有文章, 包括回收站中的文章
+    async fn list_all(&self) -> Result<Vec<PostMeta>, ReponsitoryError>;
     async fn find_by_keywords(
         &self,
         keywords: &[String],
-    ) -> Result<Vec<PostMeta>, ReponsitoryError>;
+        offset: i32,
+        limit: i32,
+    ) -> Result<Vec<PostSearchHit>, ReponsitoryError>;
     async fn find_by_tags(&self, tags: &[String]) -> Result<Vec<PostMeta>, ReponsitoryError>;
     async fn add(&self, post: PostMetaCreate) -> Result<PostMeta, ReponsitoryError>;
     async fn update(&self, post: PostMetaUpdate) -> Result<PostMeta, ReponsitoryError>;
-    async fn delete(&self, id: i32) -> Result<(), ReponsitoryError>;
+    /// 重新写入由内容得到的字段, 包括回收站中的文章, 不修改最后修改时间
+    async fn reindex(&self, post: PostIndexUpdate) -> Result<PostMeta, ReponsitoryError>;
+    /// 永久删除文章, 同时删除其评论
+    async fn delete(&self, id: i32) -> Result<PostMeta, ReponsitoryError>;
+    /// 将文章移入回收站, 回收站中的文章不会出现在任何查询结果中
+    async fn trash(&self, id: i32) -> Result<PostMeta, ReponsitoryError>;
+    async fn restore(&self, id: i32) -> Result<PostMeta, ReponsitoryError>;
+    async fn list_trash(&self) -> Result<Vec<PostMeta>, ReponsitoryError>;
     async fn list_all_tags(&self) -> Result<Vec<String>, ReponsitoryError>;
+    /// 将已到发布时间的定时文章改为已发布, 返回被发布的文章
+    async fn publish_due(&self) -> Result<Vec<PostMeta>, ReponsitoryError>;
+    /// 最早的一篇定时文章的发布时间
+    async fn next_scheduled(&self) -> Result<Option<DateTime<Utc>>, ReponsitoryError>;
 }
 pub use super::impls::post::SqlxReponsitory;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 解构时没有使用`..`, `PostMeta`增加字段后这里不更新就无法编译
+    macro_rules! post_meta_fields {
+        ($($field:ident),* $(,)?) => {{
+            fn exhaustive(meta: PostMeta) {
+                let PostMeta { $($field: _),* } = meta;
+            }
+            let _ = exhaustive;
+            vec![$(stringify!($field)),*]
+        }};
+    }
+
+    #[test]
+    fn post_meta_columns_cover_every_field() {
+        let fields = post_meta_fields!(
+            id,
+            title,
+            slug,
+            tags,
+            first_publish,
+            last_modify,
+            count,
+            toc,
+            word_count,
+            char_count,
+            reading_time,
+            excerpt,
+            status,
+            publish_at,
+            author_id,
+        );
+        let columns: Vec<&str> = POST_META_COLUMNS.split(',').map(str::trim).collect();
+        assert_eq!(columns, fields);
+    }
+}
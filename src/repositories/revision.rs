@@ -0,0 +1,55 @@
+use super::ReponsitoryError;
+use async_trait::async_trait;
+use sqlx::FromRow;
+use sqlx::types::Json;
+use sqlx::types::chrono::{DateTime, Utc};
+
+/// 文章的一个历史版本, 不包含文件内容
+#[derive(FromRow)]
+pub struct Revision {
+    pub id: i32,
+    pub post_id: i32,
+    /// 同一篇文章内从1开始递增的版本号
+    pub revision: i32,
+    pub title: String,
+    pub slug: String,
+    pub tags: Json<Vec<String>>,
+    /// 文件内容的sha256
+    pub content_hash: String,
+    pub author: Option<String>,
+    pub message: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 带有文件内容的历史版本
+#[derive(FromRow)]
+pub struct RevisionContent {
+    #[sqlx(flatten)]
+    pub meta: Revision,
+    pub content: String,
+}
+
+pub struct RevisionCreate {
+    pub post_id: i32,
+    pub title: String,
+    pub slug: String,
+    pub tags: Vec<String>,
+    pub content: String,
+    pub content_hash: String,
+    pub author: Option<String>,
+    pub message: Option<String>,
+}
+
+#[async_trait]
+pub trait PostRevisionReponsitory: Send + Sync {
+    /// 写入新的版本, 版本号为该文章已有的最大版本号加一
+    async fn add(&self, revision: RevisionCreate) -> Result<Revision, ReponsitoryError>;
+    /// 按版本号从新到旧列出文章的所有版本
+    async fn list(&self, post_id: i32) -> Result<Vec<Revision>, ReponsitoryError>;
+    async fn find(&self, post_id: i32, revision: i32) -> Result<RevisionContent, ReponsitoryError>;
+    /// 文章是否已经有历史版本
+    async fn exists(&self, post_id: i32) -> Result<bool, ReponsitoryError>;
+    /// 查找任意一个版本的内容哈希为`content_hash`的文章
+    async fn find_post_by_hash(&self, content_hash: &str) -> Result<Option<i32>, ReponsitoryError>;
+}
+pub use super::impls::revision::SqlxReponsitory;
@@ -0,0 +1,59 @@
+use super::ReponsitoryError;
+use async_trait::async_trait;
+use sqlx::FromRow;
+use sqlx::types::chrono::{DateTime, Utc};
+
+/// 换掉的刷新令牌在这么多次刷新之内再次被使用时能够发现令牌泄露,
+/// 更早的令牌被使用时只会被当作不存在的令牌拒绝
+pub const PREVIOUS_HASH_LIMIT: i32 = 8;
+
+/// 登录会话, 每次登录创建一个, 保存刷新令牌的哈希和登录设备的信息
+#[derive(Debug, FromRow)]
+pub struct Session {
+    pub id: i32,
+    pub user_id: i32,
+    pub refresh_hash: String,
+    /// 最近换掉的刷新令牌的哈希, 最近的在前, 最多保留`PREVIOUS_HASH_LIMIT`个
+    pub previous_hashes: Vec<String>,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+pub struct SessionCreate {
+    pub user_id: i32,
+    pub refresh_hash: String,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait SessionReponsitory: Send + Sync {
+    async fn add(&self, session: SessionCreate) -> Result<Session, ReponsitoryError>;
+    async fn find_by_id(&self, id: i32) -> Result<Session, ReponsitoryError>;
+    /// 查找当前或最近换掉的刷新令牌为`hash`的会话, 包括已撤销和已过期的会话
+    async fn find_by_hash(&self, hash: &str) -> Result<Session, ReponsitoryError>;
+    /// 将刷新令牌从`old_hash`换为`new_hash`并延长有效期,
+    /// 会话已被撤销或令牌已被其他请求换掉时返回`NotFound`
+    async fn rotate(
+        &self,
+        id: i32,
+        old_hash: &str,
+        new_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Session, ReponsitoryError>;
+    /// 用户没有撤销且没有过期的会话, 最近使用的在前
+    async fn list_active(&self, user_id: i32) -> Result<Vec<Session>, ReponsitoryError>;
+    async fn revoke(&self, id: i32) -> Result<Session, ReponsitoryError>;
+    /// 撤销用户的所有会话, 返回被撤销的会话id
+    async fn revoke_user(&self, user_id: i32) -> Result<Vec<i32>, ReponsitoryError>;
+    /// 在`since`之后被撤销的会话id
+    async fn list_revoked_since(&self, since: DateTime<Utc>) -> Result<Vec<i32>, ReponsitoryError>;
+    /// 删除在`before`之前过期或被撤销的会话, 返回删除的数量
+    async fn delete_stale(&self, before: DateTime<Utc>) -> Result<u64, ReponsitoryError>;
+}
+pub use super::impls::session::SqlxReponsitory;
@@ -0,0 +1,92 @@
+use super::ReponsitoryError;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use sqlx::types::chrono::{DateTime, Utc};
+use std::fmt;
+use std::str::FromStr;
+
+/// 用户的角色, 按权限从小到大排列, 较大的角色拥有较小角色的全部权限
+/// - `Commenter`只能修改和删除自己发表的评论
+/// - `Author`可以上传文章和资源, 只能修改自己的文章
+/// - `Editor`可以修改所有文章, 批量导入文章和管理所有评论
+/// - `Admin`可以管理用户, 导出备份和清理资源
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, sqlx::Type, Serialize, Deserialize,
+)]
+#[sqlx(type_name = "user_role", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[default]
+    Commenter,
+    Author,
+    Editor,
+    Admin,
+}
+impl FromStr for Role {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "commenter" => Ok(Self::Commenter),
+            "author" => Ok(Self::Author),
+            "editor" => Ok(Self::Editor),
+            "admin" => Ok(Self::Admin),
+            _ => Err(format!("无效的角色: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let role = match self {
+            Self::Commenter => "commenter",
+            Self::Author => "author",
+            Self::Editor => "editor",
+            Self::Admin => "admin",
+        };
+        f.write_str(role)
+    }
+}
+
+/// 用户账号, 密码以argon2哈希保存
+#[derive(Debug, FromRow)]
+pub struct User {
+    pub id: i32,
+    pub username: String,
+    pub password_hash: String,
+    pub role: Role,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 为空的字段保持不变
+pub struct UserUpdate {
+    pub id: i32,
+    pub password_hash: Option<String>,
+    pub role: Option<Role>,
+}
+
+#[async_trait]
+pub trait UserReponsitory: Send + Sync {
+    async fn find_by_id(&self, id: i32) -> Result<User, ReponsitoryError>;
+    async fn find_by_username(&self, username: &str) -> Result<User, ReponsitoryError>;
+    async fn list(&self) -> Result<Vec<User>, ReponsitoryError>;
+    /// 添加用户, 用户名已经存在时返回空
+    async fn add(
+        &self,
+        username: &str,
+        password_hash: &str,
+        role: Role,
+    ) -> Result<Option<User>, ReponsitoryError>;
+    /// 添加用户, 用户名已经存在时更新密码和角色
+    async fn upsert(
+        &self,
+        username: &str,
+        password_hash: &str,
+        role: Role,
+    ) -> Result<User, ReponsitoryError>;
+    async fn update(&self, user: UserUpdate) -> Result<User, ReponsitoryError>;
+    /// 删除用户, 其文章和评论保留, 不再属于任何用户
+    async fn delete(&self, id: i32) -> Result<User, ReponsitoryError>;
+}
+pub use super::impls::user::SqlxReponsitory;
@@ -1,9 +1,17 @@
 use crate::state::AppState;
 use axum::Router;
+mod asset;
+mod auth;
+mod backup;
 mod post;
+mod user;
 mod comment;
 pub async fn new() -> Router<AppState> {
     Router::new()
+        .nest("/auth", auth::new().await)
         .nest("/post", post::new().await)
         .nest("/comment", comment::new().await)
+        .nest("/asset", asset::new().await)
+        .nest("/backup", backup::new().await)
+        .nest("/user", user::new().await)
 }
@@ -0,0 +1,206 @@
+use crate::models::SuccessResponse;
+use crate::models::asset::{AssetCleanup, AssetRead, AssetVariantQuery};
+use crate::service::{MAX_ASSET_SIZE, Principal, ServiceError};
+use crate::state::AppState;
+use axum::body::Body;
+use axum::extract::{DefaultBodyLimit, Multipart, Path, Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::{
+    Router,
+    routing::{get, post},
+};
+use tracing::{Level, event};
+
+/// 资源的内容由id唯一确定, 可以被永久缓存
+const CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+pub async fn new() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/",
+            // 为multipart的其他字段和边界预留空间
+            post(upload_asset).layer(DefaultBodyLimit::max(MAX_ASSET_SIZE + 64 * 1024)),
+        )
+        .route("/{id}", get(read_asset))
+        .route("/{id}/info", get(read_asset_info))
+        .route("/cleanup", post(cleanup_assets))
+}
+
+/// 上传图片或附件, 文件放在表单的`file`字段中, 可选的`post_id`字段将资源关联到文章
+pub async fn upload_asset(
+    State(state): State<AppState>,
+    principal: Principal,
+    mut multipart: Multipart,
+) -> Result<SuccessResponse<AssetRead>, ServiceError> {
+    event!(Level::INFO, username = %principal.username, "开始处理资源上传");
+
+    let mut file = None;
+    let mut post_id = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| ServiceError::BadArugment(e.to_string()))?
+    {
+        match field.name() {
+            Some("file") => {
+                let filename = field.file_name().unwrap_or("file").trim().to_string();
+                let data = field
+                    .bytes()
+                    .await
+                    .map_err(|e| ServiceError::BadArugment(e.to_string()))?;
+                file = Some((filename, data));
+            }
+            Some("post_id") => {
+                let text = field
+                    .text()
+                    .await
+                    .map_err(|e| ServiceError::BadArugment(e.to_string()))?;
+                let id = text
+                    .trim()
+                    .parse::<i32>()
+                    .ok()
+                    .filter(|id| *id > 0)
+                    .ok_or_else(|| ServiceError::BadArugment("无效的文章id".to_string()))?;
+                post_id = Some(id);
+            }
+            _ => return Err(ServiceError::BadArugment("无效的字段".to_string())),
+        }
+    }
+    let Some((filename, data)) = file else {
+        event!(Level::WARN, "没有上传文件");
+        return Err(ServiceError::BadArugment("缺少文件".to_string()));
+    };
+    if filename.chars().count() > 255 {
+        event!(Level::WARN, "文件名过长");
+        return Err(ServiceError::BadArugment(
+            "文件名长度不能超过255".to_string(),
+        ));
+    }
+
+    let asset = state
+        .asset_service
+        .upload(&principal, filename, data, post_id)
+        .await?;
+
+    event!(Level::INFO, asset_id = asset.id, "成功上传资源");
+    Ok(SuccessResponse::new(AssetRead::new(
+        asset,
+        state.asset_service.image_widths(),
+    )))
+}
+
+/// 返回资源的信息, 图片包含可以用于srcset的各个尺寸和格式
+pub async fn read_asset_info(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<SuccessResponse<AssetRead>, ServiceError> {
+    event!(Level::INFO, asset_id = id, "开始获取资源信息");
+
+    if id <= 0 {
+        event!(Level::WARN, asset_id = id, "无效的资源ID");
+        return Err(ServiceError::BadArugment("无效的id".to_string()));
+    }
+    let asset = state.asset_service.read(id).await?;
+
+    event!(Level::INFO, asset_id = id, "成功获取资源信息");
+    Ok(SuccessResponse::new(AssetRead::new(
+        asset,
+        state.asset_service.image_widths(),
+    )))
+}
+
+/// 返回资源文件, 客户端缓存的版本仍然有效时返回304;
+/// 图片可以通过`w`和`format`请求缩放或转换格式后的版本, 第一次请求时生成
+pub async fn read_asset(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Query(query): Query<AssetVariantQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ServiceError> {
+    event!(Level::INFO, asset_id = id, "开始获取资源");
+
+    if id <= 0 {
+        event!(Level::WARN, asset_id = id, "无效的资源ID");
+        return Err(ServiceError::BadArugment("无效的id".to_string()));
+    }
+    let asset = state.asset_service.read(id).await?;
+    let variant = if query.w.is_some() || query.format.is_some() {
+        state.asset_service.variant(&asset, query.w, query.format)?
+    } else {
+        None
+    };
+    let etag = match &variant {
+        Some(variant) => format!("\"{}.{}\"", asset.hash, variant.name()),
+        None => format!("\"{}\"", asset.hash),
+    };
+    let cached = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .any(|tag| tag.trim() == etag || tag.trim() == "*")
+        });
+    if cached {
+        event!(Level::INFO, asset_id = id, "资源没有变化");
+        return Ok((
+            StatusCode::NOT_MODIFIED,
+            [
+                (header::ETAG, etag),
+                (header::CACHE_CONTROL, CACHE_CONTROL.to_string()),
+            ],
+        )
+            .into_response());
+    }
+    if let Some(variant) = variant {
+        let data = state.asset_service.read_variant(&asset, variant).await?;
+        event!(Level::INFO, asset_id = id, variant = %variant.name(), "开始发送衍生图片");
+        return Ok((
+            [
+                (header::CONTENT_TYPE, variant.format.mime().to_string()),
+                (header::ETAG, etag),
+                (header::CACHE_CONTROL, CACHE_CONTROL.to_string()),
+                (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+            ],
+            data,
+        )
+            .into_response());
+    }
+    let stream = state.asset_service.read_stream(&asset).await?;
+
+    event!(Level::INFO, asset_id = id, mime = %asset.mime, "开始发送资源");
+    Ok((
+        [
+            (header::CONTENT_TYPE, asset.mime),
+            (header::CONTENT_LENGTH, asset.size.to_string()),
+            (header::ETAG, etag),
+            (header::CACHE_CONTROL, CACHE_CONTROL.to_string()),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+        ],
+        Body::from_stream(stream),
+    )
+        .into_response())
+}
+
+/// 删除上传超过保护期且没有被任何文章引用的资源, 返回被删除(或将要删除)的资源
+pub async fn cleanup_assets(
+    State(state): State<AppState>,
+    principal: Principal,
+    Query(cleanup): Query<AssetCleanup>,
+) -> Result<SuccessResponse<Vec<AssetRead>>, ServiceError> {
+    event!(Level::INFO, username = %principal.username, dry_run = cleanup.dry_run, "开始处理资源清理");
+
+    let removed = state
+        .asset_service
+        .cleanup(&principal, cleanup.dry_run)
+        .await?;
+
+    event!(Level::INFO, asset_count = removed.len(), "完成资源清理");
+    Ok(SuccessResponse::new(
+        removed
+            .into_iter()
+            .map(|asset| AssetRead::new(asset, state.asset_service.image_widths()))
+            .collect(),
+    ))
+}
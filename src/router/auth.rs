@@ -0,0 +1,199 @@
+use crate::models::SuccessResponse;
+use crate::models::auth::{Device, LoginRequest, RefreshRequest, SessionRead, TokenRead};
+use crate::service::{Principal, ServiceError};
+use crate::state::AppState;
+use axum::extract::{ConnectInfo, FromRequestParts, OptionalFromRequestParts, Path, State};
+use axum::http::header;
+use axum::http::request::Parts;
+use axum::{
+    Json, Router,
+    routing::{delete, get, post},
+};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use tracing::{Level, event};
+
+/// 设备信息的最大长度, 超出的部分被截断
+const MAX_USER_AGENT_LEN: usize = 512;
+const MAX_IP_LEN: usize = 64;
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+pub async fn new() -> Router<AppState> {
+    Router::new()
+        .route("/login", post(login))
+        .route("/refresh", post(refresh))
+        .route("/logout", post(logout))
+        .route("/sessions", get(list_sessions))
+        .route("/sessions/{id}", delete(revoke_session))
+}
+
+/// 从`Authorization: Bearer <token>`中解析发起请求的用户;
+/// 作为处理函数的参数时, 没有令牌或令牌无效的请求返回401
+impl FromRequestParts<AppState> for Principal {
+    type Rejection = ServiceError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let Some(token) = bearer_token(parts) else {
+            event!(Level::WARN, "请求没有携带令牌");
+            return Err(ServiceError::Unauthorized("需要登录".to_string()));
+        };
+        state.auth_service.verify(token)
+    }
+}
+
+/// 以`Option<Principal>`作为参数时允许匿名请求, 但携带了无效的令牌时仍然返回401
+impl OptionalFromRequestParts<AppState> for Principal {
+    type Rejection = ServiceError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        bearer_token(parts)
+            .map(|token| state.auth_service.verify(token))
+            .transpose()
+    }
+}
+
+/// 从请求头中读取登录设备的信息, 只用于在会话列表中展示;
+/// 部署在反向代理之后时以`X-Forwarded-For`中的第一个地址作为ip
+impl FromRequestParts<AppState> for Device {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let read_header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+        let forwarded = read_header(X_FORWARDED_FOR)
+            .and_then(|value| value.split(',').next())
+            .map(str::trim);
+        let ip = forwarded.map(str::to_string).or_else(|| {
+            parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip().to_string())
+        });
+        Ok(Device {
+            user_agent: read_header(header::USER_AGENT.as_str())
+                .map(|agent| agent.chars().take(MAX_USER_AGENT_LEN).collect()),
+            ip: ip.map(|ip| ip.chars().take(MAX_IP_LEN).collect()),
+        })
+    }
+}
+
+fn bearer_token(parts: &Parts) -> Option<&str> {
+    parts
+        .headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+/// 使用用户名和密码登录, 返回用于其他请求的访问令牌和用于刷新访问令牌的刷新令牌
+pub async fn login(
+    State(state): State<AppState>,
+    device: Device,
+    Json(request): Json<LoginRequest>,
+) -> Result<SuccessResponse<TokenRead>, ServiceError> {
+    event!(Level::INFO, "开始处理登录请求");
+
+    if request.username.trim().is_empty() || request.password.is_empty() {
+        event!(Level::WARN, "用户名或密码为空");
+        return Err(ServiceError::BadArugment(
+            "用户名和密码不能为空".to_string(),
+        ));
+    }
+    let token = state
+        .auth_service
+        .login(request.username.trim(), request.password, device)
+        .await?;
+
+    event!(Level::INFO, "成功处理登录请求");
+    Ok(SuccessResponse::new(token))
+}
+
+/// 使用刷新令牌换取新的令牌, 返回的刷新令牌替代原来的刷新令牌
+pub async fn refresh(
+    State(state): State<AppState>,
+    Json(request): Json<RefreshRequest>,
+) -> Result<SuccessResponse<TokenRead>, ServiceError> {
+    event!(Level::INFO, "开始处理刷新令牌请求");
+
+    if request.refresh_token.trim().is_empty() {
+        event!(Level::WARN, "刷新令牌为空");
+        return Err(ServiceError::BadArugment("刷新令牌不能为空".to_string()));
+    }
+    let token = state
+        .auth_service
+        .refresh(request.refresh_token.trim())
+        .await?;
+
+    event!(Level::INFO, "成功处理刷新令牌请求");
+    Ok(SuccessResponse::new(token))
+}
+
+/// 退出登录, 撤销当前会话
+pub async fn logout(
+    State(state): State<AppState>,
+    principal: Principal,
+) -> Result<SuccessResponse<()>, ServiceError> {
+    event!(Level::INFO, username = %principal.username, "开始处理退出登录");
+
+    state.auth_service.logout(&principal).await?;
+
+    event!(Level::INFO, username = %principal.username, "成功退出登录");
+    Ok(SuccessResponse::new(()))
+}
+
+/// 列出当前用户登录过且仍然有效的设备
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    principal: Principal,
+) -> Result<SuccessResponse<Vec<SessionRead>>, ServiceError> {
+    event!(Level::INFO, username = %principal.username, "开始获取会话列表");
+
+    let sessions = state.auth_service.list_sessions(&principal).await?;
+
+    event!(
+        Level::INFO,
+        session_count = sessions.len(),
+        "成功获取会话列表"
+    );
+    Ok(SuccessResponse::new(
+        sessions
+            .into_iter()
+            .map(|session| SessionRead::new(session, principal.session_id))
+            .collect(),
+    ))
+}
+
+/// 撤销一个会话, 使对应设备退出登录
+pub async fn revoke_session(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+) -> Result<SuccessResponse<()>, ServiceError> {
+    event!(Level::INFO, username = %principal.username, session_id = id, "开始撤销会话");
+
+    if id <= 0 {
+        event!(Level::WARN, session_id = id, "无效的会话ID");
+        return Err(ServiceError::BadArugment("无效的id".to_string()));
+    }
+    state.auth_service.revoke_session(&principal, id).await?;
+
+    event!(Level::INFO, session_id = id, "成功撤销会话");
+    Ok(SuccessResponse::new(()))
+}
@@ -0,0 +1,39 @@
+use crate::service::{Principal, ServiceError};
+use crate::state::AppState;
+use axum::extract::State;
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use axum::{Router, routing::get};
+use chrono::Utc;
+use tracing::{Level, event};
+
+pub async fn new() -> Router<AppState> {
+    Router::new().route("/export", get(export_backup))
+}
+
+/// 导出整个站点的备份, 返回tar.gz文件, 使用`restore`命令恢复
+pub async fn export_backup(
+    State(state): State<AppState>,
+    principal: Principal,
+) -> Result<Response, ServiceError> {
+    event!(Level::INFO, username = %principal.username, "开始处理导出备份");
+
+    let archive = state
+        .post_service
+        .export_archive(&principal, &state.asset_service)
+        .await?;
+    let disposition = format!(
+        "attachment; filename=\"backup-{}.tar.gz\"",
+        Utc::now().format("%Y%m%d%H%M%S")
+    );
+
+    event!(Level::INFO, archive_size = archive.len(), "成功导出备份");
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/gzip".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        archive,
+    )
+        .into_response())
+}
@@ -1,6 +1,6 @@
 use crate::models::SuccessResponse;
 use crate::models::comment::*;
-use crate::service::ServiceError;
+use crate::service::{Principal, ServiceError};
 use crate::state::AppState;
 use axum::extract::{Path, State};
 use axum::{
@@ -18,9 +18,11 @@ pub async fn new() -> Router<AppState> {
         .route("/post/{post_id}", get(get_comments_by_post_id))
 }
 
-/// 创建新评论
+/// 创建新评论, 可以匿名评论; 携带令牌时评论属于该用户, 之后可以由本人修改和删除
+/// 所属文章未公开时视为不存在, 不能评论
 pub async fn create_comment(
     State(state): State<AppState>,
+    principal: Option<Principal>,
     Json(comment): Json<CommentCreate>,
 ) -> Result<SuccessResponse<CommentRead>, ServiceError> {
     event!(Level::INFO, post_id = comment.post_id, author = %comment.author, "开始创建新评论");
@@ -38,15 +40,17 @@ pub async fn create_comment(
         ));
     }
 
-    let new_comment = state.comment_service.create(comment).await?;
+    state.post_service.read_one(principal.as_ref(), comment.post_id).await?;
+    let new_comment = state.comment_service.create(principal.as_ref(), comment).await?;
     
     event!(Level::INFO, comment_id = new_comment.id, post_id = new_comment.post_id, author = %new_comment.author, "成功创建新评论");
     Ok(SuccessResponse::new(new_comment))
 }
 
-/// 获取单个评论
+/// 获取单个评论, 所属文章未公开时只有能够修改文章的用户可以获取
 pub async fn get_comment(
     State(state): State<AppState>,
+    principal: Option<Principal>,
     Path(id): Path<i32>,
 ) -> Result<SuccessResponse<CommentRead>, ServiceError> {
     event!(Level::INFO, comment_id = id, "开始获取评论");
@@ -57,6 +61,7 @@ pub async fn get_comment(
     }
 
     let comment = state.comment_service.find_by_id(id).await?;
+    state.post_service.read_one(principal.as_ref(), comment.post_id).await?;
     
     event!(Level::INFO, comment_id = id, post_id = comment.post_id, author = %comment.author, "成功获取评论");
     Ok(SuccessResponse::new(comment))
@@ -65,10 +70,11 @@ pub async fn get_comment(
 /// 更新评论
 pub async fn update_comment(
     State(state): State<AppState>,
+    principal: Principal,
     Path(id): Path<i32>,
     Json(comment): Json<CommentUpdate>,
 ) -> Result<SuccessResponse<CommentRead>, ServiceError> {
-    event!(Level::INFO, comment_id = id, "开始更新评论");
+    event!(Level::INFO, username = %principal.username, comment_id = id, "开始更新评论");
     
     if id <= 0 {
         event!(Level::WARN, comment_id = id, "无效的评论ID");
@@ -81,7 +87,7 @@ pub async fn update_comment(
         ));
     }
 
-    let updated_comment = state.comment_service.update(id, comment).await?;
+    let updated_comment = state.comment_service.update(&principal, id, comment).await?;
     
     event!(Level::INFO, comment_id = id, post_id = updated_comment.post_id, "成功更新评论");
     Ok(SuccessResponse::new(updated_comment))
@@ -90,24 +96,26 @@ pub async fn update_comment(
 /// 删除评论
 pub async fn delete_comment(
     State(state): State<AppState>,
+    principal: Principal,
     Path(id): Path<i32>,
 ) -> Result<SuccessResponse<CommentRead>, ServiceError> {
-    event!(Level::INFO, comment_id = id, "开始删除评论");
+    event!(Level::INFO, username = %principal.username, comment_id = id, "开始删除评论");
     
     if id <= 0 {
         event!(Level::WARN, comment_id = id, "无效的评论ID");
         return Err(ServiceError::BadArugment("无效的id".to_string()));
     }
 
-    let deleted_comment = state.comment_service.delete(id).await?;
+    let deleted_comment = state.comment_service.delete(&principal, id).await?;
     
     event!(Level::INFO, comment_id = id, post_id = deleted_comment.post_id, author = %deleted_comment.author, "成功删除评论");
     Ok(SuccessResponse::new(deleted_comment))
 }
 
-/// 获取指定文章的所有评论
+/// 获取指定文章的所有评论, 文章未公开时只有能够修改文章的用户可以获取
 pub async fn get_comments_by_post_id(
     State(state): State<AppState>,
+    principal: Option<Principal>,
     Path(post_id): Path<i32>,
 ) -> Result<SuccessResponse<Vec<CommentRead>>, ServiceError> {
     event!(Level::INFO, post_id = post_id, "开始获取文章的所有评论");
@@ -117,6 +125,7 @@ pub async fn get_comments_by_post_id(
         return Err(ServiceError::BadArugment("无效的post_id".to_string()));
     }
 
+    state.post_service.read_one(principal.as_ref(), post_id).await?;
     let comments = state.comment_service.find_by_post_id(post_id).await?;
     
     event!(Level::INFO, post_id = post_id, comment_count = comments.len(), "成功获取文章的所有评论");
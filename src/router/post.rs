@@ -1,23 +1,56 @@
+use crate::import::{self, ImportFormat};
 use crate::models::Pagenigation;
 use crate::models::SuccessResponse;
+use crate::models::import::ImportReport;
 use crate::models::post::*;
-use crate::service::ServiceError;
+use crate::repositories::post::PostStatus;
+use crate::service::{Principal, ServiceError};
 use crate::state::AppState;
+use crate::util::{MARKDOWN_UTIL, clean_tags, slugify};
+use axum::body::Body;
+use axum::extract::DefaultBodyLimit;
 use axum::extract::Query;
 use axum::extract::{Path, State};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
 use axum::{
     Router,
     extract::Multipart,
     routing::{get, post},
 };
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use tracing::{Level, event};
 
 pub async fn new() -> Router<AppState> {
     Router::new()
-        .route("/upload", post(add_post))
+        .route(
+            "/upload",
+            post(add_post).layer(DefaultBodyLimit::max(import::MAX_BUNDLE_SIZE)),
+        )
+        .route(
+            "/import",
+            post(import_posts).layer(DefaultBodyLimit::max(import::MAX_ARCHIVE_SIZE)),
+        )
         .route("/{id}/meta", get(read_post_meta))
-        .route("/{id}", get(read_post_content))
+        .route("/{id}/raw", get(read_post_raw))
+        .route(
+            "/{id}",
+            get(read_post_content)
+                .put(update_post)
+                .delete(delete_post)
+                .layer(DefaultBodyLimit::max(import::MAX_BUNDLE_SIZE)),
+        )
+        .route("/{id}/restore", post(restore_post))
+        .route("/{id}/revisions", get(list_revisions))
+        .route("/{id}/revisions/{revision}", get(read_revision))
+        .route("/{id}/revisions/{revision}/rollback", post(rollback_post))
+        .route("/{id}/diff", get(diff_revisions))
+        .route("/trash", get(list_trash))
+        .route("/by-slug/{slug}", get(read_post_by_slug))
+        .route("/by-alias", get(read_post_by_alias))
         .route("/list", get(list_posts))
+        .route("/search", get(search_posts))
 }
 
 /// 分页访问报告的元数据
@@ -39,26 +72,148 @@ pub async fn list_posts(
         posts.into_iter().map(|p| p.into()).collect(),
     ))
 }
+/// 按关键词搜索文章, 结果按相关度排序并带有高亮摘要
+///
+/// # Arguments
+///
+/// - `Query(search)` (`PostSearch`) - 搜索语句和分页参数.
+///
+pub async fn search_posts(
+    Query(search): Query<PostSearch>,
+    State(state): State<AppState>,
+) -> Result<SuccessResponse<Vec<PostSearchRead>>, ServiceError> {
+    event!(Level::INFO, q = %search.q, offset = search.offset, page_size = search.page_size, "开始搜索文章");
+
+    if search.q.trim().is_empty() || search.q.chars().count() > 255 {
+        event!(Level::WARN, q_length = search.q.len(), "搜索语句长度无效");
+        return Err(ServiceError::BadArugment(
+            "搜索语句长度不能超过255或为空".to_string(),
+        ));
+    }
+    if search.offset < 0 || search.page_size <= 0 || search.page_size > 50 {
+        event!(
+            Level::WARN,
+            offset = search.offset,
+            page_size = search.page_size,
+            "分页参数无效"
+        );
+        return Err(ServiceError::BadArugment("无效的分页参数".to_string()));
+    }
+
+    let invalid_weight = |weight: Option<f32>| weight.is_some_and(|w| !(w >= 0.0 && w.is_finite()));
+    if search.k.is_some_and(|k| !(k > 0.0 && k.is_finite()))
+        || invalid_weight(search.keyword_weight)
+        || invalid_weight(search.semantic_weight)
+    {
+        event!(Level::WARN, k = ?search.k, keyword_weight = ?search.keyword_weight, semantic_weight = ?search.semantic_weight, "融合参数无效");
+        return Err(ServiceError::BadArugment("无效的融合参数".to_string()));
+    }
+
+    let hits = state.post_service.search(search).await?;
+
+    event!(Level::INFO, post_count = hits.len(), "成功搜索文章");
+    Ok(SuccessResponse::new(hits))
+}
+/// 获取文章内容, `?format=html`时返回渲染后的html
 pub async fn read_post_content(
     State(state): State<AppState>,
+    principal: Option<Principal>,
     Path(id): Path<i32>,
+    Query(read): Query<PostRead>,
 ) -> Result<SuccessResponse<Post>, ServiceError> {
-    event!(Level::INFO, post_id = id, "开始获取文章内容");
+    event!(Level::INFO, post_id = id, format = ?read.format, "开始获取文章内容");
 
     if id <= 0 {
         event!(Level::WARN, post_id = id, "无效的文章ID");
         return Err(ServiceError::BadArugment("无效的id".to_string()));
     }
-    let post = state.post_service.read_one(id).await?;
-    let path = state.post_service.build_file_path(&post.title).await;
-    let content = tokio::fs::read_to_string(path).await?;
+    let post = state.post_service.read_one(principal.as_ref(), id).await?;
+    let content = state
+        .post_service
+        .read_formatted(&post, read.format)
+        .await?;
 
     event!(Level::INFO, post_id = id, title = %post.title, "成功获取文章内容");
-    Ok(SuccessResponse::new(Post::with_content(post, content)))
+    Ok(SuccessResponse::new(Post::with_content(
+        post,
+        content,
+        read.format,
+    )))
+}
+
+/// 通过slug获取文章内容, 用于生成更友好的url
+pub async fn read_post_by_slug(
+    State(state): State<AppState>,
+    principal: Option<Principal>,
+    Path(slug): Path<String>,
+    Query(read): Query<PostRead>,
+) -> Result<SuccessResponse<Post>, ServiceError> {
+    event!(Level::INFO, slug = %slug, format = ?read.format, "开始根据slug获取文章内容");
+
+    let post = state
+        .post_service
+        .read_by_slug(principal.as_ref(), &slug)
+        .await?;
+    let content = state
+        .post_service
+        .read_formatted(&post, read.format)
+        .await?;
+
+    event!(Level::INFO, post_id = post.id, slug = %slug, "成功根据slug获取文章内容");
+    Ok(SuccessResponse::new(Post::with_content(
+        post,
+        content,
+        read.format,
+    )))
+}
+
+/// 以流的形式返回文章的markdown源文件, 不经过json包装
+pub async fn read_post_raw(
+    State(state): State<AppState>,
+    principal: Option<Principal>,
+    Path(id): Path<i32>,
+) -> Result<Response, ServiceError> {
+    event!(Level::INFO, post_id = id, "开始获取文章源文件");
+
+    if id <= 0 {
+        event!(Level::WARN, post_id = id, "无效的文章ID");
+        return Err(ServiceError::BadArugment("无效的id".to_string()));
+    }
+    let post = state.post_service.read_one(principal.as_ref(), id).await?;
+    let stream = state.post_service.read_raw(&post).await?;
+
+    event!(Level::INFO, post_id = id, title = %post.title, "开始发送文章源文件");
+    Ok((
+        [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+        Body::from_stream(stream),
+    )
+        .into_response())
+}
+
+/// 根据导入前的旧地址获取文章元数据, 用于把旧链接重定向到新地址
+pub async fn read_post_by_alias(
+    State(state): State<AppState>,
+    principal: Option<Principal>,
+    Query(query): Query<PostAliasQuery>,
+) -> Result<SuccessResponse<PostMetaRead>, ServiceError> {
+    event!(Level::INFO, path = %query.path, "开始根据旧地址获取文章元数据");
+
+    if query.path.trim().is_empty() || query.path.len() > 1024 {
+        event!(Level::WARN, "无效的旧地址");
+        return Err(ServiceError::BadArugment("无效的地址".to_string()));
+    }
+    let post = state
+        .post_service
+        .read_by_alias(principal.as_ref(), &query.path)
+        .await?;
+
+    event!(Level::INFO, post_id = post.id, path = %query.path, "成功根据旧地址获取文章元数据");
+    Ok(SuccessResponse::new(post.into()))
 }
 
 pub async fn read_post_meta(
     State(state): State<AppState>,
+    principal: Option<Principal>,
     Path(id): Path<i32>,
 ) -> Result<SuccessResponse<PostMetaRead>, ServiceError> {
     event!(Level::INFO, post_id = id, "开始获取文章元数据");
@@ -67,19 +222,27 @@ pub async fn read_post_meta(
         event!(Level::WARN, post_id = id, "无效的文章ID");
         return Err(ServiceError::BadArugment("无效的id".to_string()));
     }
-    let post = state.post_service.read_one(id).await?;
+    let post = state.post_service.read_one(principal.as_ref(), id).await?;
 
     event!(Level::INFO, post_id = id, title = %post.title, "成功获取文章元数据");
     Ok(SuccessResponse::new(post.into()))
 }
 
+/// 上传新文章, 文件放在`content`字段中; 也可以在`bundle`字段中上传包含markdown文件和
+/// 所引用图片的压缩包, 图片会作为资源保存, 正文中的相对地址被改写为资源地址
 pub async fn add_post(
     State(state): State<AppState>,
+    principal: Principal,
     multipart: Multipart,
-) -> Result<SuccessResponse<PostMetaRead>, ServiceError> {
-    event!(Level::INFO, "开始处理新文章上传");
+) -> Result<SuccessResponse<PostUploadRead>, ServiceError> {
+    event!(Level::INFO, username = %principal.username, "开始处理新文章上传");
 
-    let new = match process_multipart(multipart).await {
+    let mut form = process_multipart(multipart).await.map_err(|e| {
+        event!(Level::ERROR, error = %e, "处理multipart数据失败");
+        ServiceError::BadArugment(e)
+    })?;
+    let warnings = unpack_bundle(&state, &principal, &mut form).await?;
+    let new = match form.into_create() {
         Ok(new) => Ok(new),
         Err(e) => {
             event!(Level::ERROR, error = %e, "处理multipart数据失败");
@@ -87,62 +250,529 @@ pub async fn add_post(
         }
     }?;
 
-    if new.title.len() > 255 || new.title.is_empty() {
-        event!(Level::WARN, title_length = new.title.len(), "标题长度无效");
+    validate_post(Some(&new.title), Some(&new.tags), Some(&new.content))?;
+    validate_revision_note(new.author.as_deref(), new.message.as_deref())?;
+
+    tracing::Span::current().record("title", &new.title);
+    event!(Level::INFO, title = %new.title, tags_count = new.tags.len(), content_size = new.content.len(), "开始创建新文章");
+
+    let post = state.post_service.add_one(&principal, new).await?;
+
+    event!(Level::INFO, post_id = post.id, title = %post.title, "成功创建新文章");
+    Ok(SuccessResponse::new(PostUploadRead {
+        post: post.into(),
+        warnings,
+    }))
+}
+
+/// 从zip, tar或tar.gz压缩包中批量导入文章, 压缩包放在表单的`archive`字段中,
+/// `format`字段指定来源(`markdown`, `hexo`, `hugo`, `jekyll`, `wordpress`, 默认为`markdown`),
+/// 返回每篇文章的导入结果
+pub async fn import_posts(
+    State(state): State<AppState>,
+    principal: Principal,
+    mut multipart: Multipart,
+) -> Result<SuccessResponse<ImportReport>, ServiceError> {
+    event!(Level::INFO, username = %principal.username, "开始处理批量导入");
+
+    let mut archive = None;
+    let mut format = ImportFormat::default();
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| ServiceError::BadArugment(e.to_string()))?
+    {
+        match field.name() {
+            Some("archive") => {
+                archive = Some(
+                    field
+                        .bytes()
+                        .await
+                        .map_err(|e| ServiceError::BadArugment(e.to_string()))?,
+                )
+            }
+            Some("format") => {
+                let text = field
+                    .text()
+                    .await
+                    .map_err(|e| ServiceError::BadArugment(e.to_string()))?;
+                format = text.parse().map_err(|e: String| {
+                    event!(Level::WARN, format = %text, "无效的导入格式");
+                    ServiceError::BadArugment(e)
+                })?;
+            }
+            _ => return Err(ServiceError::BadArugment("无效的字段".to_string())),
+        }
+    }
+    let Some(archive) = archive else {
+        event!(Level::WARN, "没有上传压缩包");
+        return Err(ServiceError::BadArugment("缺少压缩包".to_string()));
+    };
+    event!(Level::INFO, archive_size = archive.len(), format = ?format, "成功接收压缩包");
+
+    let files = import::read_archive(archive, format).await?;
+    let report = state.post_service.import(&principal, files, format).await?;
+
+    event!(
+        Level::INFO,
+        imported = report.imported,
+        duplicated = report.duplicated,
+        failed = report.failed,
+        "完成批量导入"
+    );
+    Ok(SuccessResponse::new(report))
+}
+
+/// 更新文章, 表单中没有提供的字段保持不变, 同样可以使用`bundle`字段上传压缩包
+pub async fn update_post(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+    multipart: Multipart,
+) -> Result<SuccessResponse<PostUploadRead>, ServiceError> {
+    event!(Level::INFO, username = %principal.username, post_id = id, "开始处理文章更新");
+
+    if id <= 0 {
+        event!(Level::WARN, post_id = id, "无效的文章ID");
+        return Err(ServiceError::BadArugment("无效的id".to_string()));
+    }
+
+    let mut form = process_multipart(multipart).await.map_err(|e| {
+        event!(Level::ERROR, error = %e, "处理multipart数据失败");
+        ServiceError::BadArugment(e)
+    })?;
+    let warnings = unpack_bundle(&state, &principal, &mut form).await?;
+    let update = match form.into_update() {
+        Ok(update) => Ok(update),
+        Err(e) => {
+            event!(Level::ERROR, error = %e, "处理multipart数据失败");
+            Err(ServiceError::BadArugment(e))
+        }
+    }?;
+
+    validate_post(
+        update.title.as_deref(),
+        update.tags.as_deref(),
+        update.content.as_deref(),
+    )?;
+    validate_revision_note(update.author.as_deref(), update.message.as_deref())?;
+
+    let post = state
+        .post_service
+        .update_one(&principal, id, update)
+        .await?;
+
+    event!(Level::INFO, post_id = post.id, title = %post.title, "成功更新文章");
+    Ok(SuccessResponse::new(PostUploadRead {
+        post: post.into(),
+        warnings,
+    }))
+}
+
+/// 删除文章, 默认移入回收站, `?permanent=true`时同时删除文件和评论
+pub async fn delete_post(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+    Query(delete): Query<PostDelete>,
+) -> Result<SuccessResponse<PostMetaRead>, ServiceError> {
+    event!(
+        Level::INFO,
+        username = %principal.username,
+        post_id = id,
+        permanent = delete.permanent,
+        "开始删除文章"
+    );
+
+    if id <= 0 {
+        event!(Level::WARN, post_id = id, "无效的文章ID");
+        return Err(ServiceError::BadArugment("无效的id".to_string()));
+    }
+    let post = state
+        .post_service
+        .delete_one(&principal, id, delete.permanent)
+        .await?;
+
+    event!(Level::INFO, post_id = id, title = %post.title, "成功删除文章");
+    Ok(SuccessResponse::new(post.into()))
+}
+
+/// 从回收站恢复文章
+pub async fn restore_post(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+) -> Result<SuccessResponse<PostMetaRead>, ServiceError> {
+    event!(Level::INFO, username = %principal.username, post_id = id, "开始恢复文章");
+
+    if id <= 0 {
+        event!(Level::WARN, post_id = id, "无效的文章ID");
+        return Err(ServiceError::BadArugment("无效的id".to_string()));
+    }
+    let post = state.post_service.restore_one(&principal, id).await?;
+
+    event!(Level::INFO, post_id = id, title = %post.title, "成功恢复文章");
+    Ok(SuccessResponse::new(post.into()))
+}
+
+/// 列出回收站中的文章
+pub async fn list_trash(
+    State(state): State<AppState>,
+    principal: Principal,
+) -> Result<SuccessResponse<Vec<PostMetaRead>>, ServiceError> {
+    event!(Level::INFO, username = %principal.username, "开始获取回收站");
+
+    let posts = state.post_service.list_trash(&principal).await?;
+
+    event!(Level::INFO, post_count = posts.len(), "成功获取回收站");
+    Ok(SuccessResponse::new(
+        posts.into_iter().map(|p| p.into()).collect(),
+    ))
+}
+
+/// 列出文章的所有历史版本, 不包含文件内容; 未公开的文章只有作者, 编辑和管理员可以查看
+pub async fn list_revisions(
+    State(state): State<AppState>,
+    principal: Option<Principal>,
+    Path(id): Path<i32>,
+) -> Result<SuccessResponse<Vec<RevisionRead>>, ServiceError> {
+    event!(Level::INFO, post_id = id, "开始获取文章版本列表");
+
+    if id <= 0 {
+        event!(Level::WARN, post_id = id, "无效的文章ID");
+        return Err(ServiceError::BadArugment("无效的id".to_string()));
+    }
+    let revisions = state
+        .post_service
+        .list_revisions(principal.as_ref(), id)
+        .await?;
+
+    event!(
+        Level::INFO,
+        post_id = id,
+        revision_count = revisions.len(),
+        "成功获取文章版本列表"
+    );
+    Ok(SuccessResponse::new(
+        revisions.into_iter().map(|r| r.into()).collect(),
+    ))
+}
+
+/// 获取文章某个历史版本的内容
+pub async fn read_revision(
+    State(state): State<AppState>,
+    principal: Option<Principal>,
+    Path((id, revision)): Path<(i32, i32)>,
+) -> Result<SuccessResponse<RevisionContentRead>, ServiceError> {
+    event!(
+        Level::INFO,
+        post_id = id,
+        revision = revision,
+        "开始获取文章版本"
+    );
+
+    if id <= 0 || revision <= 0 {
+        event!(
+            Level::WARN,
+            post_id = id,
+            revision = revision,
+            "无效的文章ID或版本号"
+        );
+        return Err(ServiceError::BadArugment("无效的id或版本号".to_string()));
+    }
+    let revision = state
+        .post_service
+        .read_revision(principal.as_ref(), id, revision)
+        .await?;
+
+    event!(
+        Level::INFO,
+        post_id = id,
+        revision = revision.meta.revision,
+        "成功获取文章版本"
+    );
+    Ok(SuccessResponse::new(revision.into()))
+}
+
+/// 比较文章的两个历史版本
+///
+/// # Arguments
+///
+/// - `Query(query)` (`RevisionDiffQuery`) - 旧版本号`from`和新版本号`to`.
+///
+pub async fn diff_revisions(
+    State(state): State<AppState>,
+    principal: Option<Principal>,
+    Path(id): Path<i32>,
+    Query(query): Query<RevisionDiffQuery>,
+) -> Result<SuccessResponse<RevisionDiff>, ServiceError> {
+    event!(
+        Level::INFO,
+        post_id = id,
+        from = query.from,
+        to = query.to,
+        "开始比较文章版本"
+    );
+
+    if id <= 0 || query.from <= 0 || query.to <= 0 {
+        event!(
+            Level::WARN,
+            post_id = id,
+            from = query.from,
+            to = query.to,
+            "无效的文章ID或版本号"
+        );
+        return Err(ServiceError::BadArugment("无效的id或版本号".to_string()));
+    }
+    let diff = state
+        .post_service
+        .diff_revisions(principal.as_ref(), id, query.from, query.to)
+        .await?;
+
+    event!(
+        Level::INFO,
+        post_id = id,
+        diff_size = diff.diff.len(),
+        "成功比较文章版本"
+    );
+    Ok(SuccessResponse::new(diff))
+}
+
+/// 将文章回滚到某个历史版本
+pub async fn rollback_post(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path((id, revision)): Path<(i32, i32)>,
+    Query(rollback): Query<PostRollback>,
+) -> Result<SuccessResponse<PostMetaRead>, ServiceError> {
+    event!(
+        Level::INFO,
+        username = %principal.username,
+        post_id = id,
+        revision = revision,
+        "开始回滚文章"
+    );
+
+    if id <= 0 || revision <= 0 {
+        event!(
+            Level::WARN,
+            post_id = id,
+            revision = revision,
+            "无效的文章ID或版本号"
+        );
+        return Err(ServiceError::BadArugment("无效的id或版本号".to_string()));
+    }
+    validate_revision_note(rollback.author.as_deref(), rollback.message.as_deref())?;
+    let post = state
+        .post_service
+        .rollback(&principal, id, revision, rollback.author, rollback.message)
+        .await?;
+
+    event!(
+        Level::INFO,
+        post_id = id,
+        revision = revision,
+        "成功回滚文章"
+    );
+    Ok(SuccessResponse::new(post.into()))
+}
+
+/// 校验历史版本的作者和修改说明
+fn validate_revision_note(author: Option<&str>, message: Option<&str>) -> Result<(), ServiceError> {
+    if author.is_some_and(|author| author.len() > 255)
+        || message.is_some_and(|message| message.len() > 1024)
+    {
+        event!(Level::WARN, author = ?author, "作者或修改说明过长");
+        return Err(ServiceError::BadArugment(
+            "作者长度不能超过255, 修改说明长度不能超过1024".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// 校验文章字段, 为空的字段不做检查
+fn validate_post(
+    title: Option<&str>,
+    tags: Option<&[String]>,
+    content: Option<&[u8]>,
+) -> Result<(), ServiceError> {
+    if let Some(title) = title
+        && (title.len() > 255 || title.is_empty())
+    {
+        event!(Level::WARN, title_length = title.len(), "标题长度无效");
         return Err(ServiceError::BadArugment(
             "标题长度不能超过255或为空".to_string(),
         ));
     }
-    if new.tags.len() > 10 {
-        event!(Level::WARN, tags_count = new.tags.len(), "标签数量过多");
+    if let Some(tags) = tags
+        && tags.len() > 10
+    {
+        event!(Level::WARN, tags_count = tags.len(), "标签数量过多");
         return Err(ServiceError::BadArugment("标签长度不能超过10".to_string()));
     }
-    if new.content.len() > 1024 * 1024 * 10 {
+    if let Some(content) = content
+        && content.len() > 1024 * 1024 * 10
+    {
         event!(
             Level::WARN,
-            content_size = new.content.len(),
+            content_size = content.len(),
             "内容大小超过限制"
         );
         return Err(ServiceError::BadArugment("内容长度不能超过10M".to_string()));
     }
+    Ok(())
+}
 
-    tracing::Span::current().record("title", &new.title);
-    event!(Level::INFO, title = %new.title, tags_count = new.tags.len(), content_size = new.content.len(), "开始创建新文章");
+/// multipart表单中的原始字段
+#[derive(Default)]
+struct PostForm {
+    title: Option<String>,
+    slug: Option<String>,
+    tags: Option<Vec<String>>,
+    content: Option<Vec<u8>>,
+    status: Option<PostStatus>,
+    publish_at: Option<DateTime<Utc>>,
+    author: Option<String>,
+    message: Option<String>,
+    /// 包含markdown文件和所引用文件的压缩包
+    bundle: Option<Bytes>,
+}
 
-    let post = state.post_service.add_one(new).await?;
+impl PostForm {
+    /// 表单中的字段优先于文件front matter中的同名字段
+    fn into_create(self) -> Result<PostCreate, String> {
+        let content = self.content.unwrap_or_default();
+        let front_matter = MARKDOWN_UTIL
+            .parse(&content)
+            .map_err(|e| e.to_string())?
+            .front_matter;
+        event!(Level::DEBUG, front_matter = ?front_matter, "成功解析front matter");
+        let status = front_matter.status.as_deref().map(str::parse).transpose()?;
 
-    event!(Level::INFO, post_id = post.id, title = %post.title, "成功创建新文章");
-    Ok(SuccessResponse::new(post.into()))
+        Ok(PostCreate {
+            title: self.title.or(front_matter.title).unwrap_or_default(),
+            slug: self.slug.or(front_matter.slug),
+            tags: self.tags.or(front_matter.tags).unwrap_or_default(),
+            content,
+            date: front_matter.date,
+            updated: front_matter.updated,
+            summary: front_matter.summary,
+            status: self.status.or(status),
+            publish_at: self.publish_at.or(front_matter.publish_at),
+            author: self.author,
+            message: self.message,
+        })
+    }
+
+    /// 同[`PostForm::into_create`], 但是表单和front matter中都没有的字段保持为空
+    fn into_update(self) -> Result<PostUpdate, String> {
+        let front_matter = match &self.content {
+            Some(content) => {
+                MARKDOWN_UTIL
+                    .parse(content)
+                    .map_err(|e| e.to_string())?
+                    .front_matter
+            }
+            None => Default::default(),
+        };
+        let status = front_matter.status.as_deref().map(str::parse).transpose()?;
+
+        Ok(PostUpdate {
+            title: self.title.or(front_matter.title),
+            slug: self.slug.or(front_matter.slug),
+            tags: self.tags.or(front_matter.tags),
+            content: self.content,
+            status: self.status.or(status),
+            publish_at: self.publish_at.or(front_matter.publish_at),
+            author: self.author,
+            message: self.message,
+        })
+    }
+}
+
+/// 表单中有压缩包时上传其中引用的文件, 并以改写后的markdown作为文章内容, 返回警告
+async fn unpack_bundle(
+    state: &AppState,
+    principal: &Principal,
+    form: &mut PostForm,
+) -> Result<Vec<String>, ServiceError> {
+    let Some(bundle) = form.bundle.take() else {
+        return Ok(Vec::new());
+    };
+    if form.content.is_some() {
+        event!(Level::WARN, "同时上传了content和bundle");
+        return Err(ServiceError::BadArugment(
+            "content和bundle只能上传一个".to_string(),
+        ));
+    }
+    event!(
+        Level::INFO,
+        bundle_size = bundle.len(),
+        "开始处理上传的压缩包"
+    );
+
+    let bundle = import::read_bundle(bundle).await?;
+    let (content, warnings) = state.asset_service.upload_bundle(principal, bundle).await?;
+    form.content = Some(content);
+
+    event!(
+        Level::INFO,
+        warning_count = warnings.len(),
+        "成功处理上传的压缩包"
+    );
+    Ok(warnings)
 }
+
 #[inline]
-async fn process_multipart(mut multipart: Multipart) -> Result<PostCreate, String> {
-    let mut post = PostCreate::default();
+async fn process_multipart(mut multipart: Multipart) -> Result<PostForm, String> {
+    let mut form = PostForm::default();
     while let Some(field) = multipart.next_field().await.map_err(|e| e.to_string())? {
         match field.name() {
             None => continue,
             Some("title") => {
-                post.title = field
+                let title = field
                     .text()
                     .await
                     .map_err(|e| e.to_string())?
                     .trim()
                     .to_string();
+                form.title = Some(title).filter(|title| !title.is_empty());
+            }
+            Some("slug") => {
+                let slug = field.text().await.map_err(|e| e.to_string())?;
+                form.slug = Some(slugify(&slug)).filter(|_| !slug.trim().is_empty());
             }
             Some("tags") => {
-                post.tags = field
-                    .text()
-                    .await
-                    .map_err(|e| e.to_string())?
-                    .split(',')
-                    .map(|s| s.trim().to_string())
-                    .collect();
-                post.tags.dedup();
+                let tags = field.text().await.map_err(|e| e.to_string())?;
+                form.tags = Some(clean_tags(tags.split(',').map(str::to_string)));
+            }
+            Some("status") => {
+                let status = field.text().await.map_err(|e| e.to_string())?;
+                form.status = Some(status.parse()?);
+            }
+            Some("publish_at") => {
+                let publish_at = field.text().await.map_err(|e| e.to_string())?;
+                form.publish_at = Some(
+                    MARKDOWN_UTIL
+                        .parse_date(&publish_at)
+                        .map_err(|e| e.to_string())?,
+                );
+            }
+            Some("author") => {
+                let author = field.text().await.map_err(|e| e.to_string())?;
+                form.author = Some(author.trim().to_string()).filter(|author| !author.is_empty());
+            }
+            Some("message") => {
+                let message = field.text().await.map_err(|e| e.to_string())?;
+                form.message =
+                    Some(message.trim().to_string()).filter(|message| !message.is_empty());
             }
             Some("content") => {
-                post.content = field.bytes().await.map_err(|e| e.to_string())?.into()
+                form.content = Some(field.bytes().await.map_err(|e| e.to_string())?.into())
             }
+            Some("bundle") => form.bundle = Some(field.bytes().await.map_err(|e| e.to_string())?),
             Some(_) => return Err("无效的字段".to_string()),
         }
     }
-    Ok(post)
+    Ok(form)
 }
@@ -0,0 +1,98 @@
+use crate::models::SuccessResponse;
+use crate::models::user::{UserCreate, UserRead, UserUpdate};
+use crate::service::{Principal, ServiceError};
+use crate::state::AppState;
+use axum::extract::{Path, State};
+use axum::{
+    Json, Router,
+    routing::{get, put},
+};
+use tracing::{Level, event};
+
+pub async fn new() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_users).post(create_user))
+        .route("/me", get(read_me))
+        .route("/{id}", put(update_user).delete(delete_user))
+}
+
+/// 当前登录的用户
+pub async fn read_me(
+    State(state): State<AppState>,
+    principal: Principal,
+) -> Result<SuccessResponse<UserRead>, ServiceError> {
+    let Some(id) = principal.id else {
+        return Err(ServiceError::NotFound);
+    };
+    let user = state.user_service.read_one(id).await?;
+    Ok(SuccessResponse::new(user.into()))
+}
+
+pub async fn list_users(
+    State(state): State<AppState>,
+    principal: Principal,
+) -> Result<SuccessResponse<Vec<UserRead>>, ServiceError> {
+    event!(Level::INFO, username = %principal.username, "开始获取用户列表");
+
+    let users = state.user_service.list(&principal).await?;
+
+    event!(Level::INFO, user_count = users.len(), "成功获取用户列表");
+    Ok(SuccessResponse::new(
+        users.into_iter().map(UserRead::from).collect(),
+    ))
+}
+
+pub async fn create_user(
+    State(state): State<AppState>,
+    principal: Principal,
+    Json(user): Json<UserCreate>,
+) -> Result<SuccessResponse<UserRead>, ServiceError> {
+    event!(Level::INFO, username = %principal.username, "开始创建用户");
+
+    let user = state.user_service.create(&principal, user).await?;
+
+    event!(Level::INFO, user_id = user.id, "成功创建用户");
+    Ok(SuccessResponse::new(user.into()))
+}
+
+/// 修改用户的密码或角色, 修改后用户的所有会话被撤销, 需要重新登录
+pub async fn update_user(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+    Json(update): Json<UserUpdate>,
+) -> Result<SuccessResponse<UserRead>, ServiceError> {
+    event!(Level::INFO, username = %principal.username, user_id = id, "开始更新用户");
+
+    if id <= 0 {
+        event!(Level::WARN, user_id = id, "无效的用户ID");
+        return Err(ServiceError::BadArugment("无效的id".to_string()));
+    }
+    let revoke = update.password.is_some() || update.role.is_some();
+    let user = state.user_service.update(&principal, id, update).await?;
+    if revoke {
+        state.auth_service.revoke_user(id).await?;
+    }
+
+    event!(Level::INFO, user_id = id, "成功更新用户");
+    Ok(SuccessResponse::new(user.into()))
+}
+
+/// 删除用户, 其文章和评论保留, 已经签发的令牌立即失效
+pub async fn delete_user(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+) -> Result<SuccessResponse<UserRead>, ServiceError> {
+    event!(Level::INFO, username = %principal.username, user_id = id, "开始删除用户");
+
+    if id <= 0 {
+        event!(Level::WARN, user_id = id, "无效的用户ID");
+        return Err(ServiceError::BadArugment("无效的id".to_string()));
+    }
+    let user = state.user_service.delete(&principal, id).await?;
+    state.auth_service.deny_user(id);
+
+    event!(Level::INFO, user_id = id, "成功删除用户");
+    Ok(SuccessResponse::new(user.into()))
+}
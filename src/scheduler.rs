@@ -0,0 +1,35 @@
+use crate::state::AppState;
+use chrono::Utc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{Level, event};
+
+/// 两次检查之间的最短间隔, 避免应用和数据库的时钟不一致时空转
+const MIN_WAIT: Duration = Duration::from_secs(1);
+
+/// 启动发布定时文章的后台任务
+///
+/// 每次发布完已到时间的文章后, 休眠到下一篇定时文章的发布时间,
+/// 最长不超过`interval`, 以便发现新增或修改的定时文章
+pub fn spawn(state: AppState, interval: Duration) -> JoinHandle<()> {
+    event!(Level::INFO, interval = ?interval, "启动定时发布任务");
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = state.post_service.publish_scheduled().await {
+                event!(Level::ERROR, error = %e, "发布定时文章失败");
+            }
+            let wait = match state.post_service.next_scheduled().await {
+                Ok(Some(next)) => (next - Utc::now())
+                    .to_std()
+                    .unwrap_or(Duration::ZERO)
+                    .clamp(MIN_WAIT, interval.max(MIN_WAIT)),
+                Ok(None) => interval,
+                Err(e) => {
+                    event!(Level::ERROR, error = %e, "查询下一篇定时文章失败");
+                    interval
+                }
+            };
+            tokio::time::sleep(wait).await;
+        }
+    })
+}
@@ -1,12 +1,14 @@
+use crate::config::{AppConfig, ConfigError};
 use crate::router;
-use crate::{config::AppConfig, state::AppState};
+use crate::scheduler;
+use crate::state::AppState;
 use axum::Router;
+use std::net::SocketAddr;
 use tower_http::cors::{Any, CorsLayer};
 use tracing_subscriber::prelude::*;
 
-pub async fn serve() {
-    let config = AppConfig::new();
-    let addr = config.get_listener_addr();
+/// 初始化日志记录
+pub fn init_tracing(config: &AppConfig) {
     let log_level = config.get_log_level();
 
     // TODO: 将layer设置分离到到单独的配置文件中
@@ -23,6 +25,14 @@ pub async fn serve() {
         )
         .init();
     tracing::info!("应用日志记录等级为: {}", log_level);
+}
+
+/// 启动http服务, 只有http服务签发令牌, 所以只在这里检查密钥
+pub async fn serve() -> Result<(), ConfigError> {
+    let config = AppConfig::new();
+    config.check_secret()?;
+    let addr = config.get_listener_addr();
+    init_tracing(&config);
 
     let listener = match tokio::net::TcpListener::bind(&addr).await {
         Ok(item) => item,
@@ -35,12 +45,21 @@ pub async fn serve() {
         .allow_headers(Any)
         .allow_methods(Any);
 
+    let publish_interval = config.get_publish_interval();
     let state = AppState::new(config).await;
+    scheduler::spawn(state.clone(), publish_interval);
     let router = Router::new()
         .merge(router::new().await)
         .layer(cors)
         .layer(tower_http::trace::TraceLayer::new_for_http())
         .with_state(state);
 
-    axum::serve(listener, router).await.unwrap();
+    // 记录客户端地址, 用于在会话列表中展示登录设备
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
+    Ok(())
 }
@@ -1,12 +1,26 @@
+mod asset;
+mod auth;
 mod comment;
 mod post;
+mod principal;
+mod user;
+use crate::backup::BackupError;
+use crate::embedding::EmbeddingError;
+use crate::imaging::ImageError;
+use crate::import::ImportError;
 use crate::models::ErrorResponse;
 use crate::repositories::ReponsitoryError;
+use crate::storage::StorageError;
+use crate::util::MarkdownError;
 use axum::Json;
-use axum::http::StatusCode;
+use axum::http::{StatusCode, header};
 use axum::response::IntoResponse;
+pub use asset::{AssetService, MAX_ASSET_SIZE};
+pub use auth::AuthService;
 pub use comment::CommentService;
 pub use post::PostService;
+pub use principal::Principal;
+pub use user::UserService;
 use std::io;
 use thiserror::Error;
 #[derive(Debug, Error)]
@@ -15,6 +29,10 @@ pub enum ServiceError {
     NotFound,
     #[error("bad argument: {0}")]
     BadArugment(String),
+    #[error("unauthorized: {0}")]
+    Unauthorized(String),
+    #[error("forbidden: {0}")]
+    Forbidden(String),
     #[error("repository error: {0}")]
     InternalError(String),
     #[error("can't save file")]
@@ -28,6 +46,49 @@ impl From<ReponsitoryError> for ServiceError {
         }
     }
 }
+impl From<EmbeddingError> for ServiceError {
+    fn from(value: EmbeddingError) -> Self {
+        Self::InternalError(value.to_string())
+    }
+}
+impl From<StorageError> for ServiceError {
+    fn from(value: StorageError) -> Self {
+        match value {
+            StorageError::Io(e) => Self::FileError(e),
+            StorageError::NotFound(_) => Self::NotFound,
+            _ => Self::InternalError(value.to_string()),
+        }
+    }
+}
+impl From<ImportError> for ServiceError {
+    fn from(value: ImportError) -> Self {
+        match value {
+            ImportError::Io(e) => Self::FileError(e),
+            _ => Self::BadArugment(value.to_string()),
+        }
+    }
+}
+impl From<BackupError> for ServiceError {
+    fn from(value: BackupError) -> Self {
+        match value {
+            BackupError::Io(e) => Self::FileError(e),
+            _ => Self::BadArugment(value.to_string()),
+        }
+    }
+}
+impl From<ImageError> for ServiceError {
+    fn from(value: ImageError) -> Self {
+        match value {
+            ImageError::Decode(_) => Self::BadArugment(value.to_string()),
+            ImageError::Encode(_) => Self::InternalError(value.to_string()),
+        }
+    }
+}
+impl From<MarkdownError> for ServiceError {
+    fn from(value: MarkdownError) -> Self {
+        Self::BadArugment(value.to_string())
+    }
+}
 
 impl IntoResponse for ServiceError {
     fn into_response(self) -> axum::response::Response {
@@ -37,6 +98,17 @@ impl IntoResponse for ServiceError {
                 Json(ErrorResponse::new(StatusCode::BAD_REQUEST, message)),
             )
                 .into_response(),
+            ServiceError::Unauthorized(message) => (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Bearer")],
+                Json(ErrorResponse::new(StatusCode::UNAUTHORIZED, message)),
+            )
+                .into_response(),
+            ServiceError::Forbidden(message) => (
+                StatusCode::FORBIDDEN,
+                Json(ErrorResponse::new(StatusCode::FORBIDDEN, message)),
+            )
+                .into_response(),
             ServiceError::InternalError(message) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ErrorResponse::new(
@@ -0,0 +1,535 @@
+use crate::backup::BackupAsset;
+use crate::config::AssetConfig;
+use crate::imaging::{self, OutputFormat, Variant};
+use crate::import::Bundle;
+use crate::models::asset::AssetBackfill;
+use crate::repositories::ReponsitoryError;
+use crate::repositories::asset::{Asset, AssetCreate, AssetReponsitory, SqlxReponsitory};
+use crate::repositories::user::Role;
+use crate::service::{Principal, ServiceError};
+use crate::storage::{ByteStream, ContentStore, StorageError};
+use crate::util::MARKDOWN_UTIL;
+use bytes::Bytes;
+use chrono::{Duration, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task;
+use tracing::{Level, event, instrument};
+
+/// 单个资源的最大大小
+pub const MAX_ASSET_SIZE: usize = 20 * 1024 * 1024;
+/// 同时进行的图片缩放和转换的数量, avif编码很耗费CPU
+const MAX_CONCURRENT_ENCODES: usize = 2;
+
+/// 允许上传的文件类型, 根据文件头判断, 不信任客户端提供的类型和扩展名;
+/// svg和html等可以包含脚本的类型不允许上传
+const ALLOWED_TYPES: &[&str] = &[
+    "image/jpeg",
+    "image/png",
+    "image/gif",
+    "image/webp",
+    "image/avif",
+    "image/bmp",
+    "application/pdf",
+    "application/zip",
+    "application/gzip",
+    "audio/mpeg",
+    "audio/ogg",
+    "audio/x-flac",
+    "audio/x-wav",
+    "video/mp4",
+    "video/webm",
+];
+
+pub struct AssetService {
+    asset: Box<dyn AssetReponsitory>,
+    store: Arc<dyn ContentStore>,
+    /// 没有被引用的资源在上传多久之后可以被清理
+    orphan_grace: Duration,
+    /// 可以请求的图片宽度, 从小到大排列
+    image_widths: Vec<u32>,
+    image_quality: u8,
+    encoding: Semaphore,
+}
+
+impl AssetService {
+    pub fn new(pool: PgPool, store: Arc<dyn ContentStore>, config: &AssetConfig) -> Self {
+        let mut image_widths: Vec<u32> = config
+            .image_widths
+            .iter()
+            .copied()
+            .filter(|width| *width > 0)
+            .collect();
+        image_widths.sort_unstable();
+        image_widths.dedup();
+        tracing::info!("创建AssetService实例成功");
+        AssetService {
+            asset: Box::new(SqlxReponsitory::new(pool)),
+            store,
+            orphan_grace: Duration::hours(config.orphan_grace_hours),
+            image_widths,
+            image_quality: config.image_quality.clamp(1, 100),
+            encoding: Semaphore::new(MAX_CONCURRENT_ENCODES),
+        }
+    }
+
+    pub fn image_widths(&self) -> &[u32] {
+        &self.image_widths
+    }
+
+    /// 资源文件的键, 以哈希的前两位分目录
+    fn key(hash: &str) -> String {
+        format!("{}/{}", &hash[..2], hash)
+    }
+
+    /// 衍生图片的键, 保存在原图旁边, 以原图的键加`.`开头
+    fn variant_key(hash: &str, variant: &Variant) -> String {
+        format!("{}.{}", Self::key(hash), variant.name())
+    }
+
+    /// 根据文件头判断文件类型, 不允许的类型返回空
+    pub fn sniff(data: &[u8]) -> Option<&'static str> {
+        infer::get(data)
+            .map(|kind| kind.mime_type())
+            .filter(|mime| ALLOWED_TYPES.contains(mime))
+    }
+
+    /// 保存上传的文件, 内容相同的文件只保存一份并返回已有的资源;
+    /// 可以缩放的图片先去除EXIF等元数据, 以去除后的内容判断是否重复;
+    /// 指定了`post_id`时同时关联到文章, 避免在文章引用之前被清理; 需要作者权限
+    #[instrument(name = "AssetService::upload", level = "info", skip(self, principal, data), fields(size = data.len(), username = %principal.username))]
+    pub async fn upload(
+        &self,
+        principal: &Principal,
+        filename: String,
+        data: Bytes,
+        post_id: Option<i32>,
+    ) -> Result<Asset, ServiceError> {
+        principal.require(Role::Author)?;
+        event!(Level::INFO, filename = %filename, size = data.len(), "开始保存资源");
+
+        if data.is_empty() {
+            return Err(ServiceError::BadArugment("文件不能为空".to_string()));
+        }
+        if data.len() > MAX_ASSET_SIZE {
+            return Err(ServiceError::BadArugment("文件大小不能超过20M".to_string()));
+        }
+        let Some(mime) = Self::sniff(&data) else {
+            event!(Level::WARN, filename = %filename, "不支持的文件类型");
+            return Err(ServiceError::BadArugment("不支持的文件类型".to_string()));
+        };
+
+        let (data, width, height) = if OutputFormat::from_mime(mime).is_some() {
+            let prepared = task::spawn_blocking(move || imaging::prepare(mime, &data))
+                .await
+                .map_err(|e| ServiceError::InternalError(e.to_string()))??;
+            event!(
+                Level::DEBUG,
+                width = prepared.width,
+                height = prepared.height,
+                "完成去除图片元数据"
+            );
+            (
+                Bytes::from(prepared.data),
+                Some(prepared.width as i32),
+                Some(prepared.height as i32),
+            )
+        } else {
+            (data, None, None)
+        };
+
+        let hash = format!("{:x}", Sha256::digest(&data));
+        let size = data.len() as i64;
+        // 相同内容的文件直接覆盖, 保证记录存在时文件也存在;
+        // 写入记录之前不释放锁, 避免同时进行的清理删除刚写入的文件
+        let mut lock = self.asset.lock_hash(&hash).await?;
+        self.store.put(&Self::key(&hash), data).await?;
+        let asset = self
+            .asset
+            .add(
+                &mut lock,
+                AssetCreate {
+                    id: None,
+                    hash,
+                    mime: mime.to_string(),
+                    size,
+                    filename,
+                    created_at: None,
+                    width,
+                    height,
+                },
+            )
+            .await?;
+        self.asset.release(lock).await?;
+        if let Some(post_id) = post_id {
+            self.asset.link(post_id, &[asset.id]).await?;
+        }
+
+        event!(Level::INFO, asset_id = asset.id, hash = %asset.hash, mime = %asset.mime, "成功保存资源");
+        Ok(asset)
+    }
+
+    #[instrument(name = "AssetService::read", level = "info", skip(self))]
+    pub async fn read(&self, id: i32) -> Result<Asset, ServiceError> {
+        Ok(self.asset.find_by_id(id).await?)
+    }
+
+    /// 流式读取资源文件
+    pub async fn read_stream(&self, asset: &Asset) -> Result<ByteStream, ServiceError> {
+        Ok(self.store.get_stream(&Self::key(&asset.hash)).await?)
+    }
+
+    /// 删除上传超过保护期且没有被任何文章引用的资源, 返回被删除的资源;
+    /// `dry_run`为`true`时只列出将要删除的资源; 需要管理员权限
+    #[instrument(name = "AssetService::cleanup", level = "info", skip(self, principal), fields(username = %principal.username))]
+    pub async fn cleanup(
+        &self,
+        principal: &Principal,
+        dry_run: bool,
+    ) -> Result<Vec<Asset>, ServiceError> {
+        principal.require(Role::Admin)?;
+        event!(Level::INFO, dry_run = dry_run, "开始清理没有被引用的资源");
+
+        let unreferenced = self
+            .asset
+            .list_unreferenced(Utc::now() - self.orphan_grace)
+            .await?;
+        if dry_run {
+            return Ok(unreferenced);
+        }
+
+        let mut removed = Vec::with_capacity(unreferenced.len());
+        for asset in unreferenced {
+            // 删除文件之后才释放锁, 同时上传的相同内容的文件会在之后重新写入
+            let mut lock = self.asset.lock_hash(&asset.hash).await?;
+            let asset = match self.asset.delete_unreferenced(&mut lock, asset.id).await {
+                Ok(asset) => asset,
+                // 检查之后又被文章引用了
+                Err(ReponsitoryError::NotFound) => continue,
+                Err(e) => return Err(e.into()),
+            };
+            if let Err(e) = self.delete_files(&asset).await {
+                event!(Level::WARN, asset_id = asset.id, error = %e, "删除资源文件失败");
+            }
+            self.asset.release(lock).await?;
+            removed.push(asset);
+        }
+
+        event!(
+            Level::INFO,
+            removed_count = removed.len(),
+            "完成清理没有被引用的资源"
+        );
+        Ok(removed)
+    }
+
+    /// 为没有记录尺寸的旧图片补充尺寸并去除EXIF等元数据, 内容改变时哈希随之改变,
+    /// 资源的id和引用资源的地址不变; 需要管理员权限
+    #[instrument(name = "AssetService::backfill", level = "info", skip(self, principal), fields(username = %principal.username))]
+    pub async fn backfill(
+        &self,
+        principal: &Principal,
+    ) -> Result<Vec<AssetBackfill>, ServiceError> {
+        principal.require(Role::Admin)?;
+        event!(Level::INFO, "开始为旧图片补充尺寸");
+
+        let assets = self.asset.list_all().await?;
+        let mut hashes: HashMap<String, i32> = assets
+            .iter()
+            .map(|asset| (asset.hash.clone(), asset.id))
+            .collect();
+        let mut results = Vec::new();
+        for asset in assets {
+            if asset.width.is_some() || OutputFormat::from_mime(&asset.mime).is_none() {
+                continue;
+            }
+            let (id, filename) = (asset.id, asset.filename.clone());
+            let outcome = self.backfill_one(asset, &mut hashes).await;
+            if let Err(e) = &outcome {
+                event!(Level::WARN, asset_id = id, error = %e, "补充图片尺寸失败");
+            }
+            results.push(AssetBackfill {
+                id,
+                filename,
+                outcome: outcome.map_err(|e| e.to_string()),
+            });
+        }
+
+        event!(
+            Level::INFO,
+            asset_count = results.len(),
+            "完成为旧图片补充尺寸"
+        );
+        Ok(results)
+    }
+
+    async fn backfill_one(
+        &self,
+        asset: Asset,
+        hashes: &mut HashMap<String, i32>,
+    ) -> Result<Asset, ServiceError> {
+        let original = self.store.get(&Self::key(&asset.hash)).await?;
+        let mime = asset.mime.clone();
+        let prepared = task::spawn_blocking(move || imaging::prepare(&mime, &original))
+            .await
+            .map_err(|e| ServiceError::InternalError(e.to_string()))??;
+        let hash = format!("{:x}", Sha256::digest(&prepared.data));
+        let size = prepared.data.len() as i64;
+        let changed = hash != asset.hash;
+        // 同一张图片在升级之后又被上传过
+        if changed && let Some(other) = hashes.get(&hash) {
+            return Err(ServiceError::BadArugment(format!(
+                "去除元数据后与资源{}的内容相同",
+                other
+            )));
+        }
+        let mut lock = self.asset.lock_hash(&hash).await?;
+        if changed {
+            self.store
+                .put(&Self::key(&hash), Bytes::from(prepared.data))
+                .await?;
+        }
+        let updated = self
+            .asset
+            .update_content(
+                &mut lock,
+                asset.id,
+                &hash,
+                size,
+                Some(prepared.width as i32),
+                Some(prepared.height as i32),
+            )
+            .await?;
+        self.asset.release(lock).await?;
+        if changed {
+            hashes.remove(&asset.hash);
+            hashes.insert(hash, asset.id);
+            if let Err(e) = self.delete_files(&asset).await {
+                event!(Level::WARN, asset_id = asset.id, error = %e, "删除去除元数据之前的文件失败");
+            }
+        }
+
+        event!(Level::INFO, asset_id = updated.id, hash = %updated.hash, "成功补充图片尺寸");
+        Ok(updated)
+    }
+
+    /// 上传打包中被正文以相对地址引用的文件, 并将这些地址改写为资源的地址,
+    /// 返回改写后的markdown和找不到或无法上传的文件等警告
+    #[instrument(name = "AssetService::upload_bundle", level = "info", skip_all, fields(path = %bundle.path, username = %principal.username))]
+    pub async fn upload_bundle(
+        &self,
+        principal: &Principal,
+        mut bundle: Bundle,
+    ) -> Result<(Vec<u8>, Vec<String>), ServiceError> {
+        principal.require(Role::Author)?;
+        event!(
+            Level::INFO,
+            file_count = bundle.files.len(),
+            "开始处理打包中引用的文件"
+        );
+
+        let content = std::mem::take(&mut bundle.content);
+        let body = MARKDOWN_UTIL.parse(&content)?.body;
+        let front_matter = &content[..content.len() - body.len()];
+
+        // 先找出所有引用, 上传之后再改写
+        let mut references: Vec<(String, String, bool)> = Vec::new();
+        MARKDOWN_UTIL.rewrite_links(body, |url, image| {
+            if let Some(path) = bundle.local_path(url) {
+                references.push((url.to_string(), path, image));
+            }
+            None
+        });
+
+        let mut warnings = Vec::new();
+        let mut uploaded: HashMap<String, Option<String>> = HashMap::new();
+        for (url, path, image) in references.iter() {
+            if uploaded.contains_key(path) {
+                continue;
+            }
+            let Some(data) = bundle.files.get(path) else {
+                // 指向其他文章或页面的相对链接不需要提示
+                if *image || Self::is_attachment(path) {
+                    warnings.push(format!("引用的文件`{}`不在压缩包中", url));
+                }
+                uploaded.insert(path.clone(), None);
+                continue;
+            };
+            let filename = path.rsplit('/').next().unwrap_or(path).to_string();
+            let url = match self
+                .upload(principal, filename, Bytes::from(data.clone()), None)
+                .await
+            {
+                Ok(asset) => Some(format!("/asset/{}", asset.id)),
+                Err(ServiceError::BadArugment(e)) => {
+                    warnings.push(format!("文件`{}`无法作为资源上传: {}", path, e));
+                    None
+                }
+                Err(e) => return Err(e),
+            };
+            uploaded.insert(path.clone(), url);
+        }
+        let unused = bundle
+            .files
+            .keys()
+            .filter(|path| !uploaded.contains_key(*path))
+            .count();
+        if unused > 0 {
+            warnings.push(format!(
+                "压缩包中有{}个文件没有被正文引用, 没有上传",
+                unused
+            ));
+        }
+
+        let body = MARKDOWN_UTIL.rewrite_links(body, |url, _| {
+            let path = bundle.local_path(url)?;
+            uploaded.get(&path).cloned().flatten()
+        });
+        let mut rewritten = front_matter.to_vec();
+        rewritten.extend_from_slice(body.as_bytes());
+
+        event!(
+            Level::INFO,
+            asset_count = uploaded.values().filter(|url| url.is_some()).count(),
+            warning_count = warnings.len(),
+            "完成处理打包中引用的文件"
+        );
+        Ok((rewritten, warnings))
+    }
+
+    /// 链接指向的是否为附件, 而不是其他文章或页面
+    fn is_attachment(path: &str) -> bool {
+        let extension = path
+            .rsplit('/')
+            .next()
+            .and_then(|name| name.rsplit_once('.'))
+            .map(|(_, extension)| extension.to_lowercase());
+        extension.is_some_and(|extension| {
+            !matches!(extension.as_str(), "md" | "markdown" | "html" | "htm")
+        })
+    }
+
+    /// 删除原图和所有衍生图片
+    async fn delete_files(&self, asset: &Asset) -> Result<(), StorageError> {
+        let prefix = format!("{}.", Self::key(&asset.hash));
+        for key in self.store.list(&prefix).await? {
+            self.store.delete(&key).await?;
+        }
+        self.store.delete(&Self::key(&asset.hash)).await?;
+        Ok(())
+    }
+
+    /// 检查请求的宽度和格式, 宽度必须是配置中的宽度之一, 不小于原图宽度时使用原图尺寸;
+    /// 与原图相同时返回空
+    pub fn variant(
+        &self,
+        asset: &Asset,
+        width: Option<u32>,
+        format: Option<OutputFormat>,
+    ) -> Result<Option<Variant>, ServiceError> {
+        let (Some(original), Some(original_width)) =
+            (OutputFormat::from_mime(&asset.mime), asset.width)
+        else {
+            return Err(ServiceError::BadArugment(
+                "该资源不是可以缩放的图片".to_string(),
+            ));
+        };
+        if let Some(width) = width
+            && !self.image_widths.contains(&width)
+        {
+            return Err(ServiceError::BadArugment(format!(
+                "宽度只能是{:?}之一",
+                self.image_widths
+            )));
+        }
+        let variant = Variant {
+            width: width.filter(|width| i64::from(*width) < i64::from(original_width)),
+            format: format.unwrap_or(original),
+        };
+        // bmp没有对应的输出格式, 总是需要转换
+        if variant.width.is_none() && variant.format.mime() == asset.mime {
+            return Ok(None);
+        }
+        Ok(Some(variant))
+    }
+
+    /// 读取衍生图片, 不存在时由原图生成并保存
+    #[instrument(name = "AssetService::read_variant", level = "info", skip(self, asset), fields(asset_id = asset.id))]
+    pub async fn read_variant(
+        &self,
+        asset: &Asset,
+        variant: Variant,
+    ) -> Result<Bytes, ServiceError> {
+        let key = Self::variant_key(&asset.hash, &variant);
+        match self.store.get(&key).await {
+            Ok(data) => return Ok(data),
+            Err(StorageError::NotFound(_)) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        let _permit = self
+            .encoding
+            .acquire()
+            .await
+            .map_err(|e| ServiceError::InternalError(e.to_string()))?;
+        // 等待期间可能已经由其他请求生成
+        match self.store.get(&key).await {
+            Ok(data) => return Ok(data),
+            Err(StorageError::NotFound(_)) => {}
+            Err(e) => return Err(e.into()),
+        }
+        event!(Level::INFO, variant = %variant.name(), "开始生成衍生图片");
+        let original = self.store.get(&Self::key(&asset.hash)).await?;
+        let quality = self.image_quality;
+        let data = task::spawn_blocking(move || imaging::derive(&original, variant, quality))
+            .await
+            .map_err(|e| ServiceError::InternalError(e.to_string()))??;
+        let data = Bytes::from(data);
+        self.store.put(&key, data.clone()).await?;
+
+        event!(Level::INFO, variant = %variant.name(), size = data.len(), "成功生成衍生图片");
+        Ok(data)
+    }
+
+    /// 读取所有资源及其文件, 用于导出备份, 衍生图片可以重新生成因此不导出
+    pub async fn export_assets(&self) -> Result<Vec<BackupAsset>, ServiceError> {
+        let mut assets = Vec::new();
+        for asset in self.asset.list_all().await? {
+            let content = self.store.get(&Self::key(&asset.hash)).await?;
+            assets.push(BackupAsset { asset, content });
+        }
+        Ok(assets)
+    }
+
+    /// 从备份写入资源, 保留原来的id, 返回写入的数量
+    #[instrument(name = "AssetService::restore_assets", level = "info", skip_all)]
+    pub async fn restore_assets(&self, assets: Vec<BackupAsset>) -> Result<usize, ServiceError> {
+        let count = assets.len();
+        for BackupAsset { asset, content } in assets {
+            let mut lock = self.asset.lock_hash(&asset.hash).await?;
+            self.store.put(&Self::key(&asset.hash), content).await?;
+            self.asset
+                .add(
+                    &mut lock,
+                    AssetCreate {
+                        id: Some(asset.id),
+                        hash: asset.hash,
+                        mime: asset.mime,
+                        size: asset.size,
+                        filename: asset.filename,
+                        created_at: Some(asset.created_at),
+                        width: asset.width,
+                        height: asset.height,
+                    },
+                )
+                .await?;
+            self.asset.release(lock).await?;
+        }
+        self.asset.reset_sequence().await?;
+        event!(Level::INFO, asset_count = count, "成功从备份恢复资源");
+        Ok(count)
+    }
+}
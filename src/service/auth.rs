@@ -0,0 +1,356 @@
+use crate::config::AppConfig;
+use crate::models::auth::{Claims, Device, TokenRead};
+use crate::repositories::ReponsitoryError;
+use crate::repositories::session::{
+    Session, SessionCreate, SessionReponsitory, SqlxReponsitory as SessionSqlxReponsitory,
+};
+use crate::repositories::user::{SqlxReponsitory, User, UserReponsitory};
+use crate::service::user::{dummy_hash, verify_password};
+use crate::service::{Principal, ServiceError};
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use tracing::{Level, event, instrument};
+
+/// 校验访问令牌的有效期时允许的时钟误差, 令牌在过期之后这么久之内仍然有效
+const TOKEN_LEEWAY_SECS: u64 = 60;
+/// 过期或被撤销的会话至少保留这么久才删除, 用于在会话列表之外排查问题
+const STALE_SESSION_RETENTION_DAYS: i64 = 7;
+
+/// 被撤销的会话和被删除的用户, 在它们签发的访问令牌全部过期之前拒绝这些令牌
+#[derive(Default)]
+struct Denylist {
+    /// 会话id -> 撤销时间
+    sessions: HashMap<i32, DateTime<Utc>>,
+    /// 用户id -> 删除时间, 删除用户时会话随之删除, 所以单独记录
+    users: HashMap<i32, DateTime<Utc>>,
+}
+
+impl Denylist {
+    fn denies(&self, claims: &Claims) -> bool {
+        self.sessions.contains_key(&claims.sid) || self.users.contains_key(&claims.sub)
+    }
+
+    /// 删除撤销时间早于`before`的记录, 之前签发的访问令牌都已经过期
+    fn prune(&mut self, before: DateTime<Utc>) {
+        self.sessions.retain(|_, revoked| *revoked >= before);
+        self.users.retain(|_, deleted| *deleted >= before);
+    }
+}
+
+pub struct AuthService {
+    user: Box<dyn UserReponsitory>,
+    session: Box<dyn SessionReponsitory>,
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    /// 访问令牌的有效期
+    expiration: Duration,
+    /// 刷新令牌的有效期
+    refresh_expiration: Duration,
+    /// 用户名不存在时也校验一次密码, 避免通过响应时间判断用户名是否存在
+    dummy_hash: String,
+    denylist: RwLock<Denylist>,
+}
+
+impl AuthService {
+    /// 不检查密钥的强度, 只有签发令牌的http服务需要检查, 见[`AppConfig::check_secret`]
+    pub fn new(pool: PgPool, config: &AppConfig) -> Self {
+        let secret = config.get_secret();
+        tracing::info!("创建AuthService实例成功");
+        AuthService {
+            user: Box::new(SqlxReponsitory::new(pool.clone())),
+            session: Box::new(SessionSqlxReponsitory::new(pool)),
+            encoding: EncodingKey::from_secret(secret.as_bytes()),
+            decoding: DecodingKey::from_secret(secret.as_bytes()),
+            expiration: Duration::minutes(config.get_jwt_expiration()),
+            refresh_expiration: Duration::days(config.get_refresh_expiration()),
+            dummy_hash: dummy_hash(),
+            denylist: RwLock::new(Denylist::default()),
+        }
+    }
+
+    /// 启动时加载最近被撤销的会话, 重启之前撤销的会话签发的访问令牌可能还没有过期
+    #[instrument(name = "AuthService::load_denylist", skip(self))]
+    pub async fn load_denylist(&self) -> Result<(), ServiceError> {
+        let now = Utc::now();
+        let revoked = self
+            .session
+            .list_revoked_since(now - self.token_lifetime())
+            .await?;
+        self.deny(&revoked);
+        event!(
+            Level::INFO,
+            session_count = revoked.len(),
+            "成功加载已撤销的会话"
+        );
+        Ok(())
+    }
+
+    /// 校验用户名和密码, 成功时创建会话并签发访问令牌和刷新令牌
+    #[instrument(name = "AuthService::login", skip(self, password))]
+    pub async fn login(
+        &self,
+        username: &str,
+        password: String,
+        device: Device,
+    ) -> Result<TokenRead, ServiceError> {
+        event!(Level::INFO, "开始处理登录");
+
+        let user = match self.user.find_by_username(username).await {
+            Ok(user) => Some(user),
+            Err(ReponsitoryError::NotFound) => None,
+            Err(e) => return Err(e.into()),
+        };
+        let hash = user.as_ref().map_or_else(
+            || self.dummy_hash.clone(),
+            |user| user.password_hash.clone(),
+        );
+        let verified = verify_password(password, hash).await?;
+        let Some(user) = user.filter(|_| verified) else {
+            event!(Level::WARN, "用户名或密码错误");
+            return Err(ServiceError::Unauthorized("用户名或密码错误".to_string()));
+        };
+
+        let retention = Duration::days(STALE_SESSION_RETENTION_DAYS).max(self.token_lifetime());
+        if let Err(e) = self.session.delete_stale(Utc::now() - retention).await {
+            event!(Level::WARN, error = %e, "删除失效的会话失败");
+        }
+        let (refresh_token, refresh_hash) = new_refresh_token();
+        let session = self
+            .session
+            .add(SessionCreate {
+                user_id: user.id,
+                refresh_hash,
+                user_agent: device.user_agent,
+                ip: device.ip,
+                expires_at: Utc::now() + self.refresh_expiration,
+            })
+            .await?;
+        let token = self.issue(&user, &session, refresh_token)?;
+
+        event!(Level::INFO, user_id = user.id, role = %user.role, session_id = session.id, "成功登录");
+        Ok(token)
+    }
+
+    /// 使用刷新令牌换取新的访问令牌和刷新令牌, 旧的刷新令牌随即失效;
+    /// 最近换掉的刷新令牌再次被使用说明令牌可能已经泄露, 此时撤销整个会话
+    #[instrument(name = "AuthService::refresh", skip_all)]
+    pub async fn refresh(&self, refresh_token: &str) -> Result<TokenRead, ServiceError> {
+        event!(Level::INFO, "开始刷新令牌");
+
+        let hash = hash_token(refresh_token);
+        let invalid = || ServiceError::Unauthorized("刷新令牌无效或已过期".to_string());
+        let session = match self.session.find_by_hash(&hash).await {
+            Ok(session) => session,
+            Err(ReponsitoryError::NotFound) => {
+                event!(Level::WARN, "刷新令牌不存在");
+                return Err(invalid());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if session.refresh_hash != hash {
+            event!(
+                Level::WARN,
+                session_id = session.id,
+                user_id = session.user_id,
+                "刷新令牌被重复使用, 撤销会话"
+            );
+            self.revoke(session.id).await?;
+            return Err(invalid());
+        }
+        if session.revoked_at.is_some() || session.expires_at <= Utc::now() {
+            event!(Level::WARN, session_id = session.id, "会话已被撤销或已过期");
+            return Err(invalid());
+        }
+
+        // 重新读取用户, 刷新后的令牌使用最新的角色
+        let user = self.user.find_by_id(session.user_id).await?;
+        let (refresh_token, refresh_hash) = new_refresh_token();
+        let session = match self
+            .session
+            .rotate(
+                session.id,
+                &hash,
+                &refresh_hash,
+                Utc::now() + self.refresh_expiration,
+            )
+            .await
+        {
+            Ok(session) => session,
+            Err(ReponsitoryError::NotFound) => {
+                event!(
+                    Level::WARN,
+                    session_id = session.id,
+                    "刷新令牌已被其他请求使用"
+                );
+                return Err(invalid());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let token = self.issue(&user, &session, refresh_token)?;
+
+        event!(
+            Level::INFO,
+            user_id = user.id,
+            session_id = session.id,
+            "成功刷新令牌"
+        );
+        Ok(token)
+    }
+
+    /// 撤销发起请求的会话, 会话的访问令牌和刷新令牌都立即失效
+    #[instrument(name = "AuthService::logout", skip_all, fields(username = %principal.username))]
+    pub async fn logout(&self, principal: &Principal) -> Result<(), ServiceError> {
+        let Some(session_id) = principal.session_id else {
+            return Ok(());
+        };
+        self.revoke(session_id).await?;
+        event!(Level::INFO, session_id = session_id, "成功退出登录");
+        Ok(())
+    }
+
+    /// 用户所有有效的会话, 即登录过的设备
+    #[instrument(name = "AuthService::list_sessions", skip_all, fields(username = %principal.username))]
+    pub async fn list_sessions(&self, principal: &Principal) -> Result<Vec<Session>, ServiceError> {
+        let Some(user_id) = principal.id else {
+            return Ok(Vec::new());
+        };
+        let sessions = self.session.list_active(user_id).await?;
+        event!(Level::INFO, session_count = sessions.len(), "成功查询会话");
+        Ok(sessions)
+    }
+
+    /// 撤销一个会话, 用户可以撤销自己的会话, 管理员可以撤销所有会话
+    #[instrument(name = "AuthService::revoke_session", skip(self, principal), fields(username = %principal.username))]
+    pub async fn revoke_session(&self, principal: &Principal, id: i32) -> Result<(), ServiceError> {
+        let session = self.session.find_by_id(id).await?;
+        principal.require_session_owner(session.user_id)?;
+        self.revoke(id).await?;
+        event!(Level::INFO, session_id = id, "成功撤销会话");
+        Ok(())
+    }
+
+    /// 撤销用户的所有会话, 修改密码或角色之后调用
+    #[instrument(name = "AuthService::revoke_user", skip(self))]
+    pub async fn revoke_user(&self, user_id: i32) -> Result<(), ServiceError> {
+        let revoked = self.session.revoke_user(user_id).await?;
+        self.deny(&revoked);
+        event!(
+            Level::INFO,
+            user_id = user_id,
+            session_count = revoked.len(),
+            "成功撤销用户的所有会话"
+        );
+        Ok(())
+    }
+
+    /// 拒绝已删除的用户还没有过期的访问令牌, 删除用户之后调用
+    pub fn deny_user(&self, user_id: i32) {
+        let now = Utc::now();
+        let mut denylist = self.denylist.write().unwrap_or_else(|e| e.into_inner());
+        denylist.prune(now - self.token_lifetime());
+        denylist.users.insert(user_id, now);
+        event!(Level::INFO, user_id = user_id, "已拒绝被删除用户的令牌");
+    }
+
+    /// 校验令牌的签名和有效期, 并检查令牌所属的会话没有被撤销, 返回令牌对应的用户
+    pub fn verify(&self, token: &str) -> Result<Principal, ServiceError> {
+        let mut validation = Validation::default();
+        validation.leeway = TOKEN_LEEWAY_SECS;
+        let data =
+            jsonwebtoken::decode::<Claims>(token, &self.decoding, &validation).map_err(|e| {
+                event!(Level::WARN, error = %e, "令牌无效");
+                ServiceError::Unauthorized("令牌无效或已过期".to_string())
+            })?;
+        let claims = data.claims;
+        let denied = self
+            .denylist
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .denies(&claims);
+        if denied {
+            event!(
+                Level::WARN,
+                user_id = claims.sub,
+                session_id = claims.sid,
+                "令牌所属的会话已被撤销"
+            );
+            return Err(ServiceError::Unauthorized("会话已被撤销".to_string()));
+        }
+        Ok(Principal {
+            id: Some(claims.sub),
+            username: claims.name,
+            role: claims.role,
+            session_id: Some(claims.sid),
+        })
+    }
+
+    /// 撤销会话并拒绝它已经签发的访问令牌, 会话已经被撤销时不做任何事
+    async fn revoke(&self, id: i32) -> Result<(), ServiceError> {
+        match self.session.revoke(id).await {
+            Ok(_) => {}
+            Err(ReponsitoryError::NotFound) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+        self.deny(&[id]);
+        Ok(())
+    }
+
+    /// 签发之后访问令牌能够通过校验的最长时间, 被撤销的会话在这段时间内都需要拒绝
+    fn token_lifetime(&self) -> Duration {
+        self.expiration + Duration::seconds(TOKEN_LEEWAY_SECS as i64)
+    }
+
+    fn deny(&self, ids: &[i32]) {
+        let now = Utc::now();
+        let mut denylist = self.denylist.write().unwrap_or_else(|e| e.into_inner());
+        denylist.prune(now - self.token_lifetime());
+        for id in ids {
+            denylist.sessions.insert(*id, now);
+        }
+    }
+
+    fn issue(
+        &self,
+        user: &User,
+        session: &Session,
+        refresh_token: String,
+    ) -> Result<TokenRead, ServiceError> {
+        let now = Utc::now();
+        let claims = Claims {
+            sub: user.id,
+            name: user.username.clone(),
+            role: user.role,
+            sid: session.id,
+            iat: now.timestamp(),
+            exp: (now + self.expiration).timestamp(),
+        };
+        let access_token = jsonwebtoken::encode(&Header::default(), &claims, &self.encoding)
+            .map_err(|e| ServiceError::InternalError(e.to_string()))?;
+        Ok(TokenRead {
+            access_token,
+            token_type: "Bearer",
+            expires_in: self.expiration.num_seconds(),
+            refresh_token,
+            refresh_expires_in: (session.expires_at - now).num_seconds(),
+        })
+    }
+}
+
+/// 随机生成的刷新令牌和它的哈希, 数据库中只保存哈希
+fn new_refresh_token() -> (String, String) {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    let hash = hash_token(&token);
+    (token, hash)
+}
+
+/// 刷新令牌是足够长的随机数, 不需要像密码一样使用慢哈希
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
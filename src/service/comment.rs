@@ -5,7 +5,7 @@ use crate::repositories::comment::{
     Comment as RepoComment, CommentCreate as RepoCommentCreate, CommentReponsitory,
     CommentUpdate as RepoCommentUpdate,
 };
-use crate::service::ServiceError;
+use crate::service::{Principal, ServiceError};
 use sqlx::PgPool;
 use tracing::{instrument, event, Level};
 
@@ -21,13 +21,18 @@ impl CommentService {
         }
     }
 
+    /// 创建评论, 已登录用户的评论记录用户id, 之后可以由本人修改和删除
     #[instrument(
         name = "CommentService::create",
         level = "info",
         skip_all,
         fields(post_id, author)
     )]
-    pub async fn create(&self, comment: ModelCommentCreate) -> Result<CommentRead, ServiceError> {
+    pub async fn create(
+        &self,
+        principal: Option<&Principal>,
+        comment: ModelCommentCreate,
+    ) -> Result<CommentRead, ServiceError> {
         event!(Level::INFO, post_id = comment.post_id, author = %comment.author, parent_id = ?comment.parent_id, "开始创建评论");
 
         let comment_create = RepoCommentCreate {
@@ -35,6 +40,8 @@ impl CommentService {
             author: comment.author.clone(),
             content: comment.content,
             parent_id: comment.parent_id,
+            created_at: None,
+            user_id: principal.and_then(|principal| principal.id),
         };
 
         tracing::Span::current().record("post_id", &comment_create.post_id);
@@ -46,9 +53,11 @@ impl CommentService {
         Ok(convert_repo_comment_to_read(new_comment))
     }
 
-    #[instrument(name = "CommentService::update", level = "info", skip_all, fields(id))]
+    /// 编辑和管理员可以修改所有评论, 其他用户只能修改自己的评论
+    #[instrument(name = "CommentService::update", level = "info", skip_all, fields(id, username = %principal.username))]
     pub async fn update(
         &self,
+        principal: &Principal,
         id: i32,
         comment: ModelCommentUpdate,
     ) -> Result<CommentRead, ServiceError> {
@@ -56,6 +65,9 @@ impl CommentService {
 
         tracing::Span::current().record("id", &id);
 
+        let old = self.comment.find_by_id(id).await?;
+        principal.require_comment_owner(old.user_id)?;
+
         let comment_update = RepoCommentUpdate {
             id,
             content: comment.content,
@@ -67,12 +79,16 @@ impl CommentService {
         Ok(convert_repo_comment_to_read(updated_comment))
     }
 
-    #[instrument(name = "CommentService::delete", level = "info", skip_all, fields(id))]
-    pub async fn delete(&self, id: i32) -> Result<CommentRead, ServiceError> {
+    /// 编辑和管理员可以删除所有评论, 其他用户只能删除自己的评论
+    #[instrument(name = "CommentService::delete", level = "info", skip_all, fields(id, username = %principal.username))]
+    pub async fn delete(&self, principal: &Principal, id: i32) -> Result<CommentRead, ServiceError> {
         event!(Level::INFO, comment_id = id, "开始删除评论");
 
         tracing::Span::current().record("id", &id);
 
+        let old = self.comment.find_by_id(id).await?;
+        principal.require_comment_owner(old.user_id)?;
+
         let deleted_comment = self.comment.delete(id).await?;
         
         event!(Level::INFO, comment_id = id, post_id = deleted_comment.post_id, author = %deleted_comment.author, "成功删除评论");
@@ -125,5 +141,6 @@ fn convert_repo_comment_to_read(comment: RepoComment) -> CommentRead {
         content: comment.content,
         created_at: comment.created_at.to_string(),
         parent_id: comment.parent_id,
+        user_id: comment.user_id,
     }
 }
@@ -1,97 +1,477 @@
-use std::fs::create_dir_all;
-use std::path::{Path, PathBuf};
-use std::sync::Arc;
+mod backup;
+mod consistency;
+mod history;
+mod import;
+mod reindex;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 
+use crate::config::SearchConfig;
+use crate::embedding::Embedder;
 use crate::models::{Pagenigation, post::*};
-use crate::repositories::post;
-use crate::repositories::post::{PostMeta, PostMetaCreate, PostMetaReponsitory};
-use crate::service::ServiceError;
+use crate::repositories::alias::PostAliasReponsitory;
+use crate::repositories::asset::AssetReponsitory;
+use crate::repositories::backup::BackupReponsitory;
+use crate::repositories::chunk::{PostChunkCreate, PostChunkReponsitory};
+use crate::repositories::comment::CommentReponsitory;
+use crate::repositories::revision::PostRevisionReponsitory;
+use crate::repositories::user::Role;
+use crate::repositories::{alias, asset, chunk, comment, post, revision};
+use crate::repositories::post::{
+    Keywords, PostMeta, PostMetaCreate, PostMetaReponsitory, PostMetaUpdate, PostSearchHit,
+    PostStatus,
+};
+use crate::service::{Principal, ServiceError};
+use crate::storage::{ByteStream, Change, ChangeNote, PostStorage, StorageError};
+use crate::util::{
+    MARKDOWN_UTIL, PlainText, asset_ids, escape_html, highlight_snippet, join_segments,
+    normalize_path, slugify, strip_segment_spaces,
+};
 use jieba_rs::Jieba;
+use pgvector::Vector;
 use sqlx::PgPool;
-use tokio::fs::File;
-use tokio::io::{self, AsyncWriteExt};
+use sqlx::types::chrono::{DateTime, Utc};
 use tokio::task;
 use tracing::{instrument, event, Level};
+
+/// 计算向量时每个分段的最大字符数
+const CHUNK_CHARS: usize = 500;
+/// 语义搜索结果中摘要的最大字符数
+const SNIPPET_CHARS: usize = 120;
+/// 自动生成的文章摘要的最大字符数
+const EXCERPT_CHARS: usize = 150;
+
+/// 渲染结果及其对应的文章修改时间
+type RenderedPost = (DateTime<Utc>, Arc<str>);
+
 pub struct PostService {
     post: Box<dyn PostMetaReponsitory>,
-    save_path: String,
+    chunk: Box<dyn PostChunkReponsitory>,
+    revision: Box<dyn PostRevisionReponsitory>,
+    alias: Box<dyn PostAliasReponsitory>,
+    /// 导入其他博客程序的评论时使用
+    comment: Box<dyn CommentReponsitory>,
+    backup: Box<dyn BackupReponsitory>,
+    /// 记录文章正文引用的资源
+    asset: Box<dyn AssetReponsitory>,
+    embedder: Arc<dyn Embedder>,
+    search: SearchConfig,
+    storage: Arc<dyn PostStorage>,
     jieba: Arc<Jieba>,
+    /// 渲染后的html缓存, 以文章的最后修改时间作为版本
+    rendered: RwLock<HashMap<i32, RenderedPost>>,
 }
 impl PostService {
-    pub fn new(pool: PgPool, save_dir: &str) -> Self {
-        let dir = Path::new(save_dir);
-        if !dir.exists() {
-            if let Err(e) = create_dir_all(dir) {
-                panic!(
-                    "无法创建文件夹: {}\n{}",
-                    dir.to_str().unwrap_or(""),
-                    e.to_string()
-                )
-            }
-        }
-
-        if !dir.is_dir() {
-            panic!(r"`{}`为无效的路径, 请输入路径", dir.to_str().unwrap_or(""))
-        }
+    pub fn new(
+        pool: PgPool,
+        storage: Arc<dyn PostStorage>,
+        embedder: Arc<dyn Embedder>,
+        search: SearchConfig,
+    ) -> Self {
         let jieba = Arc::new(Jieba::new());
 
-        tracing::info!("创建PostServie 实例成功, 保存路径为: {}", save_dir);
+        tracing::info!("创建PostServie 实例成功");
 
         PostService {
             post: Box::new(post::SqlxReponsitory::new(pool.clone())),
-            save_path: save_dir.to_string(),
+            chunk: Box::new(chunk::SqlxReponsitory::new(pool.clone())),
+            revision: Box::new(revision::SqlxReponsitory::new(pool.clone())),
+            alias: Box::new(alias::SqlxReponsitory::new(pool.clone())),
+            comment: Box::new(comment::SqlxReponsitory::new(pool.clone())),
+            backup: Box::new(crate::repositories::backup::SqlxReponsitory::new(
+                pool.clone(),
+            )),
+            asset: Box::new(asset::SqlxReponsitory::new(pool.clone())),
+            embedder,
+            search,
+            storage,
             jieba,
+            rendered: RwLock::new(HashMap::new()),
         }
     }
 
-    #[instrument(name = "PostService::add_one", level = "info", skip_all, fields(id))]
-    pub async fn add_one(&self, post: PostCreate) -> Result<PostMeta, ServiceError> {
+    /// 上传文章, 需要作者权限, 文章属于上传的用户, 没有指定提交者时以用户名作为提交者
+    #[instrument(name = "PostService::add_one", level = "info", skip_all, fields(id, username = %principal.username))]
+    pub async fn add_one(&self, principal: &Principal, mut post: PostCreate) -> Result<PostMeta, ServiceError> {
+        principal.require(Role::Author)?;
+        post.author = post.author.or_else(|| principal.committer());
+        self.create_post(None, principal.id, post).await
+    }
+    /// 创建文章, `id`不为空时使用指定的id(从备份恢复时)
+    async fn create_post(&self, id: Option<i32>, author_id: Option<i32>, post: PostCreate) -> Result<PostMeta, ServiceError> {
         let PostCreate {
             title,
+            slug,
             tags,
             content,
+            date,
+            updated,
+            summary,
+            status,
+            publish_at,
+            author,
+            message,
         } = post;
 
         event!(Level::INFO, title = %title, tags_count = tags.len(), content_size = content.len(), "开始创建新文章");
 
+        let body = MARKDOWN_UTIL.parse(&content)?.body;
+        let text = MARKDOWN_UTIL.to_plain_text(body);
+        let toc = MARKDOWN_UTIL.toc(body);
+        let stats = MARKDOWN_UTIL.stats(&text);
+        let excerpt = Self::excerpt(summary, body);
+        let assets = asset_ids(body);
+        let status = Self::resolve_status(status, publish_at)?;
+
         //metadata的存储
-        // 使用jieba进行分词
-        let kw = self.cut(&title).await;
-        event!(Level::DEBUG, keywords_count = kw.len(), "完成文章分词");
-        
-        let post_meta_create = PostMetaCreate { title: title.clone(), tags, kw };
+        // 使用jieba对标题和正文进行分词
+        let kw = self.keywords(&title, &text).await;
+        event!(Level::DEBUG, keywords_count = kw.count(), "完成文章分词");
+
+        let slug = self
+            .unique_slug(slug.as_deref().unwrap_or(&title), 0)
+            .await?;
+
+        let post_meta_create = PostMetaCreate {
+            id,
+            title: title.clone(),
+            slug,
+            tags,
+            kw,
+            toc,
+            stats,
+            excerpt,
+            status,
+            publish_at,
+            // 定时发布的文章以发布时间作为首次发布时间
+            first_publish: match status {
+                PostStatus::Scheduled => publish_at,
+                _ => date,
+            },
+            last_modify: updated,
+            author_id,
+        };
         let new = self.post.add(post_meta_create).await?;
 
         tracing::Span::current().record("id", &new.id);
         event!(Level::INFO, post_id = new.id, title = %new.title, "成功保存文章元数据");
 
-        // 文件的存储
-        self.to_file(&new.title, content).await?;
+        // 文件的存储, 失败时删除刚插入的元数据, 避免留下没有内容的文章
+        let snapshot = String::from_utf8_lossy(&content).into_owned();
+        let note = ChangeNote {
+            change: Change::Add,
+            id: new.id,
+            title: &new.title,
+            author: author.as_deref(),
+            message: message.as_deref(),
+        };
+        if let Err(e) = self.storage.create(new.id, content, &note).await {
+            event!(Level::ERROR, post_id = new.id, error = %e, "保存文章内容失败, 撤销文章元数据");
+            if let Err(e) = self.post.delete(new.id).await {
+                event!(Level::ERROR, post_id = new.id, error = %e, "撤销文章元数据失败");
+            }
+            return Err(e.into());
+        }
         event!(Level::INFO, post_id = new.id, title = %new.title, "成功保存文章内容");
+        self.sync_assets(new.id, &assets).await;
+
+        // 向量计算失败时文章仍然可以通过关键词搜索到, 因此不中断上传
+        if let Err(e) = self.embed(new.id, &new.title, &text).await {
+            event!(Level::WARN, post_id = new.id, error = %e, "计算文章向量失败");
+        }
+
+        self.record_revision(&new, snapshot, author, message).await;
 
         Ok(new)
     }
-    #[instrument(name = "PostService::read_one", level = "info", skip(self))]
-    pub async fn read_one(&self, id: i32) -> Result<PostMeta, ServiceError> {
+    /// 更新文章的元数据和文件, 没有指定slug时保持原来的slug, 避免已有的链接失效
+    #[instrument(name = "PostService::update_one", level = "info", skip(self, principal, update), fields(username = %principal.username))]
+    pub async fn update_one(&self, principal: &Principal, id: i32, update: PostUpdate) -> Result<PostMeta, ServiceError> {
+        let PostUpdate {
+            title,
+            slug,
+            tags,
+            content,
+            status,
+            publish_at,
+            author,
+            message,
+        } = update;
+
+        event!(Level::INFO, post_id = id, title = ?title, content_updated = content.is_some(), "开始更新文章");
+
+        let old = self.post.find_by_id(id).await?;
+        principal.require_post_author(old.author_id)?;
+        let author = author.or_else(|| principal.committer());
+        let title = title.unwrap_or_else(|| old.title.clone());
+        let tags = tags.unwrap_or_else(|| old.tags.0.clone());
+        let slug = match slug {
+            Some(slug) if slug != old.slug => self.unique_slug(&slug, id).await?,
+            _ => old.slug.clone(),
+        };
+        // 只修改了发布时间时按照新的时间重新判断状态
+        let status = match (status, publish_at) {
+            (None, None) => Some(old.status),
+            (status, _) => status,
+        };
+        let publish_at = publish_at.or(old.publish_at);
+        let status = Self::resolve_status(status, publish_at)?;
+        // 保存修改前的文件, 元数据更新失败时用来恢复
+        let previous = match self.storage.read(id).await {
+            Ok(previous) => Some(previous),
+            Err(StorageError::NotFound(_)) => None,
+            Err(e) => return Err(e.into()),
+        };
+        let content = match content.or_else(|| previous.clone()) {
+            Some(content) => content,
+            None => return Err(ServiceError::NotFound),
+        };
+
+        let parsed = MARKDOWN_UTIL.parse(&content)?;
+        let body = parsed.body;
+        let text = MARKDOWN_UTIL.to_plain_text(body);
+        let toc = MARKDOWN_UTIL.toc(body);
+        let stats = MARKDOWN_UTIL.stats(&text);
+        let excerpt = Self::excerpt(parsed.front_matter.summary, body);
+        let assets = asset_ids(body);
+        let kw = self.keywords(&title, &text).await;
+        event!(Level::DEBUG, keywords_count = kw.count(), "完成文章分词");
+
+        // 启用版本记录之前创建的文章没有历史版本, 先保存修改前的内容
+        if !self.revision.exists(id).await? {
+            self.record_legacy_revision(&old).await;
+        }
+
+        let snapshot = String::from_utf8_lossy(&content).into_owned();
+        let note = ChangeNote {
+            change: Change::Update,
+            id,
+            title: &title,
+            author: author.as_deref(),
+            message: message.as_deref(),
+        };
+        self.storage.replace(id, content, &note).await?;
+        self.invalidate_rendered(id);
+        let update = PostMetaUpdate {
+            id,
+            title: title.clone(),
+            slug,
+            tags,
+            kw,
+            toc,
+            stats,
+            excerpt,
+            status,
+            publish_at,
+        };
+        let updated = match self.post.update(update).await {
+            Ok(updated) => updated,
+            Err(e) => {
+                event!(Level::ERROR, post_id = id, error = %e, "更新文章元数据失败, 撤销文件修改");
+                self.undo_replace(id, &title, previous).await;
+                return Err(e.into());
+            }
+        };
+        event!(Level::INFO, post_id = id, title = %updated.title, "成功更新文章元数据");
+        self.sync_assets(id, &assets).await;
+        self.record_revision(&updated, snapshot, author, message)
+            .await;
+
+        if let Err(e) = self.embed(id, &updated.title, &text).await {
+            event!(Level::WARN, post_id = id, error = %e, "计算文章向量失败");
+        }
+
+        Ok(updated)
+    }
+    /// 记录文章正文引用的资源, 失败时只记录日志, 资源在清理的保护期内不会被删除
+    async fn sync_assets(&self, id: i32, assets: &[i32]) {
+        if let Err(e) = self.asset.sync_links(id, assets).await {
+            event!(Level::WARN, post_id = id, error = %e, "更新文章引用的资源失败");
+        }
+    }
+    /// 元数据更新失败时将文件恢复为修改前的内容, 修改前没有文件时删除
+    async fn undo_replace(&self, id: i32, title: &str, previous: Option<Vec<u8>>) {
+        let message = Some("撤销未完成的修改");
+        let undone = match previous {
+            Some(previous) => {
+                let note = ChangeNote {
+                    change: Change::Update,
+                    id,
+                    title,
+                    author: None,
+                    message,
+                };
+                self.storage.replace(id, previous, &note).await
+            }
+            None => {
+                let note = ChangeNote {
+                    change: Change::Delete,
+                    id,
+                    title,
+                    author: None,
+                    message,
+                };
+                self.storage.remove(id, &note).await.map(|_| ())
+            }
+        };
+        self.invalidate_rendered(id);
+        if let Err(e) = undone {
+            event!(Level::ERROR, post_id = id, error = %e, "撤销文件修改失败");
+        }
+    }
+    /// 未公开的文章只有能够修改它的用户可以读取, 用于预览草稿; 其他人看来文章不存在
+    #[instrument(name = "PostService::read_one", level = "info", skip(self, principal))]
+    pub async fn read_one(&self, principal: Option<&Principal>, id: i32) -> Result<PostMeta, ServiceError> {
         event!(Level::INFO, post_id = id, "开始查询文章元数据");
         let post = self.post.find_by_id(id).await?;
+        Self::require_visible(principal, &post)?;
         event!(Level::INFO, post_id = id, title = %post.title, "成功查询文章元数据");
         Ok(post)
     }
-    #[instrument(name = "PostService::delete_one", level = "info", skip(self))]
-    pub async fn delete_one(&self, id: i32) -> Result<PostMeta, ServiceError> {
-        event!(Level::INFO, post_id = id, "开始删除文章");
-        
-        // 首先获取要删除的 post
-        let post = self.post.find_by_id(id).await?;
-        event!(Level::INFO, post_id = id, title = %post.title, "找到要删除的文章");
+    #[instrument(name = "PostService::read_by_slug", level = "info", skip(self, principal))]
+    pub async fn read_by_slug(&self, principal: Option<&Principal>, slug: &str) -> Result<PostMeta, ServiceError> {
+        event!(Level::INFO, slug = slug, "开始根据slug查询文章元数据");
+        let post = self.post.find_by_slug(slug).await?;
+        Self::require_visible(principal, &post)?;
+        event!(Level::INFO, post_id = post.id, slug = slug, "成功根据slug查询文章元数据");
+        Ok(post)
+    }
+    /// 根据导入前的旧地址查询文章, 地址可以是完整的链接
+    #[instrument(name = "PostService::read_by_alias", level = "info", skip(self, principal))]
+    pub async fn read_by_alias(&self, principal: Option<&Principal>, path: &str) -> Result<PostMeta, ServiceError> {
+        event!(Level::INFO, path = path, "开始根据旧地址查询文章元数据");
+        let Some(path) = normalize_path(path) else {
+            return Err(ServiceError::NotFound);
+        };
+        let id = self.alias.find(&path).await?;
+        self.read_one(principal, id).await
+    }
+    fn require_visible(principal: Option<&Principal>, post: &PostMeta) -> Result<(), ServiceError> {
+        let editable = principal.is_some_and(|principal| principal.can_edit_post(post.author_id));
+        if post.status.is_reachable() || editable {
+            return Ok(());
+        }
+        event!(Level::INFO, post_id = post.id, status = ?post.status, "文章尚未公开");
+        Err(ServiceError::NotFound)
+    }
+    /// 读取文章的markdown源文件
+    pub async fn read_content(&self, post: &PostMeta) -> Result<String, ServiceError> {
+        let content = self.storage.read(post.id).await?;
+        String::from_utf8(content).map_err(|e| ServiceError::InternalError(e.to_string()))
+    }
+    /// 流式读取文章的markdown源文件
+    pub async fn read_raw(&self, post: &PostMeta) -> Result<ByteStream, ServiceError> {
+        Ok(self.storage.read_stream(post.id).await?)
+    }
+    pub async fn read_formatted(
+        &self,
+        post: &PostMeta,
+        format: ContentFormat,
+    ) -> Result<String, ServiceError> {
+        match format {
+            ContentFormat::Markdown => self.read_content(post).await,
+            ContentFormat::Html => self.read_html(post).await,
+        }
+    }
+    /// 读取渲染为html的文章正文, 优先使用缓存
+    #[instrument(name = "PostService::read_html", level = "info", skip_all, fields(id = post.id))]
+    pub async fn read_html(&self, post: &PostMeta) -> Result<String, ServiceError> {
+        let cached = self
+            .rendered
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&post.id)
+            .filter(|(version, _)| *version == post.last_modify)
+            .map(|(_, html)| Arc::clone(html));
+        if let Some(html) = cached {
+            event!(Level::DEBUG, post_id = post.id, "命中html缓存");
+            return Ok(html.to_string());
+        }
+
+        let content = self.storage.read(post.id).await?;
+        let html: Arc<str> = task::spawn_blocking(move || {
+            MARKDOWN_UTIL
+                .parse(&content)
+                .map(|parsed| MARKDOWN_UTIL.to_html(parsed.body))
+        })
+        .await
+        .map_err(|e| ServiceError::InternalError(e.to_string()))??
+        .into();
+        event!(Level::DEBUG, post_id = post.id, html_size = html.len(), "完成html渲染");
+
+        self.rendered
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(post.id, (post.last_modify, Arc::clone(&html)));
+        Ok(html.to_string())
+    }
+    fn invalidate_rendered(&self, id: i32) {
+        self.rendered
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&id);
+    }
+    /// 删除文章, `permanent`为`false`时只移入回收站, 之后可以恢复
+    #[instrument(name = "PostService::delete_one", level = "info", skip(self, principal), fields(username = %principal.username))]
+    pub async fn delete_one(&self, principal: &Principal, id: i32, permanent: bool) -> Result<PostMeta, ServiceError> {
+        event!(Level::INFO, post_id = id, permanent = permanent, "开始删除文章");
+
+        principal.require_post_author(self.post.find_author(id).await?)?;
 
-        // 然后删除它
-        self.post.delete(id).await?;
-        event!(Level::INFO, post_id = id, title = %post.title, "成功删除文章元数据");
+        if !permanent {
+            let post = self.post.trash(id).await?;
+            event!(Level::INFO, post_id = id, title = %post.title, "成功将文章移入回收站");
+            return Ok(post);
+        }
+
+        // 回收站中的文章也可以被永久删除, 评论随元数据一起删除
+        // 先删除元数据, 删除文件失败时只会留下没有对应文章的文件, 不影响读取,
+        // 之后可以由一致性检查隔离
+        let post = self.post.delete(id).await?;
+        self.invalidate_rendered(id);
+        event!(Level::INFO, post_id = id, title = %post.title, "成功删除文章元数据和评论");
+
+        let note = ChangeNote {
+            change: Change::Delete,
+            id,
+            title: &post.title,
+            author: None,
+            message: None,
+        };
+        match self.storage.remove(id, &note).await {
+            Ok(true) => {
+                event!(Level::INFO, post_id = id, title = %post.title, "成功删除文章文件")
+            }
+            Ok(false) => {
+                event!(Level::WARN, post_id = id, title = %post.title, "文章文件已经不存在")
+            }
+            Err(e) => {
+                event!(Level::ERROR, post_id = id, error = %e, "删除文章文件失败, 文件需要由一致性检查隔离")
+            }
+        }
 
         Ok(post)
     }
+    #[instrument(name = "PostService::restore_one", level = "info", skip(self, principal), fields(username = %principal.username))]
+    pub async fn restore_one(&self, principal: &Principal, id: i32) -> Result<PostMeta, ServiceError> {
+        event!(Level::INFO, post_id = id, "开始从回收站恢复文章");
+        principal.require_post_author(self.post.find_author(id).await?)?;
+        let post = self.post.restore(id).await?;
+        event!(Level::INFO, post_id = id, title = %post.title, "成功从回收站恢复文章");
+        Ok(post)
+    }
+    /// 编辑和管理员可以看到回收站中的所有文章, 作者只能看到自己的文章
+    #[instrument(name = "PostService::list_trash", level = "info", skip_all, fields(username = %principal.username))]
+    pub async fn list_trash(&self, principal: &Principal) -> Result<Vec<PostMeta>, ServiceError> {
+        event!(Level::INFO, "开始查询回收站");
+        principal.require(Role::Author)?;
+        let mut posts = self.post.list_trash().await?;
+        if principal.role < Role::Editor {
+            posts.retain(|post| post.author_id.is_some() && post.author_id == principal.id);
+        }
+        event!(Level::INFO, post_count = posts.len(), "成功查询回收站");
+        Ok(posts)
+    }
     #[instrument(name = "PostService::list", level = "info", skip_all, fields(cursor = %page.cursor.unwrap_or_default(), page_size = %page.page_size))]
     pub async fn list(&self, page: Pagenigation) -> Result<Vec<PostMeta>, ServiceError> {
         // TODO: 目前的分页是简单的使用上一次查询的结果的最后一个id作为下一次查询的游标
@@ -108,6 +488,256 @@ impl PostService {
         event!(Level::INFO, post_count = posts.len(), "成功分页查询文章列表");
         Ok(posts)
     }
+    /// 发布所有已到发布时间的定时文章
+    #[instrument(name = "PostService::publish_scheduled", level = "info", skip(self))]
+    pub async fn publish_scheduled(&self) -> Result<Vec<PostMeta>, ServiceError> {
+        let posts = self.post.publish_due().await?;
+        for post in posts.iter() {
+            event!(Level::INFO, post_id = post.id, title = %post.title, "成功发布定时文章");
+        }
+        Ok(posts)
+    }
+    /// 下一篇定时文章的发布时间, 没有定时文章时为空
+    pub async fn next_scheduled(&self) -> Result<Option<DateTime<Utc>>, ServiceError> {
+        Ok(self.post.next_scheduled().await?)
+    }
+
+    #[instrument(name = "PostService::search", level = "info", skip_all, fields(q = %search.q, mode = ?search.mode))]
+    pub async fn search(&self, search: PostSearch) -> Result<Vec<PostSearchRead>, ServiceError> {
+        let PostSearch {
+            q,
+            mode,
+            offset,
+            page_size,
+            k,
+            keyword_weight,
+            semantic_weight,
+        } = search;
+
+        event!(Level::INFO, q = %q, mode = ?mode, offset = offset, page_size = page_size, "开始搜索文章");
+
+        let results: Vec<PostSearchRead> = match mode {
+            SearchMode::Keyword => self
+                .search_keyword(&q, offset, page_size)
+                .await?
+                .into_iter()
+                .map(|hit| hit.into())
+                .collect(),
+            SearchMode::Semantic => self
+                .search_semantic(&q, offset, page_size)
+                .await?
+                .into_iter()
+                .map(|hit| hit.into())
+                .collect(),
+            SearchMode::Hybrid => {
+                let params = SearchConfig {
+                    rrf_k: k.unwrap_or(self.search.rrf_k),
+                    keyword_weight: keyword_weight.unwrap_or(self.search.keyword_weight),
+                    semantic_weight: semantic_weight.unwrap_or(self.search.semantic_weight),
+                    candidates: self.search.candidates.max(offset + page_size),
+                };
+                self.search_hybrid(&q, &params)
+                    .await?
+                    .into_iter()
+                    .skip(offset as usize)
+                    .take(page_size as usize)
+                    .map(|(hit, explain)| PostSearchRead::with_explain(hit, explain))
+                    .collect()
+            }
+        };
+
+        event!(Level::INFO, post_count = results.len(), "成功搜索文章");
+        Ok(results)
+    }
+
+    /// 分别取关键词搜索和语义搜索的前`candidates`个结果, 按照
+    /// `weight / (k + rank)`累加每篇文章的得分后重新排序
+    async fn search_hybrid(
+        &self,
+        q: &str,
+        params: &SearchConfig,
+    ) -> Result<Vec<(PostSearchHit, SearchExplain)>, ServiceError> {
+        let (keyword, semantic) = tokio::try_join!(
+            self.search_keyword(q, 0, params.candidates),
+            self.search_semantic(q, 0, params.candidates)
+        )?;
+        event!(
+            Level::DEBUG,
+            keyword_count = keyword.len(),
+            semantic_count = semantic.len(),
+            "开始融合搜索结果"
+        );
+        Ok(Self::fuse(keyword, semantic, params))
+    }
+
+    /// 倒数排序融合, 两种结果中都出现的文章得分相加, 得分相同时id大的在前
+    fn fuse(
+        keyword: Vec<PostSearchHit>,
+        semantic: Vec<PostSearchHit>,
+        params: &SearchConfig,
+    ) -> Vec<(PostSearchHit, SearchExplain)> {
+        let mut fused: HashMap<i32, (PostSearchHit, SearchExplain)> = HashMap::new();
+        for (index, hit) in keyword.into_iter().enumerate() {
+            let explain = SearchExplain {
+                keyword_rank: Some(index + 1),
+                keyword_score: Some(hit.score),
+                keyword_rrf: params.keyword_weight / (params.rrf_k + (index + 1) as f32),
+                ..Default::default()
+            };
+            fused.insert(hit.meta.id, (hit, explain));
+        }
+        for (index, hit) in semantic.into_iter().enumerate() {
+            let rank = index + 1;
+            let semantic_score = hit.score;
+            // 关键词搜索的摘要带有高亮, 优先保留
+            let (_, explain) = fused
+                .entry(hit.meta.id)
+                .or_insert_with(|| (hit, SearchExplain::default()));
+            explain.semantic_rank = Some(rank);
+            explain.semantic_score = Some(semantic_score);
+            explain.semantic_rrf = params.semantic_weight / (params.rrf_k + rank as f32);
+        }
+
+        let mut results: Vec<_> = fused
+            .into_values()
+            .map(|(mut hit, explain)| {
+                hit.score = explain.keyword_rrf + explain.semantic_rrf;
+                (hit, explain)
+            })
+            .collect();
+        results.sort_by(|(a, _), (b, _)| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| b.meta.id.cmp(&a.meta.id))
+        });
+        results
+    }
+
+    async fn search_keyword(
+        &self,
+        q: &str,
+        offset: i32,
+        page_size: i32,
+    ) -> Result<Vec<PostSearchHit>, ServiceError> {
+        // 查询语句和正文使用同样的分词方式, 保证词语能够对应上
+        let mut keywords = self.cut(q).await;
+        keywords.sort();
+        keywords.dedup();
+
+        let mut hits = self
+            .post
+            .find_by_keywords(&keywords, offset, page_size)
+            .await?;
+        for hit in hits.iter_mut() {
+            hit.snippet = highlight_snippet(&strip_segment_spaces(&hit.snippet));
+        }
+        Ok(hits)
+    }
+
+    async fn search_semantic(
+        &self,
+        q: &str,
+        offset: i32,
+        page_size: i32,
+    ) -> Result<Vec<PostSearchHit>, ServiceError> {
+        let embedding = self
+            .embedder
+            .embed(&[q.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| ServiceError::InternalError("嵌入模型没有返回向量".to_string()))?;
+
+        let mut hits = self
+            .chunk
+            .find_by_embedding(
+                self.embedder.model(),
+                Vector::from(embedding),
+                offset,
+                page_size,
+            )
+            .await?;
+        for hit in hits.iter_mut() {
+            // 先截断再转义, 避免从转义后的实体中间截断
+            let mut snippet: String = hit.snippet.chars().take(SNIPPET_CHARS).collect();
+            if snippet.len() < hit.snippet.len() {
+                snippet.push_str("...");
+            }
+            hit.snippet = escape_html(&snippet);
+        }
+        Ok(hits)
+    }
+
+    /// 将标题和正文切分为若干段, 分别计算向量后保存
+    #[instrument(name = "PostService::embed", level = "debug", skip(self, text))]
+    async fn embed(&self, id: i32, title: &str, text: &PlainText) -> Result<(), ServiceError> {
+        let mut contents = vec![title.to_string()];
+        contents.extend(MARKDOWN_UTIL.chunks(&text.body, CHUNK_CHARS));
+
+        let embeddings = self.embedder.embed(&contents).await?;
+        let chunks = contents
+            .into_iter()
+            .zip(embeddings)
+            .map(|(content, embedding)| PostChunkCreate {
+                content,
+                embedding: Vector::from(embedding),
+            })
+            .collect::<Vec<_>>();
+
+        event!(Level::DEBUG, post_id = id, chunk_count = chunks.len(), "完成文章向量计算");
+        self.chunk.replace(id, self.embedder.model(), chunks).await?;
+        Ok(())
+    }
+
+    /// 确定文章的发布状态
+    /// - 没有指定状态时, 发布时间在未来的文章为定时发布, 否则直接发布
+    /// - 定时发布必须指定发布时间, 发布时间已经过去时直接发布
+    fn resolve_status(
+        status: Option<PostStatus>,
+        publish_at: Option<DateTime<Utc>>,
+    ) -> Result<PostStatus, ServiceError> {
+        let due = publish_at.map(|publish_at| publish_at <= Utc::now());
+        match (status, due) {
+            (Some(PostStatus::Scheduled), None) => Err(ServiceError::BadArugment(
+                "定时发布的文章需要指定发布时间".to_string(),
+            )),
+            (Some(PostStatus::Scheduled) | None, Some(true)) | (None, None) => {
+                Ok(PostStatus::Published)
+            }
+            (None, Some(false)) => Ok(PostStatus::Scheduled),
+            (Some(status), _) => Ok(status),
+        }
+    }
+
+    /// front matter中的`summary`优先, 否则从正文中生成
+    fn excerpt(summary: Option<String>, body: &str) -> String {
+        match summary {
+            Some(summary) if !summary.trim().is_empty() => summary.trim().to_string(),
+            _ => MARKDOWN_UTIL.excerpt(body, EXCERPT_CHARS),
+        }
+    }
+
+    /// 分别对标题, 小标题和正文分词
+    async fn keywords(&self, title: &str, text: &PlainText) -> Keywords {
+        Keywords {
+            title: self.cut(title).await,
+            headings: self.cut(&text.headings.join("\n")).await,
+            body: self.cut(&text.body).await,
+            segmented: self.segment(&text.body).await,
+        }
+    }
+
+    /// 使用精确模式分词, 返回用空格隔开词语的文本
+    async fn segment(&self, text: &str) -> String {
+        let jieba = Arc::clone(&self.jieba);
+        let text = text.to_string();
+
+        task::spawn_blocking(move || join_segments(jieba.cut(&text, true)))
+            .await
+            .unwrap_or_else(|e| {
+                event!(Level::ERROR, error = %e, "分词任务执行失败");
+                String::new()
+            })
+    }
 
     async fn cut(&self, text: &str) -> Vec<String> {
         let jieba = Arc::clone(&self.jieba);
@@ -119,6 +749,8 @@ impl PostService {
             jieba
                 .cut_for_search(&text, true)
                 .into_iter()
+                // 标点和空白不参与索引
+                .filter(|item| item.chars().any(char::is_alphanumeric))
                 .map(|item| item.to_string())
                 .collect()
         })
@@ -129,31 +761,108 @@ impl PostService {
         })
     }
 
-    pub async fn build_file_path(&self, title: &str) -> PathBuf {
-        Path::new(&self.save_path).join(title)
+    /// 由标题或用户指定的slug生成未被使用的slug, 重复时添加数字后缀
+    async fn unique_slug(&self, source: &str, id: i32) -> Result<String, ServiceError> {
+        let base = slugify(source);
+        let mut slug = base.clone();
+        let mut suffix = 1;
+        while self.post.slug_exists(&slug, id).await? {
+            suffix += 1;
+            slug = format!("{}-{}", base, suffix);
+        }
+        Ok(slug)
     }
 
-    #[instrument(
-        skip_all,
-        level= "info"
-        name="创建新文件"
-        fields(
-            file_name = %file_name,
-        )
-    )]
-    async fn to_file(&self, file_name: &str, content: Vec<u8>) -> Result<(), ServiceError> {
-        let path = self.build_file_path(file_name).await;
+    /// 将旧版本以标题命名的文件重命名为以id命名
+    #[instrument(name = "PostService::migrate_legacy_files", level = "info", skip(self))]
+    pub async fn migrate_legacy_files(&self) -> Result<(), ServiceError> {
+        for post in self.post.list_all().await? {
+            if self.storage.migrate_legacy(post.id, &post.title).await? {
+                event!(Level::INFO, post_id = post.id, title = %post.title, "成功迁移旧的文章文件");
+            }
+        }
+        Ok(())
+    }
+}
 
-        event!(Level::DEBUG, file_path = %path.display(), content_size = content.len(), "开始写入文件");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::types::Json;
 
-        let file = File::create_new(path.as_path()).await?;
-        {
-            let mut writer = io::BufWriter::new(file);
-            writer.write(&content).await?;
-            writer.flush().await?;
+    fn hit(id: i32, score: f32, snippet: &str) -> PostSearchHit {
+        PostSearchHit {
+            meta: PostMeta {
+                id,
+                title: format!("文章{}", id),
+                slug: format!("post-{}", id),
+                tags: Json(Vec::new()),
+                first_publish: DateTime::UNIX_EPOCH,
+                last_modify: DateTime::UNIX_EPOCH,
+                count: 0,
+                toc: Json(Vec::new()),
+                word_count: 0,
+                char_count: 0,
+                reading_time: 0,
+                excerpt: String::new(),
+                status: PostStatus::Published,
+                publish_at: None,
+                author_id: None,
+            },
+            score,
+            snippet: snippet.to_string(),
         }
-        
-        event!(Level::DEBUG, file_path = %path.display(), "成功写入文件");
-        Ok(())
+    }
+
+    fn params(keyword_weight: f32, semantic_weight: f32) -> SearchConfig {
+        SearchConfig {
+            rrf_k: 60.0,
+            keyword_weight,
+            semantic_weight,
+            candidates: 10,
+        }
+    }
+
+    fn ids(results: &[(PostSearchHit, SearchExplain)]) -> Vec<i32> {
+        results.iter().map(|(hit, _)| hit.meta.id).collect()
+    }
+
+    #[test]
+    fn fuse_sums_reciprocal_ranks() {
+        let keyword = vec![hit(1, 0.9, "<mark>1</mark>"), hit(2, 0.5, "")];
+        let semantic = vec![hit(3, 0.8, ""), hit(2, 0.7, "语义摘要")];
+        let results = PostService::fuse(keyword, semantic, &params(1.0, 1.0));
+
+        // 文章2在两种结果中都排第二, 得分是两者之和
+        assert_eq!(ids(&results), vec![2, 3, 1]);
+        let (hit, explain) = &results[0];
+        assert_eq!(explain.keyword_rank, Some(2));
+        assert_eq!(explain.keyword_score, Some(0.5));
+        assert_eq!(explain.semantic_rank, Some(2));
+        assert_eq!(explain.semantic_score, Some(0.7));
+        assert_eq!(hit.score, 2.0 / 62.0);
+        // 关键词搜索的摘要优先
+        assert_eq!(hit.snippet, "");
+        // 得分相同时id大的在前
+        assert_eq!(results[1].0.score, results[2].0.score);
+    }
+
+    #[test]
+    fn fuse_applies_weights() {
+        let keyword = vec![hit(1, 0.9, "")];
+        let semantic = vec![hit(2, 0.8, "")];
+
+        let results = PostService::fuse(keyword, semantic, &params(2.0, 1.0));
+        assert_eq!(ids(&results), vec![1, 2]);
+        assert_eq!(results[0].1.keyword_rrf, 2.0 / 61.0);
+        assert_eq!(results[0].1.semantic_rank, None);
+        assert_eq!(results[1].1.semantic_rrf, 1.0 / 61.0);
+        assert_eq!(results[1].1.keyword_rank, None);
+    }
+
+    #[test]
+    fn fuse_without_results() {
+        let results = PostService::fuse(Vec::new(), Vec::new(), &params(1.0, 1.0));
+        assert!(results.is_empty());
     }
 }
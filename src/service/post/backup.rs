@@ -0,0 +1,301 @@
+use super::PostService;
+use crate::backup::{BACKUP_VERSION, Backup, BackupComment, BackupPost, Manifest};
+use crate::import::ImportComment;
+use crate::models::backup::RestoreReport;
+use crate::models::post::PostCreate;
+use crate::repositories::post::PostMeta;
+use crate::repositories::user::Role;
+use crate::service::{AssetService, Principal, ServiceError};
+use crate::storage::StorageError;
+use crate::util::{FrontMatter, MARKDOWN_UTIL};
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use tracing::{Level, event, instrument};
+
+impl PostService {
+    /// 导出所有文章(包括回收站中的文章), 评论, 旧地址和资源, 打包为tar.gz, 格式见[`Backup`]
+    /// 需要管理员权限
+    #[instrument(name = "PostService::export_archive", level = "info", skip_all, fields(username = %principal.username))]
+    pub async fn export_archive(
+        &self,
+        principal: &Principal,
+        assets: &AssetService,
+    ) -> Result<Vec<u8>, ServiceError> {
+        principal.require(Role::Admin)?;
+        event!(Level::INFO, "开始导出备份");
+
+        let schema_version = self.backup.schema_version().await?;
+        let metas = self.post.list_all().await?;
+        let states = self.backup.list_post_states().await?;
+        let comments = self.backup.list_comments().await?;
+        let aliases = self.backup.list_aliases().await?;
+        let assets = assets.export_assets().await?;
+
+        let mut tags = BTreeSet::new();
+        let mut posts = Vec::with_capacity(metas.len());
+        for meta in metas.iter() {
+            let content = match self.storage.read(meta.id).await {
+                Ok(content) => content,
+                Err(StorageError::NotFound(_)) => {
+                    event!(Level::ERROR, post_id = meta.id, "文章文件不存在");
+                    return Err(ServiceError::InternalError(format!(
+                        "文章{}的文件不存在, 请先运行一致性检查",
+                        meta.id
+                    )));
+                }
+                Err(e) => return Err(e.into()),
+            };
+            posts.push(BackupPost {
+                id: meta.id,
+                content: Self::backup_content(meta, &content)?,
+            });
+            tags.extend(meta.tags.0.iter().cloned());
+        }
+
+        let backup = Backup {
+            manifest: Manifest {
+                version: BACKUP_VERSION,
+                schema_version,
+                exported_at: Utc::now(),
+                post_count: posts.len(),
+                comment_count: comments.len(),
+                asset_count: assets.len(),
+            },
+            posts,
+            states,
+            comments: comments.into_iter().map(BackupComment::from).collect(),
+            aliases,
+            tags: tags.into_iter().collect(),
+            assets,
+        };
+        let archive = backup.write().await?;
+
+        event!(
+            Level::INFO,
+            post_count = metas.len(),
+            archive_size = archive.len(),
+            "成功导出备份"
+        );
+        Ok(archive)
+    }
+
+    /// 以数据库中的元数据重新生成front matter, 保留文章中原来的摘要
+    fn backup_content(meta: &PostMeta, content: &[u8]) -> Result<Vec<u8>, ServiceError> {
+        let parsed = MARKDOWN_UTIL
+            .parse(content)
+            .map_err(|e| ServiceError::InternalError(format!("无法解析文章{}: {}", meta.id, e)))?;
+        let front_matter = FrontMatter {
+            title: Some(meta.title.clone()),
+            slug: Some(meta.slug.clone()),
+            tags: Some(meta.tags.0.clone()),
+            date: Some(meta.first_publish),
+            updated: Some(meta.last_modify),
+            summary: parsed.front_matter.summary,
+            status: Some(meta.status.to_string()),
+            publish_at: meta.publish_at,
+        };
+        Ok(MARKDOWN_UTIL.compose(&front_matter, parsed.body))
+    }
+
+    /// 从备份恢复到空的数据库和文章存储, 文章和资源保留原来的id, 评论重新分配id并保留回复关系;
+    /// 先检查整个备份, 发现问题或`dry_run`为`true`时不写入任何内容
+    #[instrument(name = "PostService::restore", level = "info", skip_all, fields(dry_run = dry_run))]
+    pub async fn restore(
+        &self,
+        backup: Backup,
+        assets: &AssetService,
+        dry_run: bool,
+    ) -> Result<RestoreReport, ServiceError> {
+        event!(
+            Level::INFO,
+            post_count = backup.posts.len(),
+            dry_run = dry_run,
+            "开始从备份恢复"
+        );
+        let mut report = RestoreReport {
+            dry_run,
+            ..Default::default()
+        };
+
+        let schema_version = self.backup.schema_version().await?;
+        if backup.manifest.schema_version > schema_version {
+            report.errors.push(format!(
+                "备份来自更新的数据库版本{}, 当前数据库版本为{}",
+                backup.manifest.schema_version, schema_version
+            ));
+        }
+        if !self.backup.is_empty().await? || !self.storage.list_ids().await?.is_empty() {
+            report
+                .errors
+                .push("只能恢复到没有文章和评论的空站点".to_string());
+        }
+
+        let mut asset_ids = HashSet::new();
+        let mut restored_assets = Vec::with_capacity(backup.assets.len());
+        for asset in backup.assets {
+            let id = asset.asset.id;
+            if !asset_ids.insert(id) {
+                report.errors.push(format!("资源{}重复", id));
+            } else if format!("{:x}", Sha256::digest(&asset.content)) != asset.asset.hash {
+                report.errors.push(format!("资源{}的文件与哈希不一致", id));
+            } else {
+                restored_assets.push(asset);
+            }
+        }
+
+        let mut ids = HashSet::new();
+        let mut slugs = HashSet::new();
+        let mut creates = Vec::with_capacity(backup.posts.len());
+        for post in backup.posts {
+            let id = post.id;
+            if !ids.insert(id) {
+                report.errors.push(format!("文章{}重复", id));
+                continue;
+            }
+            match Self::restore_create(post) {
+                Ok(create) => {
+                    if let Some(slug) = &create.slug
+                        && !slugs.insert(slug.clone())
+                    {
+                        report
+                            .errors
+                            .push(format!("文章{}的slug`{}`与其他文章重复", id, slug));
+                    }
+                    creates.push((id, create));
+                }
+                Err(e) => report.errors.push(format!("文章{}: {}", id, e)),
+            }
+        }
+
+        let mut states = HashMap::new();
+        for state in backup.states {
+            if ids.contains(&state.id) {
+                states.insert(state.id, state);
+            } else {
+                report
+                    .warnings
+                    .push(format!("跳过了文章{}的状态, 备份中没有该文章", state.id));
+            }
+        }
+        let mut aliases: HashMap<i32, Vec<String>> = HashMap::new();
+        for alias in backup.aliases {
+            if ids.contains(&alias.post_id) {
+                aliases.entry(alias.post_id).or_default().push(alias.path);
+            } else {
+                report.warnings.push(format!(
+                    "跳过了旧地址`{}`, 备份中没有文章{}",
+                    alias.path, alias.post_id
+                ));
+            }
+        }
+        let mut comments: HashMap<i32, Vec<ImportComment>> = HashMap::new();
+        let mut skipped = 0;
+        for comment in backup.comments {
+            if ids.contains(&comment.post_id) {
+                comments
+                    .entry(comment.post_id)
+                    .or_default()
+                    .push(ImportComment {
+                        id: comment.id.into(),
+                        parent: comment.parent_id.map(i64::from),
+                        author: comment.author,
+                        content: comment.content,
+                        created_at: Some(comment.created_at),
+                    });
+            } else {
+                skipped += 1;
+            }
+        }
+        if skipped > 0 {
+            report
+                .warnings
+                .push(format!("跳过了{}条文章不在备份中的评论", skipped));
+        }
+
+        if dry_run || !report.is_ok() {
+            report.posts = creates.len();
+            report.aliases = aliases.values().map(Vec::len).sum();
+            report.comments = comments.values().map(Vec::len).sum();
+            report.assets = restored_assets.len();
+            event!(
+                Level::INFO,
+                error_count = report.errors.len(),
+                "完成备份检查, 没有写入任何内容"
+            );
+            return Ok(report);
+        }
+
+        // 先恢复资源, 创建文章时才能关联到文章引用的资源
+        report.assets = assets.restore_assets(restored_assets).await?;
+        for (id, create) in creates {
+            let new = match self.create_post(Some(id), None, create).await {
+                Ok(new) => new,
+                Err(e) => {
+                    event!(Level::ERROR, post_id = id, error = %e, "恢复文章失败");
+                    report.errors.push(format!("恢复文章{}失败: {}", id, e));
+                    continue;
+                }
+            };
+            report.posts += 1;
+            if let Some(state) = states.remove(&id)
+                && let Err(e) = self.backup.set_post_state(&state).await
+            {
+                event!(Level::WARN, post_id = id, error = %e, "恢复文章状态失败");
+                report
+                    .warnings
+                    .push(format!("恢复文章{}的访问计数和回收站状态失败: {}", id, e));
+            }
+            if let Some(paths) = aliases.remove(&id) {
+                match self.alias.add(id, &paths).await {
+                    Ok(conflicts) => report.aliases += paths.len() - conflicts.len(),
+                    Err(e) => report
+                        .warnings
+                        .push(format!("恢复文章{}的旧地址失败: {}", id, e)),
+                }
+            }
+            let post_comments = comments.remove(&id).unwrap_or_default();
+            report.comments += self
+                .import_comments(&new, post_comments, &mut report.warnings)
+                .await;
+        }
+        self.backup.reset_post_sequence().await?;
+
+        event!(
+            Level::INFO,
+            post_count = report.posts,
+            comment_count = report.comments,
+            asset_count = report.assets,
+            error_count = report.errors.len(),
+            "完成从备份恢复"
+        );
+        Ok(report)
+    }
+
+    /// 由备份中的front matter生成文章, 备份中的文章一定有标题
+    fn restore_create(post: BackupPost) -> Result<PostCreate, ServiceError> {
+        let front_matter = MARKDOWN_UTIL.parse(&post.content)?.front_matter;
+        let status = front_matter
+            .status
+            .as_deref()
+            .map(str::parse)
+            .transpose()
+            .map_err(ServiceError::BadArugment)?;
+        let Some(title) = front_matter.title.filter(|title| !title.trim().is_empty()) else {
+            return Err(ServiceError::BadArugment("缺少标题".to_string()));
+        };
+        Ok(PostCreate {
+            title,
+            slug: front_matter.slug,
+            tags: front_matter.tags.unwrap_or_default(),
+            content: post.content,
+            date: front_matter.date,
+            updated: front_matter.updated,
+            summary: front_matter.summary,
+            status,
+            publish_at: front_matter.publish_at,
+            author: None,
+            message: Some("从备份恢复".to_string()),
+        })
+    }
+}
@@ -0,0 +1,112 @@
+use super::PostService;
+use crate::models::post::{ConsistencyReport, MissingFile, OrphanFile, Repair};
+use crate::repositories::ReponsitoryError;
+use crate::repositories::post::PostMeta;
+use crate::service::ServiceError;
+use crate::storage::{Change, ChangeNote};
+use std::collections::HashSet;
+use tracing::{Level, event, instrument};
+
+impl PostService {
+    /// 检查数据库中的文章(包括回收站中的文章)和文章文件是否一一对应,
+    /// `repair`为`true`时同时修复:
+    /// - 没有文件的文章使用最新的版本记录恢复文件, 没有版本记录时移入回收站,
+    ///   由管理员决定是否永久删除
+    /// - 没有对应文章的文件被移到`orphans/`下
+    #[instrument(name = "PostService::check_consistency", level = "info", skip(self))]
+    pub async fn check_consistency(&self, repair: bool) -> Result<ConsistencyReport, ServiceError> {
+        event!(Level::INFO, repair = repair, "开始检查文章和文件的一致性");
+
+        let posts = self.post.list_all().await?;
+        let files = self.storage.list_ids().await?;
+        let post_ids: HashSet<i32> = posts.iter().map(|post| post.id).collect();
+        let file_ids: HashSet<i32> = files.iter().copied().collect();
+
+        let mut report = ConsistencyReport::default();
+        for post in posts.iter().filter(|post| !file_ids.contains(&post.id)) {
+            event!(Level::WARN, post_id = post.id, title = %post.title, "文章文件不存在");
+            let repair = if repair {
+                Some(Self::or_failed(self.repair_missing(post).await))
+            } else {
+                None
+            };
+            report.missing_files.push(MissingFile {
+                id: post.id,
+                title: post.title.clone(),
+                repair,
+            });
+        }
+        for id in files.into_iter().filter(|id| !post_ids.contains(id)) {
+            event!(Level::WARN, post_id = id, "文件没有对应的文章");
+            let repair = if repair {
+                Some(Self::or_failed(self.quarantine_orphan(id).await))
+            } else {
+                None
+            };
+            report.orphan_files.push(OrphanFile { id, repair });
+        }
+
+        event!(
+            Level::INFO,
+            missing_count = report.missing_files.len(),
+            orphan_count = report.orphan_files.len(),
+            "完成一致性检查"
+        );
+        Ok(report)
+    }
+
+    fn or_failed(result: Result<Repair, ServiceError>) -> Repair {
+        result.unwrap_or_else(|e| Repair::Failed {
+            error: e.to_string(),
+        })
+    }
+
+    async fn repair_missing(&self, post: &PostMeta) -> Result<Repair, ServiceError> {
+        let Some(latest) = self.revision.list(post.id).await?.into_iter().next() else {
+            // 已经在回收站中的文章保持原样
+            match self.post.trash(post.id).await {
+                Ok(_) | Err(ReponsitoryError::NotFound) => {}
+                Err(e) => return Err(e.into()),
+            }
+            self.invalidate_rendered(post.id);
+            event!(Level::INFO, post_id = post.id, title = %post.title, "没有可以恢复的版本, 将文章移入回收站");
+            return Ok(Repair::Trashed);
+        };
+
+        let revision = self.revision.find(post.id, latest.revision).await?;
+        let message = format!("从版本{}恢复丢失的文件", latest.revision);
+        let note = ChangeNote {
+            change: Change::Add,
+            id: post.id,
+            title: &post.title,
+            author: None,
+            message: Some(&message),
+        };
+        self.storage
+            .create(post.id, revision.content.into_bytes(), &note)
+            .await?;
+        self.invalidate_rendered(post.id);
+        event!(
+            Level::INFO,
+            post_id = post.id,
+            revision = latest.revision,
+            "成功从版本记录恢复文件"
+        );
+        Ok(Repair::Restored {
+            revision: latest.revision,
+        })
+    }
+
+    async fn quarantine_orphan(&self, id: i32) -> Result<Repair, ServiceError> {
+        let note = ChangeNote {
+            change: Change::Quarantine,
+            id,
+            title: "没有对应文章的文件",
+            author: None,
+            message: None,
+        };
+        let key = self.storage.quarantine(id, &note).await?;
+        event!(Level::INFO, post_id = id, key = %key, "成功隔离文件");
+        Ok(Repair::Quarantined { key })
+    }
+}
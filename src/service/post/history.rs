@@ -0,0 +1,203 @@
+use super::PostService;
+use crate::models::post::{PostUpdate, RevisionDiff};
+use crate::repositories::post::PostMeta;
+use crate::repositories::revision::{Revision, RevisionContent, RevisionCreate};
+use crate::service::{Principal, ServiceError};
+use sha2::{Digest, Sha256};
+use similar::TextDiff;
+use tracing::{Level, event, instrument};
+
+impl PostService {
+    /// 记录文章的一个版本, 失败时文章的修改仍然有效, 因此只记录日志
+    pub(super) async fn record_revision(
+        &self,
+        post: &PostMeta,
+        content: String,
+        author: Option<String>,
+        message: Option<String>,
+    ) {
+        let revision = RevisionCreate {
+            post_id: post.id,
+            title: post.title.clone(),
+            slug: post.slug.clone(),
+            tags: post.tags.0.clone(),
+            content_hash: Self::content_hash(content.as_bytes()),
+            content,
+            author,
+            message,
+        };
+        match self.revision.add(revision).await {
+            Ok(revision) => {
+                event!(
+                    Level::INFO,
+                    post_id = post.id,
+                    revision = revision.revision,
+                    "成功记录文章版本"
+                )
+            }
+            Err(e) => event!(Level::WARN, post_id = post.id, error = %e, "记录文章版本失败"),
+        }
+    }
+
+    /// 文章内容的sha256, 用于比较版本和导入时去重
+    pub(super) fn content_hash(content: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(content))
+    }
+
+    /// 将修改前的文章保存为第一个版本
+    pub(super) async fn record_legacy_revision(&self, post: &PostMeta) {
+        match self.storage.read(post.id).await {
+            Ok(content) => {
+                let content = String::from_utf8_lossy(&content).into_owned();
+                self.record_revision(post, content, None, Some("初始版本".to_string()))
+                    .await
+            }
+            Err(e) => {
+                event!(Level::WARN, post_id = post.id, error = %e, "读取修改前的文章失败")
+            }
+        }
+    }
+
+    /// 历史版本包含文章的完整内容, 能够修改文章的用户可以查看所有文章的历史版本,
+    /// 其他人只能查看公开的文章; 同时确认文章存在, 否则无法区分文章不存在和没有历史版本
+    async fn require_history_access(
+        &self,
+        principal: Option<&Principal>,
+        id: i32,
+    ) -> Result<(), ServiceError> {
+        if let Some(principal) = principal {
+            let author_id = self.post.find_author(id).await?;
+            if principal.can_edit_post(author_id) {
+                return Ok(());
+            }
+        }
+        let post = self.post.find_by_id(id).await?;
+        if !post.status.is_reachable() {
+            event!(Level::INFO, post_id = id, status = ?post.status, "文章尚未公开");
+            return Err(ServiceError::NotFound);
+        }
+        Ok(())
+    }
+
+    #[instrument(
+        name = "PostService::list_revisions",
+        level = "info",
+        skip(self, principal)
+    )]
+    pub async fn list_revisions(
+        &self,
+        principal: Option<&Principal>,
+        id: i32,
+    ) -> Result<Vec<Revision>, ServiceError> {
+        event!(Level::INFO, post_id = id, "开始查询文章版本");
+        self.require_history_access(principal, id).await?;
+        let revisions = self.revision.list(id).await?;
+        event!(
+            Level::INFO,
+            post_id = id,
+            revision_count = revisions.len(),
+            "成功查询文章版本"
+        );
+        Ok(revisions)
+    }
+
+    #[instrument(
+        name = "PostService::read_revision",
+        level = "info",
+        skip(self, principal)
+    )]
+    pub async fn read_revision(
+        &self,
+        principal: Option<&Principal>,
+        id: i32,
+        revision: i32,
+    ) -> Result<RevisionContent, ServiceError> {
+        self.require_history_access(principal, id).await?;
+        Ok(self.revision.find(id, revision).await?)
+    }
+
+    /// 生成两个版本之间的unified diff, 标题, slug和标签的变化放在文件内容之前
+    #[instrument(
+        name = "PostService::diff_revisions",
+        level = "info",
+        skip(self, principal)
+    )]
+    pub async fn diff_revisions(
+        &self,
+        principal: Option<&Principal>,
+        id: i32,
+        from: i32,
+        to: i32,
+    ) -> Result<RevisionDiff, ServiceError> {
+        event!(
+            Level::INFO,
+            post_id = id,
+            from = from,
+            to = to,
+            "开始比较文章版本"
+        );
+        self.require_history_access(principal, id).await?;
+        let (old, new) =
+            tokio::try_join!(self.revision.find(id, from), self.revision.find(id, to))?;
+        let (old, new) = (Self::revision_text(&old), Self::revision_text(&new));
+        let diff = TextDiff::from_lines(&old, &new)
+            .unified_diff()
+            .context_radius(3)
+            .header(&format!("revision {}", from), &format!("revision {}", to))
+            .to_string();
+        event!(
+            Level::INFO,
+            post_id = id,
+            from = from,
+            to = to,
+            "成功比较文章版本"
+        );
+        Ok(RevisionDiff { from, to, diff })
+    }
+
+    /// 用历史版本的内容和元数据覆盖当前文章, 回滚本身也会记录为一个新的版本
+    #[instrument(name = "PostService::rollback", level = "info", skip(self, principal), fields(username = %principal.username))]
+    pub async fn rollback(
+        &self,
+        principal: &Principal,
+        id: i32,
+        revision: i32,
+        author: Option<String>,
+        message: Option<String>,
+    ) -> Result<PostMeta, ServiceError> {
+        event!(
+            Level::INFO,
+            post_id = id,
+            revision = revision,
+            "开始回滚文章"
+        );
+        let RevisionContent { meta, content } = self.revision.find(id, revision).await?;
+        let update = PostUpdate {
+            title: Some(meta.title),
+            slug: Some(meta.slug),
+            tags: Some(meta.tags.0),
+            content: Some(content.into_bytes()),
+            author,
+            message: message.or_else(|| Some(format!("回滚到版本{}", revision))),
+            ..Default::default()
+        };
+        let post = self.update_one(principal, id, update).await?;
+        event!(
+            Level::INFO,
+            post_id = id,
+            revision = revision,
+            "成功回滚文章"
+        );
+        Ok(post)
+    }
+
+    fn revision_text(revision: &RevisionContent) -> String {
+        format!(
+            "title: {}\nslug: {}\ntags: {}\n\n{}",
+            revision.meta.title,
+            revision.meta.slug,
+            revision.meta.tags.0.join(", "),
+            revision.content
+        )
+    }
+}
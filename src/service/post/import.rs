@@ -0,0 +1,236 @@
+use super::PostService;
+use crate::import::{self, ImportComment, ImportFile, ImportFormat, ImportPost, MAX_FILE_SIZE};
+use crate::models::import::{ImportOutcome, ImportReport};
+use crate::repositories::comment::CommentCreate;
+use crate::repositories::post::PostMeta;
+use crate::repositories::user::Role;
+use crate::service::{Principal, ServiceError};
+use crate::util::normalize_path;
+use std::collections::{HashMap, HashSet};
+use tokio::task;
+use tracing::{Level, event, instrument};
+
+/// 导入的文章最多保留的标签数量, 与上传接口的限制相同
+const MAX_TAGS: usize = 10;
+
+impl PostService {
+    /// 按照`format`逐个导入文件中的文章, 单篇文章失败不影响其他文章
+    /// - front matter中的发布和修改时间作为首次发布和最后修改时间
+    /// - 内容与已有文章的任意版本或同一批中之前的文章相同时跳过
+    /// - 文章原来的地址保存为别名, WXR中的评论保留回复关系一起导入
+    ///
+    /// 需要编辑权限, 导入的文章不属于任何用户
+    #[instrument(name = "PostService::import", level = "info", skip_all, fields(file_count = files.len(), format = ?format, username = %principal.username))]
+    pub async fn import(
+        &self,
+        principal: &Principal,
+        files: Vec<ImportFile>,
+        format: ImportFormat,
+    ) -> Result<ImportReport, ServiceError> {
+        principal.require(Role::Editor)?;
+        event!(Level::INFO, file_count = files.len(), format = ?format, "开始批量导入文章");
+
+        // 内容哈希 -> 同一批中第一篇导入成功的文章
+        let mut seen: HashMap<String, String> = HashMap::new();
+        let mut report = ImportReport::default();
+        for file in files {
+            let path = file.path.clone();
+            let parsed = match task::spawn_blocking(move || import::parse(format, file)).await {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    event!(Level::ERROR, path = %path, error = %e, "解析文件失败");
+                    report.push(
+                        path,
+                        ImportOutcome::Failed {
+                            error: e.to_string(),
+                        },
+                    );
+                    continue;
+                }
+            };
+            for parsed in parsed {
+                let post = match parsed {
+                    Ok(post) => post,
+                    Err(failure) => {
+                        event!(Level::WARN, source = %failure.source, error = %failure.error, "解析文章失败");
+                        report.push(
+                            failure.source,
+                            ImportOutcome::Failed {
+                                error: failure.error,
+                            },
+                        );
+                        continue;
+                    }
+                };
+                let source = post.source.clone();
+                let hash = Self::content_hash(&post.post.content);
+                let outcome = match seen.get(&hash) {
+                    Some(path) => Ok(ImportOutcome::Duplicate {
+                        post_id: None,
+                        path: Some(path.clone()),
+                    }),
+                    None => self.import_one(post, &hash).await,
+                };
+                let outcome = outcome.unwrap_or_else(|e| {
+                    event!(Level::WARN, source = %source, error = %e, "导入文章失败");
+                    ImportOutcome::Failed {
+                        error: e.to_string(),
+                    }
+                });
+                if let ImportOutcome::Imported { .. } = outcome {
+                    seen.insert(hash, source.clone());
+                }
+                report.push(source, outcome);
+            }
+        }
+
+        event!(
+            Level::INFO,
+            imported = report.imported,
+            duplicated = report.duplicated,
+            failed = report.failed,
+            "完成批量导入文章"
+        );
+        Ok(report)
+    }
+
+    async fn import_one(
+        &self,
+        post: ImportPost,
+        hash: &str,
+    ) -> Result<ImportOutcome, ServiceError> {
+        let ImportPost {
+            source,
+            post: mut create,
+            aliases,
+            comments,
+            mut warnings,
+        } = post;
+        if let Some(post_id) = self.revision.find_post_by_hash(hash).await? {
+            event!(Level::INFO, source = %source, post_id = post_id, "文章已经存在");
+            return Ok(ImportOutcome::Duplicate {
+                post_id: Some(post_id),
+                path: None,
+            });
+        }
+
+        // 校验规则与上传接口相同, 其他博客程序的标签和分类合并后可能超过限制, 只保留前10个
+        if create.content.len() > MAX_FILE_SIZE {
+            return Err(ServiceError::BadArugment("内容长度不能超过10M".to_string()));
+        }
+        if create.title.len() > 255 {
+            return Err(ServiceError::BadArugment("标题长度不能超过255".to_string()));
+        }
+        if create.tags.len() > MAX_TAGS {
+            let dropped = create.tags.split_off(MAX_TAGS);
+            warnings.push(format!(
+                "标签超过{}个, 忽略了: {}",
+                MAX_TAGS,
+                dropped.join(", ")
+            ));
+        }
+
+        let new = self.create_post(None, None, create).await?;
+        event!(Level::INFO, source = %source, post_id = new.id, "成功导入文章");
+
+        self.import_aliases(&new, aliases, &mut warnings).await;
+        let comments = self.import_comments(&new, comments, &mut warnings).await;
+        Ok(ImportOutcome::Imported {
+            id: new.id,
+            title: new.title,
+            comments,
+            warnings,
+        })
+    }
+
+    /// 保存文章原来的地址, 无效或已经被其他文章使用的地址记录为警告
+    pub(super) async fn import_aliases(
+        &self,
+        post: &PostMeta,
+        aliases: Vec<String>,
+        warnings: &mut Vec<String>,
+    ) {
+        let mut paths: Vec<String> = Vec::new();
+        for alias in aliases {
+            match normalize_path(&alias) {
+                Some(path) if !paths.contains(&path) => paths.push(path),
+                Some(_) => {}
+                None => warnings.push(format!("忽略了无效的旧地址: `{}`", alias)),
+            }
+        }
+        if paths.is_empty() {
+            return;
+        }
+        match self.alias.add(post.id, &paths).await {
+            Ok(conflicts) => {
+                for path in conflicts {
+                    warnings.push(format!("旧地址`{}`已经被其他文章使用", path));
+                }
+            }
+            Err(e) => {
+                event!(Level::WARN, post_id = post.id, error = %e, "保存文章旧地址失败");
+                warnings.push(format!("保存旧地址失败: {}", e));
+            }
+        }
+    }
+
+    /// 按照先父评论后回复的顺序导入评论, 返回导入的数量;
+    /// 父评论不存在(如未通过审核)的回复作为顶层评论导入
+    pub(super) async fn import_comments(
+        &self,
+        post: &PostMeta,
+        comments: Vec<ImportComment>,
+        warnings: &mut Vec<String>,
+    ) -> usize {
+        let ids: HashSet<i64> = comments.iter().map(|comment| comment.id).collect();
+        // 原来的评论id -> 导入后的评论id
+        let mut imported: HashMap<i64, i32> = HashMap::new();
+        let mut failed = 0;
+        let mut pending = comments;
+        while !pending.is_empty() {
+            let (ready, waiting): (Vec<_>, Vec<_>) = pending.into_iter().partition(|comment| {
+                comment
+                    .parent
+                    .is_none_or(|parent| !ids.contains(&parent) || imported.contains_key(&parent))
+            });
+            // 循环引用的评论无法确定顺序, 全部作为顶层评论
+            let (ready, waiting) = if ready.is_empty() {
+                (waiting, Vec::new())
+            } else {
+                (ready, waiting)
+            };
+            for comment in ready {
+                let create = CommentCreate {
+                    post_id: post.id,
+                    author: comment.author,
+                    content: comment.content,
+                    parent_id: comment
+                        .parent
+                        .and_then(|parent| imported.get(&parent).copied()),
+                    created_at: comment.created_at,
+                    user_id: None,
+                };
+                match self.comment.create(create).await {
+                    Ok(new) => {
+                        imported.insert(comment.id, new.id);
+                    }
+                    Err(e) => {
+                        event!(Level::WARN, post_id = post.id, comment_id = comment.id, error = %e, "导入评论失败");
+                        failed += 1;
+                    }
+                }
+            }
+            pending = waiting;
+        }
+        if failed > 0 {
+            warnings.push(format!("{}条评论导入失败", failed));
+        }
+        event!(
+            Level::INFO,
+            post_id = post.id,
+            comment_count = imported.len(),
+            "完成导入评论"
+        );
+        imported.len()
+    }
+}
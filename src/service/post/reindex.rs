@@ -0,0 +1,51 @@
+use super::PostService;
+use crate::models::post::PostReindex;
+use crate::repositories::post::{PostIndexUpdate, PostMeta};
+use crate::repositories::user::Role;
+use crate::service::{Principal, ServiceError};
+use crate::util::MARKDOWN_UTIL;
+use tracing::{Level, event, instrument};
+
+impl PostService {
+    /// 由文章文件重新计算所有文章(包括回收站中的文章)的关键词, 高亮摘要使用的正文,
+    /// 目录, 字数和摘要; 用于补全添加这些字段之前保存的文章, 不修改文章的内容和
+    /// 最后修改时间; 需要管理员权限
+    #[instrument(name = "PostService::reindex", level = "info", skip(self, principal), fields(username = %principal.username))]
+    pub async fn reindex(&self, principal: &Principal) -> Result<Vec<PostReindex>, ServiceError> {
+        principal.require(Role::Admin)?;
+        event!(Level::INFO, "开始重建文章索引");
+
+        let posts = self.post.list_all().await?;
+        let mut results = Vec::with_capacity(posts.len());
+        for post in posts {
+            let outcome = self.reindex_one(&post).await;
+            if let Err(e) = &outcome {
+                event!(Level::WARN, post_id = post.id, error = %e, "重建文章索引失败");
+            }
+            results.push(PostReindex {
+                id: post.id,
+                title: post.title,
+                outcome: outcome.map_err(|e| e.to_string()),
+            });
+        }
+
+        event!(Level::INFO, post_count = results.len(), "完成重建文章索引");
+        Ok(results)
+    }
+
+    async fn reindex_one(&self, post: &PostMeta) -> Result<(), ServiceError> {
+        let content = self.storage.read(post.id).await?;
+        let parsed = MARKDOWN_UTIL.parse(&content)?;
+        let body = parsed.body;
+        let text = MARKDOWN_UTIL.to_plain_text(body);
+        let update = PostIndexUpdate {
+            id: post.id,
+            kw: self.keywords(&post.title, &text).await,
+            toc: MARKDOWN_UTIL.toc(body),
+            stats: MARKDOWN_UTIL.stats(&text),
+            excerpt: Self::excerpt(parsed.front_matter.summary, body),
+        };
+        self.post.reindex(update).await?;
+        Ok(())
+    }
+}
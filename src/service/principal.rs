@@ -0,0 +1,77 @@
+use crate::repositories::user::Role;
+use crate::service::ServiceError;
+use tracing::{Level, event};
+
+/// 发起操作的用户, 路由从令牌中解析后传给各个服务, 权限检查都通过这里进行
+#[derive(Debug, Clone)]
+pub struct Principal {
+    /// 为空时表示命令行等可信的内部调用, 不对应任何用户
+    pub id: Option<i32>,
+    pub username: String,
+    pub role: Role,
+    /// 令牌所属的登录会话
+    pub session_id: Option<i32>,
+}
+
+impl Principal {
+    /// 命令行使用的身份, 拥有管理员权限
+    pub fn system() -> Self {
+        Principal {
+            id: None,
+            username: "system".to_string(),
+            role: Role::Admin,
+            session_id: None,
+        }
+    }
+
+    /// 记录在文章历史中的提交者, 内部调用时为空
+    pub fn committer(&self) -> Option<String> {
+        self.id.map(|_| self.username.clone())
+    }
+
+    /// 要求角色不低于`role`
+    pub fn require(&self, role: Role) -> Result<(), ServiceError> {
+        if self.role >= role {
+            return Ok(());
+        }
+        event!(Level::WARN, username = %self.username, role = %self.role, required = %role, "权限不足");
+        Err(ServiceError::Forbidden(format!("需要{}权限", role)))
+    }
+
+    /// 编辑和管理员可以修改所有文章, 作者只能修改自己上传的文章
+    pub fn require_post_author(&self, author_id: Option<i32>) -> Result<(), ServiceError> {
+        self.require(Role::Author)?;
+        if self.can_edit_post(author_id) {
+            return Ok(());
+        }
+        event!(Level::WARN, username = %self.username, author_id = ?author_id, "不是文章的作者");
+        Err(ServiceError::Forbidden("只能修改自己的文章".to_string()))
+    }
+
+    /// 同[`Principal::require_post_author`], 但只返回结果, 不记录日志
+    pub fn can_edit_post(&self, author_id: Option<i32>) -> bool {
+        self.role >= Role::Editor || (self.role >= Role::Author && self.owns(author_id))
+    }
+
+    /// 编辑和管理员可以管理所有评论, 其他用户只能修改自己发表的评论
+    pub fn require_comment_owner(&self, user_id: Option<i32>) -> Result<(), ServiceError> {
+        if self.role >= Role::Editor || self.owns(user_id) {
+            return Ok(());
+        }
+        event!(Level::WARN, username = %self.username, user_id = ?user_id, "不是评论的发表者");
+        Err(ServiceError::Forbidden("只能修改自己的评论".to_string()))
+    }
+
+    /// 用户可以管理自己的会话, 管理员可以管理所有会话
+    pub fn require_session_owner(&self, user_id: i32) -> Result<(), ServiceError> {
+        if self.role >= Role::Admin || self.id == Some(user_id) {
+            return Ok(());
+        }
+        event!(Level::WARN, username = %self.username, user_id = user_id, "不是会话的所有者");
+        Err(ServiceError::Forbidden("只能管理自己的会话".to_string()))
+    }
+
+    fn owns(&self, owner_id: Option<i32>) -> bool {
+        owner_id.is_some() && owner_id == self.id
+    }
+}
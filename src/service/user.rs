@@ -0,0 +1,181 @@
+use crate::models::user::{UserCreate, UserUpdate as ModelUserUpdate};
+use crate::repositories::user::{Role, SqlxReponsitory, User, UserReponsitory, UserUpdate};
+use crate::service::{Principal, ServiceError};
+use argon2::Argon2;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use sqlx::PgPool;
+use tokio::task;
+use tracing::{Level, event, instrument};
+
+/// 用户名的最大长度
+const MAX_USERNAME_LEN: usize = 64;
+/// 密码的最小长度
+const MIN_PASSWORD_LEN: usize = 8;
+
+/// 用户管理, 除读取自己的信息和命令行使用的`set_user`之外都只有管理员可以调用
+pub struct UserService {
+    user: Box<dyn UserReponsitory>,
+}
+
+impl UserService {
+    pub fn new(pool: PgPool) -> Self {
+        tracing::info!("创建UserService实例成功");
+        UserService {
+            user: Box::new(SqlxReponsitory::new(pool)),
+        }
+    }
+
+    #[instrument(name = "UserService::read_one", skip(self))]
+    pub async fn read_one(&self, id: i32) -> Result<User, ServiceError> {
+        Ok(self.user.find_by_id(id).await?)
+    }
+
+    #[instrument(name = "UserService::list", skip_all, fields(username = %principal.username))]
+    pub async fn list(&self, principal: &Principal) -> Result<Vec<User>, ServiceError> {
+        principal.require(Role::Admin)?;
+        let users = self.user.list().await?;
+        event!(Level::INFO, user_count = users.len(), "成功查询用户列表");
+        Ok(users)
+    }
+
+    #[instrument(name = "UserService::create", skip_all, fields(username = %principal.username))]
+    pub async fn create(
+        &self,
+        principal: &Principal,
+        user: UserCreate,
+    ) -> Result<User, ServiceError> {
+        principal.require(Role::Admin)?;
+        event!(Level::INFO, new_username = %user.username, role = %user.role, "开始创建用户");
+
+        let username = validate_username(&user.username)?;
+        let hash = hash_password(user.password).await?;
+        let Some(new) = self.user.add(username, &hash, user.role).await? else {
+            event!(Level::WARN, new_username = %username, "用户名已存在");
+            return Err(ServiceError::BadArugment("用户名已存在".to_string()));
+        };
+
+        event!(Level::INFO, user_id = new.id, "成功创建用户");
+        Ok(new)
+    }
+
+    /// 修改用户的密码或角色, 管理员不能修改自己的角色, 避免没有管理员可以登录
+    #[instrument(name = "UserService::update", skip(self, principal, update), fields(username = %principal.username))]
+    pub async fn update(
+        &self,
+        principal: &Principal,
+        id: i32,
+        update: ModelUserUpdate,
+    ) -> Result<User, ServiceError> {
+        principal.require(Role::Admin)?;
+        event!(Level::INFO, user_id = id, role = ?update.role, "开始更新用户");
+
+        if principal.id == Some(id) && update.role.is_some_and(|role| role != Role::Admin) {
+            event!(Level::WARN, user_id = id, "不能修改自己的角色");
+            return Err(ServiceError::BadArugment("不能修改自己的角色".to_string()));
+        }
+        let password_hash = match update.password {
+            Some(password) => Some(hash_password(password).await?),
+            None => None,
+        };
+        let user = self
+            .user
+            .update(UserUpdate {
+                id,
+                password_hash,
+                role: update.role,
+            })
+            .await?;
+
+        event!(Level::INFO, user_id = id, "成功更新用户");
+        Ok(user)
+    }
+
+    /// 删除用户, 管理员不能删除自己
+    #[instrument(name = "UserService::delete", skip(self, principal), fields(username = %principal.username))]
+    pub async fn delete(&self, principal: &Principal, id: i32) -> Result<User, ServiceError> {
+        principal.require(Role::Admin)?;
+        event!(Level::INFO, user_id = id, "开始删除用户");
+
+        if principal.id == Some(id) {
+            event!(Level::WARN, user_id = id, "不能删除自己");
+            return Err(ServiceError::BadArugment("不能删除自己".to_string()));
+        }
+        let user = self.user.delete(id).await?;
+
+        event!(Level::INFO, user_id = id, "成功删除用户");
+        Ok(user)
+    }
+
+    /// 添加用户或修改已有用户的密码和角色, 供命令行使用
+    #[instrument(name = "UserService::set_user", skip(self, password))]
+    pub async fn set_user(
+        &self,
+        username: &str,
+        password: String,
+        role: Role,
+    ) -> Result<User, ServiceError> {
+        event!(Level::INFO, "开始设置用户");
+
+        let username = validate_username(username)?;
+        let hash = hash_password(password).await?;
+        let user = self.user.upsert(username, &hash, role).await?;
+
+        event!(Level::INFO, user_id = user.id, "成功设置用户");
+        Ok(user)
+    }
+}
+
+fn validate_username(username: &str) -> Result<&str, ServiceError> {
+    let username = username.trim();
+    if username.is_empty() || username.chars().count() > MAX_USERNAME_LEN {
+        event!(Level::WARN, "用户名长度无效");
+        return Err(ServiceError::BadArugment(format!(
+            "用户名长度必须在1-{}之间",
+            MAX_USERNAME_LEN
+        )));
+    }
+    Ok(username)
+}
+
+/// 在阻塞线程中计算密码的哈希
+async fn hash_password(password: String) -> Result<String, ServiceError> {
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        event!(Level::WARN, "密码过短");
+        return Err(ServiceError::BadArugment(format!(
+            "密码长度不能小于{}",
+            MIN_PASSWORD_LEN
+        )));
+    }
+    task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|e| ServiceError::InternalError(e.to_string()))?;
+        Ok(hash.to_string())
+    })
+    .await
+    .map_err(|e| ServiceError::InternalError(e.to_string()))?
+}
+
+/// 在阻塞线程中校验密码
+pub(super) async fn verify_password(password: String, hash: String) -> Result<bool, ServiceError> {
+    task::spawn_blocking(move || {
+        let hash =
+            PasswordHash::new(&hash).map_err(|e| ServiceError::InternalError(e.to_string()))?;
+        Ok(Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok())
+    })
+    .await
+    .map_err(|e| ServiceError::InternalError(e.to_string()))?
+}
+
+/// 用户名不存在时用于校验的哈希, 使校验时间与用户存在时相同
+pub(super) fn dummy_hash() -> String {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(salt.as_str().as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .expect("生成密码哈希失败")
+}
@@ -1,6 +1,8 @@
 use crate::config::AppConfig;
 use crate::database::init_db;
-use crate::service::{CommentService, PostService};
+use crate::embedding;
+use crate::service::{AssetService, AuthService, CommentService, PostService, UserService};
+use crate::storage;
 use std::ops::Deref;
 use std::sync::Arc;
 use tracing::info;
@@ -8,20 +10,39 @@ pub struct Inner {
     pub config: AppConfig,
     pub post_service: PostService,
     pub comment_service: CommentService,
+    pub asset_service: AssetService,
+    pub auth_service: AuthService,
+    pub user_service: UserService,
 }
 impl Inner {
     pub async fn new(config: AppConfig) -> Self {
         let url = config.get_database_url();
         info!("使用`{}`连接数据库", url);
         let pool = init_db(&config).await;
-        let post_service = PostService::new(pool.clone(), config.get_save_dir());
+        let embedder = embedding::from_config(config.get_embedding());
+        let storage = storage::from_config(config.get_storage(), config.get_save_dir());
+        let post_service =
+            PostService::new(pool.clone(), storage, embedder, config.get_search().clone());
+        if let Err(e) = post_service.migrate_legacy_files().await {
+            panic!("迁移旧的文章文件失败: {}", e)
+        }
         let comment_service = CommentService::new(pool.clone());
+        let asset_store = storage::asset_store(config.get_storage(), config.get_asset());
+        let asset_service = AssetService::new(pool.clone(), asset_store, config.get_asset());
+        let auth_service = AuthService::new(pool.clone(), &config);
+        if let Err(e) = auth_service.load_denylist().await {
+            panic!("加载已撤销的会话失败: {}", e)
+        }
+        let user_service = UserService::new(pool.clone());
 
         info!("初始化分词器");
         Inner {
             config,
             post_service: post_service,
             comment_service: comment_service,
+            asset_service,
+            auth_service,
+            user_service,
         }
     }
 }
@@ -0,0 +1,257 @@
+mod content;
+mod fs;
+mod git;
+mod memory;
+mod s3;
+use crate::config::{AssetConfig, S3Config, StorageBackend, StorageConfig};
+use async_trait::async_trait;
+use bytes::Bytes;
+pub use content::ContentStorage;
+pub use fs::FsStore;
+use futures_util::Stream;
+pub use git::GitStorage;
+pub use memory::MemoryStore;
+pub use s3::S3Store;
+use std::fmt;
+use std::io;
+use std::path::{Component, Path};
+use std::pin::Pin;
+use std::sync::Arc;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("IO Error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Not Found: {0}")]
+    NotFound(String),
+    #[error("Already Exists: {0}")]
+    AlreadyExists(String),
+    #[error("Invalid Key: {0}")]
+    InvalidKey(String),
+    #[error("Remote Error: {0}")]
+    Remote(String),
+    #[error("Git Error: {0}")]
+    Git(String),
+    #[error("Internal Error: {0}")]
+    Internal(String),
+}
+
+/// 按块读取的文件内容
+pub type ByteStream = Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send>>;
+
+/// 以字符串为键的文件存储, 键使用`/`分隔的相对路径
+#[async_trait]
+pub trait ContentStore: Send + Sync {
+    /// 写入文件, 已经存在时覆盖, 读取者不会看到写了一半的文件
+    async fn put(&self, key: &str, data: Bytes) -> Result<(), StorageError>;
+    async fn get(&self, key: &str) -> Result<Bytes, StorageError>;
+    /// 流式读取文件, 适合直接作为响应体返回
+    async fn get_stream(&self, key: &str) -> Result<ByteStream, StorageError>;
+    async fn exists(&self, key: &str) -> Result<bool, StorageError>;
+    /// 删除文件, 文件本来就不存在时返回`false`
+    async fn delete(&self, key: &str) -> Result<bool, StorageError>;
+    async fn rename(&self, from: &str, to: &str) -> Result<(), StorageError>;
+    /// 按字典序列出以`prefix`开头的所有键
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, StorageError>;
+}
+
+/// 键必须是不包含`..`的相对路径
+pub fn check_key(key: &str) -> Result<(), StorageError> {
+    let valid = !key.is_empty()
+        && !key.ends_with('/')
+        && Path::new(key)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)));
+    if valid {
+        Ok(())
+    } else {
+        Err(StorageError::InvalidKey(key.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Change {
+    Add,
+    Update,
+    Delete,
+    /// 旧版本以标题命名的文件被重命名为以id命名
+    Rename,
+    /// 没有对应文章的文件被移到`orphans/`下
+    Quarantine,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let change = match self {
+            Self::Add => "add",
+            Self::Update => "update",
+            Self::Delete => "delete",
+            Self::Rename => "rename",
+            Self::Quarantine => "quarantine",
+        };
+        f.write_str(change)
+    }
+}
+
+/// 一次修改的说明, git存储会用它生成提交信息
+#[derive(Debug, Clone, Copy)]
+pub struct ChangeNote<'a> {
+    pub change: Change,
+    pub id: i32,
+    pub title: &'a str,
+    pub author: Option<&'a str>,
+    pub message: Option<&'a str>,
+}
+
+/// 文章markdown文件的存储, 每篇文章以`{id}.md`保存, 在[`ContentStore`]之上
+/// 提供文章级别的操作, 并可以借助修改说明记录历史(见[`GitStorage`])
+#[async_trait]
+pub trait PostStorage: Send + Sync {
+    /// 写入新的文章, 文件已经存在时返回错误
+    async fn create(
+        &self,
+        id: i32,
+        content: Vec<u8>,
+        note: &ChangeNote<'_>,
+    ) -> Result<(), StorageError>;
+    /// 替换文章内容, 读取者不会看到写了一半的文件
+    async fn replace(
+        &self,
+        id: i32,
+        content: Vec<u8>,
+        note: &ChangeNote<'_>,
+    ) -> Result<(), StorageError>;
+    async fn read(&self, id: i32) -> Result<Vec<u8>, StorageError>;
+    async fn read_stream(&self, id: i32) -> Result<ByteStream, StorageError>;
+    /// 删除文章, 文件本来就不存在时返回`false`
+    async fn remove(&self, id: i32, note: &ChangeNote<'_>) -> Result<bool, StorageError>;
+    /// 将旧版本以标题命名的文件重命名为以id命名, 没有需要迁移的文件时返回`false`
+    async fn migrate_legacy(&self, id: i32, title: &str) -> Result<bool, StorageError>;
+    /// 按id顺序列出所有文章文件的id, 不包括旧版本的文件和`orphans/`下的文件
+    async fn list_ids(&self) -> Result<Vec<i32>, StorageError>;
+    /// 将没有对应文章的文件移到`orphans/`下, 返回移动后的键
+    async fn quarantine(&self, id: i32, note: &ChangeNote<'_>) -> Result<String, StorageError>;
+}
+
+pub fn from_config(config: &StorageConfig, save_dir: &str) -> Arc<dyn PostStorage> {
+    match config.backend {
+        StorageBackend::File => Arc::new(ContentStorage::new(Arc::new(FsStore::new(save_dir)))),
+        StorageBackend::Memory => Arc::new(ContentStorage::new(Arc::new(MemoryStore::new()))),
+        StorageBackend::S3 => {
+            let Some(s3) = &config.s3 else {
+                panic!("使用s3存储时必须配置`storage.s3`")
+            };
+            Arc::new(ContentStorage::new(Arc::new(S3Store::new(s3))))
+        }
+        StorageBackend::Git => {
+            match GitStorage::open(save_dir, &config.git_name, &config.git_email) {
+                Ok(storage) => Arc::new(storage),
+                Err(e) => panic!("无法打开git仓库`{}`: {}", save_dir, e),
+            }
+        }
+    }
+}
+
+/// 图片和附件的存储, 不需要记录修改历史, 因此`git`也直接保存为文件
+pub fn asset_store(config: &StorageConfig, asset: &AssetConfig) -> Arc<dyn ContentStore> {
+    match config.backend {
+        StorageBackend::File | StorageBackend::Git => Arc::new(FsStore::new(&asset.save_dir)),
+        StorageBackend::Memory => Arc::new(MemoryStore::new()),
+        StorageBackend::S3 => {
+            let Some(s3) = &config.s3 else {
+                panic!("使用s3存储时必须配置`storage.s3`")
+            };
+            let s3 = S3Config {
+                prefix: Some(asset.s3_prefix.clone()),
+                ..s3.clone()
+            };
+            Arc::new(S3Store::new(&s3))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::TryStreamExt;
+
+    #[test]
+    fn check_key_rejects_escaping_paths() {
+        for key in ["a.md", "a/b.md", "orphans/1.md"] {
+            assert!(check_key(key).is_ok(), "{}", key);
+        }
+        for key in ["", "/a.md", "../a.md", "a/../b.md", "./a.md", "a/"] {
+            assert!(
+                matches!(check_key(key), Err(StorageError::InvalidKey(_))),
+                "{}",
+                key
+            );
+        }
+    }
+
+    /// 所有[`ContentStore`]实现都应当满足的行为
+    pub async fn round_trip(store: &dyn ContentStore) {
+        store.put("a/b.md", Bytes::from("one")).await.unwrap();
+        assert_eq!(store.get("a/b.md").await.unwrap(), "one");
+        // 已经存在时覆盖
+        store.put("a/b.md", Bytes::from("two")).await.unwrap();
+        assert_eq!(store.get("a/b.md").await.unwrap(), "two");
+        let chunks: Vec<Bytes> = store
+            .get_stream("a/b.md")
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(chunks.concat(), b"two");
+        assert!(store.exists("a/b.md").await.unwrap());
+        assert!(!store.exists("a/c.md").await.unwrap());
+
+        for key in ["a/c.md", "ab.md", "b.md"] {
+            store.put(key, Bytes::from(key)).await.unwrap();
+        }
+        assert_eq!(store.list("a/").await.unwrap(), ["a/b.md", "a/c.md"]);
+        assert_eq!(
+            store.list("a").await.unwrap(),
+            ["a/b.md", "a/c.md", "ab.md"]
+        );
+        assert_eq!(
+            store.list("").await.unwrap(),
+            ["a/b.md", "a/c.md", "ab.md", "b.md"]
+        );
+        assert!(store.list("z").await.unwrap().is_empty());
+
+        store.rename("a/b.md", "c/d.md").await.unwrap();
+        assert_eq!(store.get("c/d.md").await.unwrap(), "two");
+        assert!(!store.exists("a/b.md").await.unwrap());
+
+        assert!(store.delete("c/d.md").await.unwrap());
+        assert!(!store.delete("c/d.md").await.unwrap());
+    }
+
+    /// 读取, 重命名不存在的键时返回`NotFound`, 写入无效的键时返回`InvalidKey`
+    pub async fn missing_keys(store: &dyn ContentStore) {
+        assert!(matches!(
+            store.get("missing.md").await,
+            Err(StorageError::NotFound(key)) if key == "missing.md"
+        ));
+        assert!(matches!(
+            store.get_stream("missing.md").await,
+            Err(StorageError::NotFound(_))
+        ));
+        assert!(matches!(
+            store.rename("missing.md", "other.md").await,
+            Err(StorageError::NotFound(_))
+        ));
+        assert!(!store.exists("other.md").await.unwrap());
+        assert!(!store.delete("missing.md").await.unwrap());
+        assert!(matches!(
+            store.put("../escape.md", Bytes::from("x")).await,
+            Err(StorageError::InvalidKey(_))
+        ));
+        assert!(matches!(
+            store.rename("missing.md", "/etc/passwd").await,
+            Err(StorageError::InvalidKey(_))
+        ));
+    }
+}
@@ -0,0 +1,108 @@
+use super::{ByteStream, ChangeNote, ContentStore, PostStorage, StorageError, check_key};
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::{Level, event, instrument};
+
+/// 直接保存在[`ContentStore`]中的文章, 不记录修改历史
+pub struct ContentStorage {
+    store: Arc<dyn ContentStore>,
+}
+
+impl ContentStorage {
+    pub fn new(store: Arc<dyn ContentStore>) -> Self {
+        ContentStorage { store }
+    }
+
+    /// 文章文件的键
+    pub fn key(id: i32) -> String {
+        format!("{}.md", id)
+    }
+
+    /// 被隔离的文章文件的键
+    pub fn orphan_key(id: i32) -> String {
+        format!("orphans/{}.md", id)
+    }
+
+    /// 从文章文件的键中解析出id
+    fn parse_key(key: &str) -> Option<i32> {
+        key.strip_suffix(".md")
+            .filter(|id| id.chars().all(|c| c.is_ascii_digit()))
+            .and_then(|id| id.parse().ok())
+    }
+}
+
+#[async_trait]
+impl PostStorage for ContentStorage {
+    #[instrument(name = "ContentStorage::create", level = "debug", skip_all, fields(id = %id))]
+    async fn create(
+        &self,
+        id: i32,
+        content: Vec<u8>,
+        _note: &ChangeNote<'_>,
+    ) -> Result<(), StorageError> {
+        let key = Self::key(id);
+        if self.store.exists(&key).await? {
+            event!(Level::WARN, key = %key, "文章文件已经存在");
+            return Err(StorageError::AlreadyExists(key));
+        }
+        self.store.put(&key, content.into()).await
+    }
+
+    #[instrument(name = "ContentStorage::replace", level = "debug", skip_all, fields(id = %id))]
+    async fn replace(
+        &self,
+        id: i32,
+        content: Vec<u8>,
+        _note: &ChangeNote<'_>,
+    ) -> Result<(), StorageError> {
+        self.store.put(&Self::key(id), content.into()).await
+    }
+
+    async fn read(&self, id: i32) -> Result<Vec<u8>, StorageError> {
+        Ok(self.store.get(&Self::key(id)).await?.into())
+    }
+
+    async fn read_stream(&self, id: i32) -> Result<ByteStream, StorageError> {
+        self.store.get_stream(&Self::key(id)).await
+    }
+
+    async fn remove(&self, id: i32, _note: &ChangeNote<'_>) -> Result<bool, StorageError> {
+        self.store.delete(&Self::key(id)).await
+    }
+
+    /// 标题不能作为键(如包含`/`)时说明不存在旧版本的文件
+    async fn migrate_legacy(&self, id: i32, title: &str) -> Result<bool, StorageError> {
+        let key = Self::key(id);
+        if check_key(title).is_err()
+            || !self.store.exists(title).await?
+            || self.store.exists(&key).await?
+        {
+            return Ok(false);
+        }
+        self.store.rename(title, &key).await?;
+        Ok(true)
+    }
+
+    async fn list_ids(&self) -> Result<Vec<i32>, StorageError> {
+        let mut ids: Vec<i32> = self
+            .store
+            .list("")
+            .await?
+            .iter()
+            .filter_map(|key| Self::parse_key(key))
+            .collect();
+        ids.sort_unstable();
+        Ok(ids)
+    }
+
+    #[instrument(
+        name = "ContentStorage::quarantine",
+        level = "debug",
+        skip(self, _note)
+    )]
+    async fn quarantine(&self, id: i32, _note: &ChangeNote<'_>) -> Result<String, StorageError> {
+        let key = Self::orphan_key(id);
+        self.store.rename(&Self::key(id), &key).await?;
+        Ok(key)
+    }
+}
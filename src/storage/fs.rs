@@ -0,0 +1,206 @@
+use super::{ByteStream, ContentStore, StorageError, check_key};
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::fs::create_dir_all;
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tokio::task;
+use tokio_util::io::ReaderStream;
+use tracing::{Level, event, instrument};
+
+/// 保存在本地目录中的文件, 键就是相对于目录的路径
+pub struct FsStore {
+    dir: PathBuf,
+}
+
+impl FsStore {
+    pub fn new(dir: &str) -> Self {
+        let path = Path::new(dir);
+        if !path.exists()
+            && let Err(e) = create_dir_all(path)
+        {
+            panic!("无法创建文件夹: {}\n{}", path.display(), e)
+        }
+        if !path.is_dir() {
+            panic!(r"`{}`为无效的路径, 请输入路径", path.display())
+        }
+
+        tracing::info!("创建FsStore成功, 保存路径为: {}", dir);
+        FsStore {
+            dir: path.to_path_buf(),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, StorageError> {
+        check_key(key)?;
+        Ok(self.dir.join(key))
+    }
+
+    /// 同目录下的临时文件, 以`.`开头的文件不会出现在`list`的结果中
+    fn temp_path(path: &Path) -> PathBuf {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        path.with_file_name(format!(".{}.tmp", name))
+    }
+
+    fn not_found(key: &str, e: io::Error) -> StorageError {
+        match e.kind() {
+            io::ErrorKind::NotFound => StorageError::NotFound(key.to_string()),
+            _ => e.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl ContentStore for FsStore {
+    /// 先写入同目录下的临时文件再重命名, 读取者不会看到写了一半的文件
+    #[instrument(name = "FsStore::put", level = "debug", skip(self, data))]
+    async fn put(&self, key: &str, data: Bytes) -> Result<(), StorageError> {
+        let path = self.path(key)?;
+        let temp = Self::temp_path(&path);
+        event!(Level::DEBUG, file_path = %path.display(), content_size = data.len(), "开始写入文件");
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        {
+            let mut file = File::create(&temp).await?;
+            file.write_all(&data).await?;
+            file.sync_all().await?;
+        }
+        if let Err(e) = tokio::fs::rename(&temp, &path).await {
+            let _ = tokio::fs::remove_file(&temp).await;
+            return Err(e.into());
+        }
+
+        event!(Level::DEBUG, file_path = %path.display(), "成功写入文件");
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Bytes, StorageError> {
+        let data = tokio::fs::read(self.path(key)?)
+            .await
+            .map_err(|e| Self::not_found(key, e))?;
+        Ok(data.into())
+    }
+
+    async fn get_stream(&self, key: &str) -> Result<ByteStream, StorageError> {
+        let file = File::open(self.path(key)?)
+            .await
+            .map_err(|e| Self::not_found(key, e))?;
+        Ok(Box::pin(ReaderStream::new(file)))
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, StorageError> {
+        Ok(tokio::fs::try_exists(self.path(key)?).await?)
+    }
+
+    #[instrument(name = "FsStore::delete", level = "debug", skip(self))]
+    async fn delete(&self, key: &str) -> Result<bool, StorageError> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    #[instrument(name = "FsStore::rename", level = "debug", skip(self))]
+    async fn rename(&self, from: &str, to: &str) -> Result<(), StorageError> {
+        let to_path = self.path(to)?;
+        if let Some(parent) = to_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::rename(self.path(from)?, to_path)
+            .await
+            .map_err(|e| Self::not_found(from, e))
+    }
+
+    /// 递归列出目录中的文件, 跳过以`.`开头的文件和目录(包括临时文件和`.git`)
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        let dir = self.dir.clone();
+        let prefix = prefix.to_string();
+        let keys = task::spawn_blocking(move || -> io::Result<Vec<String>> {
+            let mut keys = Vec::new();
+            let mut pending = vec![dir.clone()];
+            while let Some(current) = pending.pop() {
+                for entry in std::fs::read_dir(&current)? {
+                    let entry = entry?;
+                    if entry.file_name().to_string_lossy().starts_with('.') {
+                        continue;
+                    }
+                    let path = entry.path();
+                    if entry.file_type()?.is_dir() {
+                        pending.push(path);
+                        continue;
+                    }
+                    let Ok(relative) = path.strip_prefix(&dir) else {
+                        continue;
+                    };
+                    let key = relative
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    if key.starts_with(&prefix) {
+                        keys.push(key);
+                    }
+                }
+            }
+            keys.sort();
+            Ok(keys)
+        })
+        .await
+        .map_err(io::Error::other)??;
+        Ok(keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::{missing_keys, round_trip};
+    use tempfile::TempDir;
+
+    fn store(dir: &TempDir) -> FsStore {
+        FsStore::new(dir.path().to_str().unwrap())
+    }
+
+    #[tokio::test]
+    async fn fs_round_trip() {
+        let dir = TempDir::new().unwrap();
+        round_trip(&store(&dir)).await;
+    }
+
+    #[tokio::test]
+    async fn fs_missing_keys() {
+        let dir = TempDir::new().unwrap();
+        missing_keys(&store(&dir)).await;
+    }
+
+    #[tokio::test]
+    async fn fs_list_skips_hidden_files() {
+        let dir = TempDir::new().unwrap();
+        let store = store(&dir);
+        store.put("a/b.md", Bytes::from("one")).await.unwrap();
+        std::fs::write(dir.path().join("a/.b.md.tmp"), "half").unwrap();
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        std::fs::write(dir.path().join(".git/HEAD"), "ref").unwrap();
+
+        assert_eq!(store.list("").await.unwrap(), ["a/b.md"]);
+        assert_eq!(std::fs::read(dir.path().join("a/b.md")).unwrap(), b"one");
+    }
+
+    #[tokio::test]
+    async fn fs_creates_missing_dir() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("nested/posts");
+        let store = FsStore::new(path.to_str().unwrap());
+        store.put("1.md", Bytes::from("one")).await.unwrap();
+        assert_eq!(std::fs::read(path.join("1.md")).unwrap(), b"one");
+    }
+}
@@ -0,0 +1,400 @@
+use super::{ByteStream, Change, ChangeNote, ContentStorage, FsStore, PostStorage, StorageError};
+use async_trait::async_trait;
+use git2::{Commit, ErrorCode, IndexAddOption, Repository, Signature};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::task;
+use tracing::{Level, event, instrument};
+
+impl From<git2::Error> for StorageError {
+    fn from(value: git2::Error) -> Self {
+        Self::Git(value.message().to_string())
+    }
+}
+
+/// 保存在本地git仓库中的文件, 每次修改都会生成一个提交
+///
+/// 仓库的工作区就是`save_dir`, 可以直接`git clone`作为备份,
+/// 文件的读写仍然由[`FsStore`]完成
+pub struct GitStorage {
+    files: ContentStorage,
+    repo: Arc<Mutex<Repository>>,
+    name: String,
+    email: String,
+}
+
+impl GitStorage {
+    /// 打开`save_dir`中的仓库, 不存在时初始化并提交已有的文章
+    pub fn open(save_dir: &str, name: &str, email: &str) -> Result<Self, StorageError> {
+        let store = FsStore::new(save_dir);
+        let repo = match Repository::open(store.dir()) {
+            Ok(repo) => repo,
+            Err(e) if e.code() == ErrorCode::NotFound => {
+                tracing::info!("在`{}`中初始化git仓库", save_dir);
+                Repository::init(store.dir())?
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let storage = GitStorage {
+            files: ContentStorage::new(Arc::new(store)),
+            repo: Arc::new(Mutex::new(repo)),
+            name: name.to_string(),
+            email: email.to_string(),
+        };
+        storage.import_existing()?;
+
+        tracing::info!("创建GitStorage成功, 仓库路径为: {}", save_dir);
+        Ok(storage)
+    }
+
+    /// 新建的仓库还没有提交时, 将目录中已有的文章作为第一个提交
+    fn import_existing(&self) -> Result<(), StorageError> {
+        let repo = self.lock()?;
+        if !repo.is_empty()? {
+            return Ok(());
+        }
+        let mut index = repo.index()?;
+        index.add_all(["*.md"], IndexAddOption::DEFAULT, None)?;
+        index.write()?;
+        if index.is_empty() {
+            return Ok(());
+        }
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let signature = Signature::now(&self.name, &self.email)?;
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            "import existing posts",
+            &tree,
+            &[],
+        )?;
+        event!(Level::INFO, file_count = index.len(), "成功提交已有的文章");
+        Ok(())
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Repository>, StorageError> {
+        self.repo
+            .lock()
+            .map_err(|_| StorageError::Git("仓库锁已损坏".to_string()))
+    }
+
+    /// 提交信息的格式为:
+    ///
+    /// ```text
+    /// update post 12: 标题
+    ///
+    /// 用户填写的修改说明
+    ///
+    /// Post-Id: 12
+    /// ```
+    fn commit_message(note: &ChangeNote<'_>) -> String {
+        let mut message = format!("{} post {}: {}\n\n", note.change, note.id, note.title);
+        if let Some(body) = note.message.filter(|body| !body.trim().is_empty()) {
+            message.push_str(body.trim());
+            message.push_str("\n\n");
+        }
+        message.push_str(&format!("Post-Id: {}\n", note.id));
+        message
+    }
+
+    /// 将工作区中`paths`的当前状态提交, 已经不存在的文件从索引中删除, 没有变化时不提交
+    async fn commit(&self, paths: Vec<String>, note: &ChangeNote<'_>) -> Result<(), StorageError> {
+        let repo = self.repo.clone();
+        let message = Self::commit_message(note);
+        let committer = (self.name.clone(), self.email.clone());
+        let author = note.author.map(str::to_string);
+
+        let committed = task::spawn_blocking(move || -> Result<bool, StorageError> {
+            let repo = repo
+                .lock()
+                .map_err(|_| StorageError::Git("仓库锁已损坏".to_string()))?;
+            let workdir = repo
+                .workdir()
+                .ok_or_else(|| StorageError::Git("仓库没有工作区".to_string()))?
+                .to_path_buf();
+
+            let mut index = repo.index()?;
+            for path in paths.iter().map(Path::new) {
+                if workdir.join(path).is_file() {
+                    index.add_path(path)?;
+                } else if index.get_path(path, 0).is_some() {
+                    index.remove_path(path)?;
+                }
+            }
+            index.write()?;
+            let tree_id = index.write_tree()?;
+
+            let parent = match repo.head() {
+                Ok(head) => Some(head.peel_to_commit()?),
+                Err(e) if e.code() == ErrorCode::UnbornBranch => None,
+                Err(e) => return Err(e.into()),
+            };
+            if parent
+                .as_ref()
+                .is_some_and(|parent| parent.tree_id() == tree_id)
+            {
+                return Ok(false);
+            }
+
+            let tree = repo.find_tree(tree_id)?;
+            let committer = Signature::now(&committer.0, &committer.1)?;
+            let author = match &author {
+                Some(author) => Signature::now(author, committer.email().unwrap_or_default())?,
+                None => committer.clone(),
+            };
+            let parents: Vec<&Commit> = parent.iter().collect();
+            repo.commit(Some("HEAD"), &author, &committer, &message, &tree, &parents)?;
+            Ok(true)
+        })
+        .await
+        .map_err(|e| StorageError::Git(e.to_string()))??;
+
+        event!(
+            Level::DEBUG,
+            post_id = note.id,
+            change = %note.change,
+            committed = committed,
+            "完成git提交"
+        );
+        Ok(())
+    }
+
+    /// 提交失败时将工作区中的文件恢复为修改前的内容, 保证文件和仓库的历史一致
+    async fn undo(&self, id: i32, previous: Option<Vec<u8>>, note: &ChangeNote<'_>) {
+        let undone = match previous {
+            Some(content) => self.files.replace(id, content, note).await,
+            None => self.files.remove(id, note).await.map(|_| ()),
+        };
+        if let Err(e) = undone {
+            event!(Level::ERROR, post_id = id, error = %e, "撤销未提交的文件修改失败");
+        }
+    }
+}
+
+#[async_trait]
+impl PostStorage for GitStorage {
+    #[instrument(
+        name = "GitStorage::create",
+        level = "debug",
+        skip(self, content, note)
+    )]
+    async fn create(
+        &self,
+        id: i32,
+        content: Vec<u8>,
+        note: &ChangeNote<'_>,
+    ) -> Result<(), StorageError> {
+        self.files.create(id, content, note).await?;
+        let committed = self.commit(vec![ContentStorage::key(id)], note).await;
+        if committed.is_err() {
+            self.undo(id, None, note).await;
+        }
+        committed
+    }
+
+    #[instrument(
+        name = "GitStorage::replace",
+        level = "debug",
+        skip(self, content, note)
+    )]
+    async fn replace(
+        &self,
+        id: i32,
+        content: Vec<u8>,
+        note: &ChangeNote<'_>,
+    ) -> Result<(), StorageError> {
+        let previous = self.files.read(id).await.ok();
+        self.files.replace(id, content, note).await?;
+        let committed = self.commit(vec![ContentStorage::key(id)], note).await;
+        if committed.is_err() {
+            self.undo(id, previous, note).await;
+        }
+        committed
+    }
+
+    async fn read(&self, id: i32) -> Result<Vec<u8>, StorageError> {
+        self.files.read(id).await
+    }
+
+    async fn read_stream(&self, id: i32) -> Result<ByteStream, StorageError> {
+        self.files.read_stream(id).await
+    }
+
+    #[instrument(name = "GitStorage::remove", level = "debug", skip(self, note))]
+    async fn remove(&self, id: i32, note: &ChangeNote<'_>) -> Result<bool, StorageError> {
+        let previous = self.files.read(id).await.ok();
+        let removed = self.files.remove(id, note).await?;
+        if let Err(e) = self.commit(vec![ContentStorage::key(id)], note).await {
+            self.undo(id, previous, note).await;
+            return Err(e);
+        }
+        Ok(removed)
+    }
+
+    async fn migrate_legacy(&self, id: i32, title: &str) -> Result<bool, StorageError> {
+        if !self.files.migrate_legacy(id, title).await? {
+            return Ok(false);
+        }
+        let note = ChangeNote {
+            change: Change::Rename,
+            id,
+            title,
+            author: None,
+            message: None,
+        };
+        self.commit(vec![title.to_string(), ContentStorage::key(id)], &note)
+            .await?;
+        Ok(true)
+    }
+
+    async fn list_ids(&self) -> Result<Vec<i32>, StorageError> {
+        self.files.list_ids().await
+    }
+
+    #[instrument(name = "GitStorage::quarantine", level = "debug", skip(self, note))]
+    async fn quarantine(&self, id: i32, note: &ChangeNote<'_>) -> Result<String, StorageError> {
+        let key = self.files.quarantine(id, note).await?;
+        self.commit(vec![ContentStorage::key(id), key.clone()], note)
+            .await?;
+        Ok(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn note(change: Change, author: Option<&str>) -> ChangeNote<'_> {
+        ChangeNote {
+            change,
+            id: 1,
+            title: "标题",
+            author,
+            message: Some("修改说明"),
+        }
+    }
+
+    fn storage(dir: &TempDir) -> GitStorage {
+        Repository::init(dir.path()).unwrap();
+        GitStorage::open(dir.path().to_str().unwrap(), "blog", "blog@example.com").unwrap()
+    }
+
+    /// 从HEAD开始的提交信息, 最新的在前
+    fn messages(dir: &TempDir) -> Vec<String> {
+        let repo = Repository::open(dir.path()).unwrap();
+        let Ok(head) = repo.head() else {
+            return Vec::new();
+        };
+        let mut commit = Some(head.peel_to_commit().unwrap());
+        let mut messages = Vec::new();
+        while let Some(current) = commit {
+            messages.push(current.message().unwrap().to_string());
+            commit = current.parents().next();
+        }
+        messages
+    }
+
+    #[tokio::test]
+    async fn each_change_creates_one_commit() {
+        let dir = TempDir::new().unwrap();
+        let storage = storage(&dir);
+        assert!(messages(&dir).is_empty());
+
+        storage
+            .create(1, b"one".to_vec(), &note(Change::Add, Some("alice")))
+            .await
+            .unwrap();
+        assert_eq!(messages(&dir).len(), 1);
+        storage
+            .replace(1, b"two".to_vec(), &note(Change::Update, None))
+            .await
+            .unwrap();
+        assert_eq!(messages(&dir).len(), 2);
+        assert!(
+            storage
+                .remove(1, &note(Change::Delete, None))
+                .await
+                .unwrap()
+        );
+
+        assert_eq!(
+            messages(&dir),
+            ["delete", "update", "add"]
+                .map(|change| format!("{} post 1: 标题\n\n修改说明\n\nPost-Id: 1\n", change))
+        );
+        let repo = Repository::open(dir.path()).unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert!(head.tree().unwrap().get_path(Path::new("1.md")).is_err());
+        let first = head.parent(0).unwrap().parent(0).unwrap();
+        assert_eq!(first.author().name(), Some("alice"));
+        assert_eq!(first.committer().name(), Some("blog"));
+    }
+
+    #[tokio::test]
+    async fn unchanged_content_is_not_committed() {
+        let dir = TempDir::new().unwrap();
+        let storage = storage(&dir);
+        storage
+            .create(1, b"one".to_vec(), &note(Change::Add, None))
+            .await
+            .unwrap();
+        storage
+            .replace(1, b"one".to_vec(), &note(Change::Update, None))
+            .await
+            .unwrap();
+        assert_eq!(messages(&dir).len(), 1);
+    }
+
+    #[test]
+    fn existing_posts_are_imported() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("1.md"), "one").unwrap();
+        storage(&dir);
+        assert_eq!(messages(&dir), ["import existing posts"]);
+    }
+
+    /// 签名中不能包含尖括号, 用来让提交失败
+    const BAD_AUTHOR: Option<&str> = Some("<bad>");
+
+    #[tokio::test]
+    async fn failed_commit_undoes_create() {
+        let dir = TempDir::new().unwrap();
+        let storage = storage(&dir);
+        assert!(
+            storage
+                .create(1, b"one".to_vec(), &note(Change::Add, BAD_AUTHOR))
+                .await
+                .is_err()
+        );
+        assert!(storage.read(1).await.is_err());
+        assert!(messages(&dir).is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_commit_undoes_replace_and_remove() {
+        let dir = TempDir::new().unwrap();
+        let storage = storage(&dir);
+        storage
+            .create(1, b"one".to_vec(), &note(Change::Add, None))
+            .await
+            .unwrap();
+
+        assert!(
+            storage
+                .replace(1, b"two".to_vec(), &note(Change::Update, BAD_AUTHOR))
+                .await
+                .is_err()
+        );
+        assert_eq!(storage.read(1).await.unwrap(), b"one");
+        assert!(
+            storage
+                .remove(1, &note(Change::Delete, BAD_AUTHOR))
+                .await
+                .is_err()
+        );
+        assert_eq!(storage.read(1).await.unwrap(), b"one");
+        assert_eq!(messages(&dir).len(), 1);
+    }
+}
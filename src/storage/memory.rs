@@ -0,0 +1,105 @@
+use super::{ByteStream, ContentStore, StorageError, check_key};
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// 保存在内存中的文件, 进程退出后丢失, 用于测试
+#[derive(Default)]
+pub struct MemoryStore {
+    files: RwLock<BTreeMap<String, Bytes>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        tracing::info!("创建MemoryStore成功, 文件不会被持久化");
+        Self::default()
+    }
+
+    fn poisoned() -> StorageError {
+        StorageError::Internal("内存存储的锁已损坏".to_string())
+    }
+}
+
+#[async_trait]
+impl ContentStore for MemoryStore {
+    async fn put(&self, key: &str, data: Bytes) -> Result<(), StorageError> {
+        check_key(key)?;
+        self.files
+            .write()
+            .map_err(|_| Self::poisoned())?
+            .insert(key.to_string(), data);
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Bytes, StorageError> {
+        self.files
+            .read()
+            .map_err(|_| Self::poisoned())?
+            .get(key)
+            .cloned()
+            .ok_or_else(|| StorageError::NotFound(key.to_string()))
+    }
+
+    async fn get_stream(&self, key: &str) -> Result<ByteStream, StorageError> {
+        let data = self.get(key).await?;
+        Ok(Box::pin(futures_util::stream::once(
+            async move { Ok(data) },
+        )))
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, StorageError> {
+        Ok(self
+            .files
+            .read()
+            .map_err(|_| Self::poisoned())?
+            .contains_key(key))
+    }
+
+    async fn delete(&self, key: &str) -> Result<bool, StorageError> {
+        Ok(self
+            .files
+            .write()
+            .map_err(|_| Self::poisoned())?
+            .remove(key)
+            .is_some())
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<(), StorageError> {
+        check_key(to)?;
+        let mut files = self.files.write().map_err(|_| Self::poisoned())?;
+        let data = files
+            .remove(from)
+            .ok_or_else(|| StorageError::NotFound(from.to_string()))?;
+        files.insert(to.to_string(), data);
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        Ok(self
+            .files
+            .read()
+            .map_err(|_| Self::poisoned())?
+            .range(prefix.to_string()..)
+            .map(|(key, _)| key)
+            .take_while(|key| key.starts_with(prefix))
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::{missing_keys, round_trip};
+
+    #[tokio::test]
+    async fn memory_round_trip() {
+        round_trip(&MemoryStore::new()).await;
+    }
+
+    #[tokio::test]
+    async fn memory_missing_keys() {
+        missing_keys(&MemoryStore::new()).await;
+    }
+}
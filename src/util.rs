@@ -1,15 +1,952 @@
+mod render;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use pulldown_cmark::{Event, LinkType, Options, Parser, Tag, TagEnd};
+pub use render::TocEntry;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_yaml::{Mapping, Value};
+use std::collections::{HashMap, HashSet};
 use std::sync::LazyLock;
+use syntect::parsing::SyntaxSet;
+
+/// 中日韩文字每分钟的阅读字数
+const CJK_PER_MINUTE: f32 = 300.0;
+/// 其他语言每分钟的阅读单词数
+const WORDS_PER_MINUTE: f32 = 200.0;
+
+/// 关键词搜索摘要中高亮的开始和结束标记, 使用正文中不会出现的控制字符,
+/// 转义html之后再由[`highlight_snippet`]替换为`<mark>`标签
+pub const HIGHLIGHT_START: char = '\u{2}';
+pub const HIGHLIGHT_END: char = '\u{3}';
 
 pub static MARKDOWN_UTIL: LazyLock<MarkdownUtil> = LazyLock::new(|| MarkdownUtil::new());
+
+#[derive(Debug, thiserror::Error)]
+pub enum MarkdownError {
+    #[error("文件内容不是有效的UTF-8编码")]
+    Encoding,
+    #[error("front matter 没有结束标记")]
+    Unclosed,
+    #[error("front matter 格式错误: {0}")]
+    Syntax(String),
+    #[error("无法识别的日期格式: {0}")]
+    Date(String),
+}
+
+/// markdown文件头部的元数据
+#[derive(Debug, Default)]
+pub struct FrontMatter {
+    pub title: Option<String>,
+    pub slug: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub date: Option<DateTime<Utc>>,
+    pub updated: Option<DateTime<Utc>>,
+    pub summary: Option<String>,
+    /// 发布状态, `draft: true`等同于`status: draft`
+    pub status: Option<String>,
+    /// 定时发布的时间
+    pub publish_at: Option<DateTime<Utc>>,
+}
+
+/// 拆分后的markdown文件
+pub struct ParsedMarkdown<'a> {
+    pub front_matter: FrontMatter,
+    pub body: &'a str,
+}
+
+/// markdown正文去除格式后的纯文本, 标题单独存放
+#[derive(Debug, Default)]
+pub struct PlainText {
+    pub headings: Vec<String>,
+    pub body: String,
+}
+
+/// 正文的字数统计, 中日韩文字每个字算一个词
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TextStats {
+    pub word_count: i32,
+    pub char_count: i32,
+    /// 预计阅读时间, 单位为分钟
+    pub reading_time: i32,
+}
+
+/// 标签既可以写成列表, 也可以写成逗号分隔的字符串
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawTags {
+    List(Vec<String>),
+    Joined(String),
+}
+
+#[derive(Deserialize, Default)]
+struct RawFrontMatter {
+    title: Option<String>,
+    slug: Option<String>,
+    tags: Option<RawTags>,
+    date: Option<String>,
+    updated: Option<String>,
+    summary: Option<String>,
+    #[serde(default)]
+    draft: bool,
+    status: Option<String>,
+    publish_at: Option<String>,
+}
+
 /// 用来处理markdown文件的工具函数集合
-/// - 获取markdown文件的front matter (使用grey_matter)
-/// - 将markdown文件转为纯文本 (使用texting)
-/// - 将markdown文件内容使用嵌入模型转化为向量(使用硅基流动)
-//TODO
-pub struct MarkdownUtil {}
+/// - 获取markdown文件的front matter (支持`---`包裹的YAML和`+++`包裹的TOML)
+/// - 将markdown文件转为纯文本 (使用pulldown-cmark)
+/// - 将纯文本切分为适合嵌入模型的片段 (向量计算见`embedding`模块)
+/// - 将markdown文件渲染为html (使用pulldown-cmark和syntect)
+/// - 生成目录, 统计字数和阅读时间
+/// - 生成文章列表中展示的摘要
+pub struct MarkdownUtil {
+    syntax_set: SyntaxSet,
+}
 
 impl MarkdownUtil {
     fn new() -> Self {
-        Self {}
+        Self {
+            syntax_set: SyntaxSet::load_defaults_newlines(),
+        }
+    }
+
+    /// 将上传的文件内容拆分为front matter和正文, 没有front matter时返回默认值
+    pub fn parse<'a>(&self, content: &'a [u8]) -> Result<ParsedMarkdown<'a>, MarkdownError> {
+        let (raw, body) = self.parse_as::<RawFrontMatter>(content)?;
+        Ok(ParsedMarkdown {
+            front_matter: raw.try_into()?,
+            body,
+        })
+    }
+
+    /// 同[`MarkdownUtil::parse`], 但是将front matter反序列化为任意类型,
+    /// 用于读取其他博客程序特有的字段
+    pub fn parse_as<'a, T>(&self, content: &'a [u8]) -> Result<(T, &'a str), MarkdownError>
+    where
+        T: DeserializeOwned + Default,
+    {
+        let text = std::str::from_utf8(content).map_err(|_| MarkdownError::Encoding)?;
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+
+        let Some((delimiter, rest)) = Self::opening_delimiter(text) else {
+            return Ok((T::default(), text));
+        };
+
+        // 找到只包含分隔符的那一行作为结束标记
+        let mut offset = 0;
+        let mut closing = None;
+        for line in rest.split_inclusive('\n') {
+            if line.trim_end() == delimiter {
+                closing = Some((offset, offset + line.len()));
+                break;
+            }
+            offset += line.len();
+        }
+        let (raw_end, body_start) = closing.ok_or(MarkdownError::Unclosed)?;
+        let raw = &rest[..raw_end];
+        let body = &rest[body_start..];
+
+        let raw = match delimiter {
+            "---" => Self::parse_yaml(raw)?,
+            _ => Self::parse_toml(raw)?,
+        };
+        Ok((raw, body))
+    }
+
+    fn opening_delimiter(text: &str) -> Option<(&'static str, &str)> {
+        ["---", "+++"].into_iter().find_map(|delimiter| {
+            let rest = text.strip_prefix(delimiter)?;
+            let rest = rest
+                .strip_prefix("\r\n")
+                .or_else(|| rest.strip_prefix('\n'))?;
+            Some((delimiter, rest))
+        })
+    }
+
+    fn parse_yaml<T: DeserializeOwned + Default>(raw: &str) -> Result<T, MarkdownError> {
+        if raw.trim().is_empty() {
+            return Ok(T::default());
+        }
+        serde_yaml::from_str(raw).map_err(|e| MarkdownError::Syntax(e.to_string()))
+    }
+
+    fn parse_toml<T: DeserializeOwned + Default>(raw: &str) -> Result<T, MarkdownError> {
+        let mut table: toml::Table =
+            toml::from_str(raw).map_err(|e| MarkdownError::Syntax(e.to_string()))?;
+        // TOML原生的日期类型无法直接反序列化为字符串
+        for (_, value) in table.iter_mut() {
+            if let toml::Value::Datetime(datetime) = value {
+                *value = toml::Value::String(datetime.to_string());
+            }
+        }
+        toml::Value::Table(table)
+            .try_into()
+            .map_err(|e: toml::de::Error| MarkdownError::Syntax(e.to_string()))
+    }
+
+    /// 生成以YAML front matter开头的markdown文件, 导入其他博客程序的文章时用来统一格式
+    pub fn compose(&self, front_matter: &FrontMatter, body: &str) -> Vec<u8> {
+        let mut mapping = Mapping::new();
+        let mut insert = |key: &str, value: Option<Value>| {
+            if let Some(value) = value {
+                mapping.insert(Value::String(key.to_string()), value);
+            }
+        };
+        let date = |date: Option<DateTime<Utc>>| date.map(|date| Value::String(date.to_rfc3339()));
+        insert("title", front_matter.title.clone().map(Value::String));
+        insert("slug", front_matter.slug.clone().map(Value::String));
+        insert(
+            "tags",
+            front_matter
+                .tags
+                .as_ref()
+                .filter(|tags| !tags.is_empty())
+                .map(|tags| tags.iter().cloned().map(Value::String).collect()),
+        );
+        insert("date", date(front_matter.date));
+        insert("updated", date(front_matter.updated));
+        insert("summary", front_matter.summary.clone().map(Value::String));
+        insert("status", front_matter.status.clone().map(Value::String));
+        insert("publish_at", date(front_matter.publish_at));
+
+        let mut content = String::new();
+        if !mapping.is_empty() {
+            // 序列化字符串和列表组成的映射不会失败
+            let yaml = serde_yaml::to_string(&mapping).unwrap_or_default();
+            content.push_str("---\n");
+            content.push_str(&yaml);
+            content.push_str("---\n");
+        }
+        content.push_str(body);
+        content.into_bytes()
+    }
+
+    /// 提取markdown正文中的文字, 代码块保留原文, 内嵌的html被丢弃
+    pub fn to_plain_text(&self, body: &str) -> PlainText {
+        let mut text = PlainText::default();
+        let mut heading: Option<String> = None;
+        for event in Parser::new_ext(body, Self::options()) {
+            match event {
+                Event::Start(Tag::Heading { .. }) => heading = Some(String::new()),
+                Event::End(TagEnd::Heading(_)) => {
+                    if let Some(heading) = heading.take() {
+                        text.headings.push(heading.trim().to_string());
+                    }
+                }
+                Event::Text(s) | Event::Code(s) => match heading.as_mut() {
+                    Some(heading) => heading.push_str(&s),
+                    None => text.body.push_str(&s),
+                },
+                Event::SoftBreak | Event::HardBreak => match heading.as_mut() {
+                    Some(heading) => heading.push(' '),
+                    None => text.body.push('\n'),
+                },
+                Event::End(
+                    TagEnd::Paragraph
+                    | TagEnd::Item
+                    | TagEnd::CodeBlock
+                    | TagEnd::TableCell
+                    | TagEnd::BlockQuote(_),
+                ) => text.body.push('\n'),
+                _ => {}
+            }
+        }
+        text
+    }
+
+    /// 按段落将纯文本切分为不超过`max_chars`个字符的片段, 过长的段落会被强制截断
+    pub fn chunks(&self, text: &str, max_chars: usize) -> Vec<String> {
+        let mut chunks = Vec::new();
+        let mut current = String::new();
+        let mut current_chars = 0;
+        for paragraph in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let paragraph_chars = paragraph.chars().count();
+            if current_chars > 0 && current_chars + paragraph_chars > max_chars {
+                chunks.push(std::mem::take(&mut current));
+                current_chars = 0;
+            }
+            if paragraph_chars > max_chars {
+                let chars: Vec<char> = paragraph.chars().collect();
+                for piece in chars.chunks(max_chars) {
+                    chunks.push(piece.iter().collect());
+                }
+                continue;
+            }
+            if current_chars > 0 {
+                current.push('\n');
+            }
+            current.push_str(paragraph);
+            current_chars += paragraph_chars;
+        }
+        if current_chars > 0 {
+            chunks.push(current);
+        }
+        chunks
+    }
+
+    /// 统计标题和正文的字数, 阅读速度按每分钟300个中日韩文字或200个其他单词估算
+    pub fn stats(&self, text: &PlainText) -> TextStats {
+        let mut cjk = 0;
+        let mut words = 0;
+        let mut chars = 0;
+        let headings = text.headings.iter().map(String::as_str);
+        for part in headings.chain(std::iter::once(text.body.as_str())) {
+            let mut in_word = false;
+            for c in part.chars() {
+                if !c.is_whitespace() {
+                    chars += 1;
+                }
+                if is_cjk(c) {
+                    cjk += 1;
+                    in_word = false;
+                } else if c.is_alphanumeric() {
+                    words += !in_word as i32;
+                    in_word = true;
+                } else {
+                    in_word = false;
+                }
+            }
+        }
+        let minutes = cjk as f32 / CJK_PER_MINUTE + words as f32 / WORDS_PER_MINUTE;
+        TextStats {
+            word_count: cjk + words,
+            char_count: chars,
+            reading_time: if cjk + words == 0 {
+                0
+            } else {
+                (minutes.ceil() as i32).max(1)
+            },
+        }
+    }
+
+    /// 生成文章摘要, 有`<!-- more -->`标记时使用标记之前的内容,
+    /// 否则截取正文的前`max_chars`个字符, 不会从英文单词中间截断
+    pub fn excerpt(&self, body: &str, max_chars: usize) -> String {
+        if let Some(index) = Self::find_more(body) {
+            return collapse_whitespace(&self.to_plain_text(&body[..index]).body);
+        }
+        let text = collapse_whitespace(&self.to_plain_text(body).body);
+        let chars: Vec<char> = text.chars().collect();
+        if chars.len() <= max_chars {
+            return text;
+        }
+        let mut end = max_chars;
+        let in_word = |c: char| c.is_alphanumeric() && !is_cjk(c);
+        if in_word(chars[end - 1]) && in_word(chars[end]) {
+            // 退回到单词开始的位置, 整段都是一个单词时只能强制截断
+            if let Some(start) = chars[..end].iter().rposition(|c| !in_word(*c)) {
+                end = start + 1;
+            }
+        }
+        let excerpt: String = chars[..end].iter().collect();
+        format!("{}…", excerpt.trim_end())
+    }
+
+    /// 查找`<!-- more -->`标记, 允许省略空格
+    fn find_more(body: &str) -> Option<usize> {
+        body.match_indices("<!--").find_map(|(index, _)| {
+            let rest = body[index + 4..].trim_start().strip_prefix("more")?;
+            rest.trim_start().starts_with("-->").then_some(index)
+        })
+    }
+
+    /// 改写正文中链接和图片的地址, `replace`的参数为原地址和是否为图片, 返回空时保持原样;
+    /// 引用式链接改写的是链接定义中的地址, 内嵌html中的`src`属性作为图片处理
+    pub fn rewrite_links(
+        &self,
+        body: &str,
+        mut replace: impl FnMut(&str, bool) -> Option<String>,
+    ) -> String {
+        // (开始位置, 结束位置, 新地址)
+        let mut edits = Vec::new();
+        let mut referenced: HashMap<String, bool> = HashMap::new();
+        let mut parser = Parser::new_ext(body, Self::options()).into_offset_iter();
+        for (event, range) in parser.by_ref() {
+            let (link_type, dest, image) = match event {
+                Event::Start(Tag::Image {
+                    link_type,
+                    dest_url,
+                    ..
+                }) => (link_type, dest_url, true),
+                Event::Start(Tag::Link {
+                    link_type,
+                    dest_url,
+                    ..
+                }) => (link_type, dest_url, false),
+                Event::Html(html) | Event::InlineHtml(html) => {
+                    for (offset, url) in Self::html_sources(&html) {
+                        if let Some(new) = replace(url, true) {
+                            let start = range.start + offset;
+                            edits.push((start, start + url.len(), new));
+                        }
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            match link_type {
+                LinkType::Inline => {
+                    // 地址在`](`之后, 链接文字中嵌套的图片有自己的事件
+                    let text = &body[range.clone()];
+                    let search = text.rfind("](").unwrap_or(0);
+                    if let Some(index) = text[search..].find(&*dest)
+                        && let Some(new) = replace(&dest, image)
+                    {
+                        let start = range.start + search + index;
+                        edits.push((start, start + dest.len(), new));
+                    }
+                }
+                LinkType::Reference | LinkType::Collapsed | LinkType::Shortcut => {
+                    *referenced.entry(dest.to_string()).or_default() |= image;
+                }
+                _ => {}
+            }
+        }
+        let mut definitions: Vec<_> = parser
+            .reference_definitions()
+            .iter()
+            .map(|(_, definition)| definition)
+            .collect();
+        definitions.sort_by_key(|definition| definition.span.start);
+        for definition in definitions {
+            let Some(image) = referenced.get(&*definition.dest) else {
+                continue;
+            };
+            let text = &body[definition.span.clone()];
+            let search = text.find("]:").unwrap_or(0);
+            if let Some(index) = text[search..].find(&*definition.dest)
+                && let Some(new) = replace(&definition.dest, *image)
+            {
+                let start = definition.span.start + search + index;
+                edits.push((start, start + definition.dest.len(), new));
+            }
+        }
+
+        edits.sort_by_key(|(start, ..)| *start);
+        let mut rewritten = String::with_capacity(body.len());
+        let mut last = 0;
+        for (start, end, new) in edits {
+            if start < last {
+                continue;
+            }
+            rewritten.push_str(&body[last..start]);
+            rewritten.push_str(&new);
+            last = end;
+        }
+        rewritten.push_str(&body[last..]);
+        rewritten
+    }
+
+    /// 找出html中`src`属性的值及其位置
+    fn html_sources(html: &str) -> Vec<(usize, &str)> {
+        html.match_indices("src=")
+            .filter(|(index, _)| {
+                html[..*index]
+                    .chars()
+                    .next_back()
+                    .is_some_and(char::is_whitespace)
+            })
+            .filter_map(|(index, _)| {
+                let start = index + 4;
+                let quote = html[start..]
+                    .chars()
+                    .next()
+                    .filter(|c| matches!(c, '"' | '\''))?;
+                let end = html[start + 1..].find(quote)? + start + 1;
+                Some((start + 1, &html[start + 1..end]))
+            })
+            .collect()
+    }
+
+    fn options() -> Options {
+        Options::ENABLE_TABLES
+            | Options::ENABLE_FOOTNOTES
+            | Options::ENABLE_STRIKETHROUGH
+            | Options::ENABLE_TASKLISTS
+    }
+
+    /// 解析front matter中常见的几种日期写法, 没有时区信息的按UTC处理
+    pub fn parse_date(&self, value: &str) -> Result<DateTime<Utc>, MarkdownError> {
+        let value = value.trim();
+        if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+            return Ok(datetime.with_timezone(&Utc));
+        }
+        // Jekyll的`2019-01-02 03:04:05 +0800`
+        for format in ["%Y-%m-%d %H:%M:%S %z", "%Y-%m-%d %H:%M %z"] {
+            if let Ok(datetime) = DateTime::parse_from_str(value, format) {
+                return Ok(datetime.with_timezone(&Utc));
+            }
+        }
+        for format in [
+            "%Y-%m-%d %H:%M:%S",
+            "%Y-%m-%dT%H:%M:%S",
+            "%Y-%m-%d %H:%M",
+            "%Y/%m/%d %H:%M:%S",
+        ] {
+            if let Ok(datetime) = NaiveDateTime::parse_from_str(value, format) {
+                return Ok(datetime.and_utc());
+            }
+        }
+        for format in ["%Y-%m-%d", "%Y/%m/%d"] {
+            if let Ok(date) = NaiveDate::parse_from_str(value, format) {
+                return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc());
+            }
+        }
+        Err(MarkdownError::Date(value.to_string()))
+    }
+}
+
+/// 由标题生成url中使用的slug, 中文会被转写为不带声调的拼音
+pub fn slugify(title: &str) -> String {
+    let slug = slug::slugify(title);
+    let slug = slug[..slug.len().min(200)].trim_matches('-');
+    if slug.is_empty() {
+        "post".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// 将文章的旧地址统一为以`/`开头, 不以`/`结尾的路径
+/// - 完整的url只保留路径部分, 去掉查询参数和`#`之后的部分
+/// - 百分号编码的字符被解码, 结尾的`index.html`被去掉
+/// - 首页不能作为文章的地址, 返回空
+pub fn normalize_path(path: &str) -> Option<String> {
+    let path = path.trim();
+    let path = match path.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("/", |index| &rest[index..]),
+        None => path,
+    };
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let path = percent_decode(path);
+    let path = path
+        .strip_suffix("index.html")
+        .or_else(|| path.strip_suffix("index.htm"))
+        .unwrap_or(&path);
+    let path = path.trim_matches('/');
+    if path.is_empty() {
+        None
+    } else {
+        Some(format!("/{}", path))
+    }
+}
+
+/// 解码`%xx`, 无效的编码保持原样
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// 找出正文中以`/asset/{id}`引用的资源, 包括markdown链接, 图片和html标签中的地址,
+/// 结果按id排序并去重
+pub fn asset_ids(content: &str) -> Vec<i32> {
+    const PREFIX: &str = "/asset/";
+    let mut ids: Vec<i32> = content
+        .match_indices(PREFIX)
+        .filter_map(|(index, _)| {
+            let rest = &content[index + PREFIX.len()..];
+            let end = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            // `/asset/12abc`这样的地址不是资源
+            let boundary = rest[end..]
+                .chars()
+                .next()
+                .is_none_or(|c| !c.is_alphanumeric() && c != '_' && c != '-');
+            rest[..end].parse().ok().filter(|_| boundary)
+        })
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+/// 去掉标签两端的空白以及空的和重复的标签, 保持标签原来的顺序
+pub fn clean_tags(tags: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    tags.into_iter()
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty() && seen.insert(tag.clone()))
+        .collect()
+}
+
+/// 转义html中的特殊字符
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        push_escaped(&mut escaped, c);
+    }
+    escaped
+}
+
+fn push_escaped(html: &mut String, c: char) {
+    match c {
+        '&' => html.push_str("&amp;"),
+        '<' => html.push_str("&lt;"),
+        '>' => html.push_str("&gt;"),
+        '"' => html.push_str("&quot;"),
+        '\'' => html.push_str("&#39;"),
+        _ => html.push(c),
+    }
+}
+
+/// 将带有高亮标记的纯文本摘要转为html, 先转义正文再将标记替换为`<mark>`标签,
+/// 不成对的标记被丢弃
+pub fn highlight_snippet(text: &str) -> String {
+    let mut html = String::with_capacity(text.len());
+    let mut open = false;
+    for c in text.chars() {
+        match c {
+            HIGHLIGHT_START if !open => {
+                html.push_str("<mark>");
+                open = true;
+            }
+            HIGHLIGHT_END if open => {
+                html.push_str("</mark>");
+                open = false;
+            }
+            HIGHLIGHT_START | HIGHLIGHT_END => {}
+            _ => push_escaped(&mut html, c),
+        }
+    }
+    if open {
+        html.push_str("</mark>");
+    }
+    html
+}
+
+/// 是否为中日韩文字, 这些文字之间没有空格分隔
+pub fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}'
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{ac00}'..='\u{d7af}'
+        | '\u{f900}'..='\u{faff}'
+        | '\u{20000}'..='\u{2fa1f}')
+}
+
+/// 将分词结果重新拼接为文本, 只在两个相邻的中日韩词语之间插入空格,
+/// 使postgres的解析器能够识别出词语边界
+pub fn join_segments<'a>(segments: impl IntoIterator<Item = &'a str>) -> String {
+    let mut text = String::new();
+    for segment in segments {
+        let boundary = text.chars().next_back().is_some_and(is_cjk)
+            && segment.chars().next().is_some_and(is_cjk);
+        if boundary {
+            text.push(' ');
+        }
+        text.push_str(segment);
+    }
+    text
+}
+
+/// 将连续的空白合并为一个空格, 两个中日韩文字之间的换行直接去掉
+pub fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    for word in text.split_whitespace() {
+        let boundary = collapsed.chars().next_back().is_some_and(is_cjk)
+            && word.chars().next().is_some_and(is_cjk);
+        if !collapsed.is_empty() && !boundary {
+            collapsed.push(' ');
+        }
+        collapsed.push_str(word);
+    }
+    collapsed
+}
+
+/// [`join_segments`]的逆操作, 去掉两个中日韩文字之间的空格, 跳过高亮标记
+pub fn strip_segment_spaces(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let neighbour = |mut index: usize, step: isize| -> Option<char> {
+        loop {
+            index = index.checked_add_signed(step)?;
+            let c = *chars.get(index)?;
+            if c != HIGHLIGHT_START && c != HIGHLIGHT_END {
+                return Some(c);
+            }
+        }
+    };
+    chars
+        .iter()
+        .enumerate()
+        .filter(|(index, c)| {
+            **c != ' '
+                || !(neighbour(*index, -1).is_some_and(is_cjk)
+                    && neighbour(*index, 1).is_some_and(is_cjk))
+        })
+        .map(|(_, c)| *c)
+        .collect()
+}
+
+impl TryFrom<RawFrontMatter> for FrontMatter {
+    type Error = MarkdownError;
+    fn try_from(value: RawFrontMatter) -> Result<Self, Self::Error> {
+        let parse_date =
+            |date: Option<String>| date.map(|date| MARKDOWN_UTIL.parse_date(&date)).transpose();
+        let tags = value.tags.map(|tags| match tags {
+            RawTags::List(tags) => clean_tags(tags),
+            RawTags::Joined(tags) => clean_tags(tags.split(',').map(str::to_string)),
+        });
+        Ok(Self {
+            title: value.title.map(|title| title.trim().to_string()),
+            slug: value.slug.map(|slug| slugify(&slug)),
+            tags,
+            date: parse_date(value.date)?,
+            updated: parse_date(value.updated)?,
+            summary: value.summary,
+            status: value
+                .status
+                .or_else(|| value.draft.then(|| "draft".to_string())),
+            publish_at: parse_date(value.publish_at)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlight_snippet_escapes_html() {
+        let text = format!(
+            "<script>alert(1)</script> {}关键词{} & \"x\"",
+            HIGHLIGHT_START, HIGHLIGHT_END
+        );
+        assert_eq!(
+            highlight_snippet(&text),
+            "&lt;script&gt;alert(1)&lt;/script&gt; <mark>关键词</mark> &amp; &quot;x&quot;"
+        );
+    }
+
+    #[test]
+    fn highlight_snippet_drops_unpaired_markers() {
+        let text = format!("{}a{}b{}c", HIGHLIGHT_END, HIGHLIGHT_START, HIGHLIGHT_START);
+        assert_eq!(highlight_snippet(&text), "a<mark>bc</mark>");
+    }
+
+    #[test]
+    fn strip_segment_spaces_skips_markers() {
+        let text = format!(
+            "中文 {}分词{} 结果 and english",
+            HIGHLIGHT_START, HIGHLIGHT_END
+        );
+        assert_eq!(
+            strip_segment_spaces(&text),
+            format!(
+                "中文{}分词{}结果 and english",
+                HIGHLIGHT_START, HIGHLIGHT_END
+            )
+        );
+    }
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn parse_yaml_front_matter() {
+        let content = "---\ntitle: \" 标题 \"\ntags: [a, a, b]\ndate: 2024-01-02 03:04:05\ndraft: true\n---\n正文\n";
+        let parsed = MARKDOWN_UTIL.parse(content.as_bytes()).unwrap();
+        let front_matter = parsed.front_matter;
+        assert_eq!(front_matter.title.as_deref(), Some("标题"));
+        assert_eq!(
+            front_matter.tags,
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(front_matter.date, Some(utc("2024-01-02T03:04:05Z")));
+        assert_eq!(front_matter.status.as_deref(), Some("draft"));
+        assert_eq!(parsed.body, "正文\n");
+    }
+
+    #[test]
+    fn tags_keep_order_without_repeats() {
+        for tags in ["[b, a, b, \" a \", \"\", c]", "\"b, a,, b , a, c\""] {
+            let content = format!("---\ntags: {}\n---\n", tags);
+            let front_matter = MARKDOWN_UTIL
+                .parse(content.as_bytes())
+                .unwrap()
+                .front_matter;
+            assert_eq!(front_matter.tags.unwrap(), ["b", "a", "c"]);
+        }
+    }
+
+    #[test]
+    fn parse_toml_front_matter() {
+        let content = "+++\r\ntitle = \"标题\"\r\ntags = \"a, b,\"\r\ndate = 2024-01-02T03:04:05+08:00\r\n+++\r\n正文";
+        let parsed = MARKDOWN_UTIL.parse(content.as_bytes()).unwrap();
+        let front_matter = parsed.front_matter;
+        assert_eq!(front_matter.title.as_deref(), Some("标题"));
+        assert_eq!(
+            front_matter.tags,
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(front_matter.date, Some(utc("2024-01-01T19:04:05Z")));
+        assert_eq!(parsed.body, "正文");
+    }
+
+    #[test]
+    fn parse_skips_bom() {
+        let content = "\u{feff}---\ntitle: 标题\n---\n正文";
+        let parsed = MARKDOWN_UTIL.parse(content.as_bytes()).unwrap();
+        assert_eq!(parsed.front_matter.title.as_deref(), Some("标题"));
+        assert_eq!(parsed.body, "正文");
+    }
+
+    #[test]
+    fn parse_without_front_matter() {
+        let parsed = MARKDOWN_UTIL.parse("# 标题\n---\n正文".as_bytes()).unwrap();
+        assert!(parsed.front_matter.title.is_none());
+        assert_eq!(parsed.body, "# 标题\n---\n正文");
+    }
+
+    #[test]
+    fn parse_rejects_unclosed_front_matter() {
+        let result = MARKDOWN_UTIL.parse("---\ntitle: 标题\n----\n正文".as_bytes());
+        assert!(matches!(result, Err(MarkdownError::Unclosed)));
+    }
+
+    #[test]
+    fn parse_rejects_invalid_utf8() {
+        let result = MARKDOWN_UTIL.parse(&[0xFF, 0xFE, 0x00]);
+        assert!(matches!(result, Err(MarkdownError::Encoding)));
+    }
+
+    #[test]
+    fn parse_date_formats() {
+        let expected = utc("2019-01-02T03:04:05Z");
+        for value in [
+            "2019-01-02T03:04:05Z",
+            "2019-01-02T11:04:05+08:00",
+            "2019-01-02 11:04:05 +0800",
+            "2019-01-02 03:04:05",
+            "2019-01-02T03:04:05",
+            "2019/01/02 03:04:05",
+            " 2019-01-02 03:04:05 ",
+        ] {
+            assert_eq!(
+                MARKDOWN_UTIL.parse_date(value).unwrap(),
+                expected,
+                "{}",
+                value
+            );
+        }
+        assert_eq!(
+            MARKDOWN_UTIL.parse_date("2019/01/02").unwrap(),
+            utc("2019-01-02T00:00:00Z")
+        );
+        assert!(matches!(
+            MARKDOWN_UTIL.parse_date("01/02/2019"),
+            Err(MarkdownError::Date(_))
+        ));
+    }
+
+    #[test]
+    fn slugify_transliterates_titles() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("你好 Rust"), "ni-hao-rust");
+        assert_eq!(slugify("  --已经-是-slug--  "), "yi-jing-shi-slug");
+    }
+
+    #[test]
+    fn slugify_falls_back_when_empty() {
+        assert_eq!(slugify(""), "post");
+        assert_eq!(slugify("!!! ???"), "post");
+    }
+
+    #[test]
+    fn slugify_limits_length() {
+        // 截断在200个字符处, 结尾的`-`被去掉
+        let title = format!("{} bcd", "a".repeat(199));
+        assert_eq!(slugify(&title), "a".repeat(199));
+        assert!(slugify(&"字".repeat(300)).len() <= 200);
+    }
+
+    #[test]
+    fn find_more_allows_missing_spaces() {
+        assert_eq!(MarkdownUtil::find_more("开头\n<!-- more -->\n"), Some(7));
+        assert_eq!(MarkdownUtil::find_more("开头<!--more-->"), Some(6));
+        assert_eq!(
+            MarkdownUtil::find_more("<!-- 注释 --><!--\n  more\t-->"),
+            Some(15)
+        );
+        assert_eq!(MarkdownUtil::find_more("<!-- mores -->"), None);
+        assert_eq!(MarkdownUtil::find_more("<!-- more"), None);
+    }
+
+    #[test]
+    fn excerpt_stops_at_more() {
+        // 标题不出现在摘要中
+        let body = "# 标题\n\n第一段**加粗**\n\n<!-- more -->\n\n第二段";
+        assert_eq!(MARKDOWN_UTIL.excerpt(body, 100), "第一段加粗");
+    }
+
+    #[test]
+    fn excerpt_truncates_at_word_boundary() {
+        assert_eq!(MARKDOWN_UTIL.excerpt("short text", 20), "short text");
+        assert_eq!(MARKDOWN_UTIL.excerpt("hello wonderful world", 8), "hello…");
+        assert_eq!(
+            MARKDOWN_UTIL.excerpt("中文可以在任何位置截断", 4),
+            "中文可以…"
+        );
+        // 整段都是一个单词时强制截断
+        assert_eq!(MARKDOWN_UTIL.excerpt("abcdefghij", 4), "abcd…");
+    }
+
+    #[test]
+    fn rewrite_links_replaces_inline_reference_and_html() {
+        let body = concat!(
+            "![图](img/a.png \"标题\") [下载](doc.pdf) [![封面](img/d.png)](page.html)\n",
+            "[引用][r] ![图片引用][i]\n",
+            "\n",
+            "<img alt=\"x\" src=\"img/b.png\"> <a data-src=\"img/e.png\">\n",
+            "\n",
+            "[r]: ref.pdf\n",
+            "[i]: img/c.png\n",
+            "[unused]: img/f.png\n",
+        );
+        let rewritten = MARKDOWN_UTIL.rewrite_links(body, |url, image| {
+            (url != "doc.pdf").then(|| format!("/{}/{}", if image { "i" } else { "l" }, url))
+        });
+        assert_eq!(
+            rewritten,
+            concat!(
+                "![图](/i/img/a.png \"标题\") [下载](doc.pdf) [![封面](/i/img/d.png)](/l/page.html)\n",
+                "[引用][r] ![图片引用][i]\n",
+                "\n",
+                "<img alt=\"x\" src=\"/i/img/b.png\"> <a data-src=\"img/e.png\">\n",
+                "\n",
+                "[r]: /l/ref.pdf\n",
+                "[i]: /i/img/c.png\n",
+                "[unused]: img/f.png\n",
+            )
+        );
+    }
+
+    #[test]
+    fn rewrite_links_ignores_code() {
+        let body = "`![图](a.png)`\n\n```\n[链接](b.html)\n```\n";
+        let rewritten = MARKDOWN_UTIL.rewrite_links(body, |_, _| Some("x".to_string()));
+        assert_eq!(rewritten, body);
     }
 }
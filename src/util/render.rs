@@ -0,0 +1,274 @@
+use super::{MarkdownUtil, escape_html};
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Parser, Tag, TagEnd, html};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use syntect::html::{ClassStyle, ClassedHTMLGenerator};
+use syntect::util::LinesWithEndings;
+
+/// 代码高亮输出的css类名前缀, 前端需要提供对应的样式表
+const HIGHLIGHT_PREFIX: &str = "hl-";
+
+/// 目录中的一个标题, 锚点与渲染出的html中标题的`id`一致
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TocEntry {
+    pub level: u8,
+    pub title: String,
+    pub anchor: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TocEntry>,
+}
+
+impl TocEntry {
+    /// 将标题挂到最后一个级别更高的标题下面, 没有时作为顶层标题
+    fn insert(entries: &mut Vec<TocEntry>, entry: TocEntry) {
+        match entries.last_mut() {
+            Some(last) if last.level < entry.level => Self::insert(&mut last.children, entry),
+            _ => entries.push(entry),
+        }
+    }
+}
+
+/// 为标题生成不重复的锚点, 同一篇文章中重复的标题会添加数字后缀
+#[derive(Default)]
+struct Anchors {
+    used: HashMap<String, usize>,
+}
+
+impl Anchors {
+    fn next(&mut self, text: &str) -> String {
+        let mut base = slug::slugify(text);
+        if base.is_empty() {
+            base = "section".to_string();
+        }
+        let count = self.used.entry(base.clone()).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            base
+        } else {
+            format!("{}-{}", base, count)
+        }
+    }
+}
+
+impl MarkdownUtil {
+    /// 将markdown正文渲染为html
+    /// - 支持表格, 脚注, 任务列表和删除线
+    /// - 代码块使用带有`hl-`前缀的css类高亮
+    /// - 标题带有由标题文字生成的`id`
+    pub fn to_html(&self, body: &str) -> String {
+        let mut anchors = Anchors::default();
+        let mut events = Vec::new();
+        // 当前标题的开始事件下标和标题文字
+        let mut heading: Option<(usize, String)> = None;
+        // 当前代码块的语言和代码
+        let mut code: Option<(String, String)> = None;
+
+        for event in Parser::new_ext(body, Self::options()) {
+            match event {
+                Event::Start(Tag::Heading { .. }) => {
+                    heading = Some((events.len(), String::new()));
+                    events.push(event);
+                }
+                Event::End(TagEnd::Heading(_)) => {
+                    if let Some((index, text)) = heading.take()
+                        && let Event::Start(Tag::Heading { id, .. }) = &mut events[index]
+                    {
+                        *id = Some(anchors.next(&text).into());
+                    }
+                    events.push(event);
+                }
+                Event::Start(Tag::CodeBlock(kind)) => {
+                    let lang = match kind {
+                        CodeBlockKind::Fenced(info) => {
+                            info.split_whitespace().next().unwrap_or("").to_string()
+                        }
+                        CodeBlockKind::Indented => String::new(),
+                    };
+                    code = Some((lang, String::new()));
+                }
+                Event::End(TagEnd::CodeBlock) => {
+                    if let Some((lang, source)) = code.take() {
+                        events.push(Event::Html(self.highlight(&lang, &source).into()));
+                    }
+                }
+                Event::Text(text) if code.is_some() => {
+                    if let Some((_, source)) = code.as_mut() {
+                        source.push_str(&text);
+                    }
+                }
+                Event::Text(ref text) | Event::Code(ref text) if heading.is_some() => {
+                    if let Some((_, heading)) = heading.as_mut() {
+                        heading.push_str(text);
+                    }
+                    events.push(event);
+                }
+                _ => events.push(event),
+            }
+        }
+
+        let mut output = String::with_capacity(body.len() * 3 / 2);
+        html::push_html(&mut output, events.into_iter());
+        output
+    }
+
+    /// 由正文中的标题生成树形目录
+    pub fn toc(&self, body: &str) -> Vec<TocEntry> {
+        let mut anchors = Anchors::default();
+        let mut toc = Vec::new();
+        let mut heading: Option<(HeadingLevel, String)> = None;
+        for event in Parser::new_ext(body, Self::options()) {
+            match event {
+                Event::Start(Tag::Heading { level, .. }) => heading = Some((level, String::new())),
+                Event::End(TagEnd::Heading(_)) => {
+                    if let Some((level, text)) = heading.take() {
+                        let entry = TocEntry {
+                            level: level as u8,
+                            title: text.trim().to_string(),
+                            anchor: anchors.next(&text),
+                            children: Vec::new(),
+                        };
+                        TocEntry::insert(&mut toc, entry);
+                    }
+                }
+                Event::Text(text) | Event::Code(text) => {
+                    if let Some((_, heading)) = heading.as_mut() {
+                        heading.push_str(&text);
+                    }
+                }
+                _ => {}
+            }
+        }
+        toc
+    }
+
+    fn highlight(&self, lang: &str, source: &str) -> String {
+        let syntax = self
+            .syntax_set
+            .find_syntax_by_token(lang)
+            .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text());
+        let mut generator = ClassedHTMLGenerator::new_with_class_style(
+            syntax,
+            &self.syntax_set,
+            ClassStyle::SpacedPrefixed {
+                prefix: HIGHLIGHT_PREFIX,
+            },
+        );
+        let highlighted = LinesWithEndings::from(source)
+            .try_for_each(|line| generator.parse_html_for_line_which_includes_newline(line));
+        let code = match highlighted {
+            Ok(()) => generator.finalize(),
+            Err(e) => {
+                tracing::warn!("代码高亮失败, 使用原始代码: {}", e);
+                escape_html(source)
+            }
+        };
+        let lang: String = lang
+            .chars()
+            .filter(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '+' | '#'))
+            .collect();
+        format!(
+            "<pre class=\"{}code\" data-lang=\"{}\"><code>{}</code></pre>\n",
+            HIGHLIGHT_PREFIX, lang, code
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::{MARKDOWN_UTIL, TocEntry};
+
+    const BODY: &str =
+        "# Intro\n\n## Setup\n\n### Install `cargo`\n\n## Setup\n\n# Intro\n\n## 用法\n";
+
+    /// html中所有标题的`id`
+    fn heading_ids(html: &str) -> Vec<&str> {
+        html.split(" id=\"")
+            .skip(1)
+            .filter_map(|rest| rest.split('"').next())
+            .collect()
+    }
+
+    fn anchors(entries: &[TocEntry]) -> Vec<String> {
+        entries
+            .iter()
+            .flat_map(|entry| std::iter::once(entry.anchor.clone()).chain(anchors(&entry.children)))
+            .collect()
+    }
+
+    #[test]
+    fn duplicate_headings_get_suffixes() {
+        let html = MARKDOWN_UTIL.to_html(BODY);
+        assert_eq!(
+            heading_ids(&html),
+            [
+                "intro",
+                "setup",
+                "install-cargo",
+                "setup-2",
+                "intro-2",
+                "yong-fa"
+            ]
+        );
+        assert!(html.contains("<h3 id=\"install-cargo\">Install <code>cargo</code></h3>"));
+    }
+
+    #[test]
+    fn toc_anchors_match_html_ids() {
+        let html = MARKDOWN_UTIL.to_html(BODY);
+        let toc = MARKDOWN_UTIL.toc(BODY);
+        assert_eq!(anchors(&toc), heading_ids(&html));
+    }
+
+    #[test]
+    fn toc_is_nested_by_level() {
+        let toc = MARKDOWN_UTIL.toc(BODY);
+        let shape: Vec<_> = toc
+            .iter()
+            .map(|entry| {
+                let children: Vec<_> = entry
+                    .children
+                    .iter()
+                    .map(|child| (child.level, child.title.as_str(), child.children.len()))
+                    .collect();
+                (entry.level, entry.title.as_str(), children)
+            })
+            .collect();
+        assert_eq!(
+            shape,
+            [
+                (1, "Intro", vec![(2, "Setup", 1), (2, "Setup", 0)]),
+                (1, "Intro", vec![(2, "用法", 0)]),
+            ]
+        );
+        assert_eq!(toc[0].children[0].children[0].title, "Install cargo");
+
+        // 没有上级标题的低级别标题作为顶层标题
+        let toc = MARKDOWN_UTIL.toc("### a\n\n# b\n\n### c\n");
+        assert_eq!(anchors(&toc), ["a", "b", "c"]);
+        assert_eq!(toc.len(), 2);
+        assert!(MARKDOWN_UTIL.toc("正文\n").is_empty());
+    }
+
+    #[test]
+    fn unknown_language_is_escaped_plain_text() {
+        let html = MARKDOWN_UTIL.to_html("```nosuchlang\n<b>&</b>\n```\n");
+        assert!(html.starts_with("<pre class=\"hl-code\" data-lang=\"nosuchlang\"><code>"));
+        assert!(html.contains("&lt;b&gt;&amp;&lt;/b&gt;"));
+        assert!(!html.contains("<b>"));
+
+        let html = MARKDOWN_UTIL.to_html("```rust\nfn main() {}\n```\n");
+        assert!(html.contains("data-lang=\"rust\""));
+        assert!(html.contains("class=\"hl-"));
+    }
+
+    #[test]
+    fn data_lang_is_sanitized() {
+        let html = MARKDOWN_UTIL.to_html("```\"><script>alert(1)</script>\nx\n```\n");
+        assert!(html.contains("data-lang=\"scriptalert1script\""));
+        assert!(!html.contains("<script>"));
+
+        let html = MARKDOWN_UTIL.to_html("```c++\nx\n```\n```c#\nx\n```\n");
+        assert!(html.contains("data-lang=\"c++\""));
+        assert!(html.contains("data-lang=\"c#\""));
+    }
+}